@@ -24,28 +24,33 @@ SOFTWARE.
 
 mod utils;
 
+use crate::utils::canvas::file::ImageCanvas;
 use crate::utils::canvas::none::NoCanvas;
 use crate::utils::canvas::sdl::WrapperCanvas;
 use crate::utils::canvas::DrawCanvas;
 use crate::utils::monitor::ProgressionMonitor;
 use crate::utils::monitor::{NoMonitor, TermMonitor};
-use crate::utils::result::{AppError, VoidAppResult};
+use crate::utils::result::{AppError, AppResult, VoidAppResult};
 use log::info;
+use raytracer::colors::{Color, ToneMapping, TransferFunction};
 use raytracer::ray_algorithm::strategy::{
+    AdaptiveAntiAliasingRenderStrategy, DepthRenderStrategy, NormalRenderStrategy, NormalSpace,
     RandomAntiAliasingRenderStrategy, StandardRenderStrategy,
 };
 use raytracer::ray_algorithm::AnyPixelRenderStrategy;
-use raytracer::renderer::{render_scene_with_finally, Pixel, RenderConfiguration};
-use raytracer::result::Result;
+use raytracer::renderer::{
+    render_scene_layers, render_scene_with_finally, render_scene_with_report, trace_pixel,
+    NanCheck, Pixel, RenderConfiguration,
+};
+use raytracer::result::{RaytracerError, Result};
 use sdl2::event::{Event, WindowEvent};
 use sdl2::keyboard::Keycode;
 use sdl2::pixels::PixelFormatEnum;
 use std::time::{Duration, Instant};
 
 use raytracer::scene::Scene;
+use raytracer::vector::Vec3;
 use simplelog::{Config, LevelFilter, TermLogger, TerminalMode};
-use std::fs;
-use std::str::FromStr;
 
 const APP_AUTHOR: &str = "Vincent Hiribarren";
 const APP_NAME: &str = "raytracer-rust";
@@ -55,15 +60,40 @@ const APP_VERSION: &str = env!("CARGO_PKG_VERSION");
 const ARG_FILE_INPUT: &str = "INPUT_FILE";
 const ARG_NO_STATUS: &str = "no-status";
 const ARG_NO_GUI: &str = "no-gui";
+const ARG_OUTPUT: &str = "output";
+const ARG_STATS_JSON: &str = "stats-json";
 const ARG_NO_PROGRESSIVE: &str = "no-progressive";
 const ARG_NO_PARALLEL: &str = "no-parallel";
+const ARG_COARSE_FIRST: &str = "coarse-first";
 const ARG_STRATEGY_RANDOM: &str = "strategy-random";
+const ARG_STRATEGY_ADAPTIVE: &str = "strategy-adaptive";
+const ARG_DEBUG: &str = "debug";
+const ARG_LIGHT: &str = "light";
+const ARG_NORMAL_SPACE: &str = "space";
+const DEBUG_SHADOWMAP: &str = "shadowmap";
+const DEBUG_NORMALS: &str = "normals";
+const SPACE_WORLD: &str = "world";
+const SPACE_VIEW: &str = "view";
 const ARG_WIDTH: &str = "width";
 const ARG_HEIGHT: &str = "height";
 const ARG_VERBOSE: &str = "verbose";
+const ARG_DUMP_SCENE: &str = "dump-scene";
+const ARG_DIRECT_ONLY: &str = "direct-only";
+const ARG_CLAY: &str = "clay";
+const ARG_EXPOSURE: &str = "exposure";
+const ARG_GAMUT_CLAMP: &str = "gamut-clamp";
+const ARG_REINHARD: &str = "reinhard";
+const ARG_LINEAR_OUTPUT: &str = "linear-output";
+const ARG_PIXEL_ASPECT_RATIO: &str = "pixel-aspect-ratio";
+const ARG_TRACE_PIXEL: &str = "trace-pixel";
+const ARG_LAYERS_BY_DEPTH: &str = "layers-by-depth";
+const ARG_CAMERA_EYE: &str = "camera-eye";
+const ARG_CAMERA_LOOK_AT: &str = "camera-look-at";
 
 const WINDOW_WIDTH: u32 = 800;
 const CANVAS_WIDTH: u32 = 1024;
+const SHADOWMAP_DEFAULT_DEPTH: f64 = 100.0;
+const ADAPTIVE_VARIANCE_THRESHOLD: f64 = 0.0025;
 const SDL_WINDOW_CLEAR_COLOR: sdl2::pixels::Color = sdl2::pixels::Color {
     r: 77,
     g: 77,
@@ -88,6 +118,53 @@ fn main() -> VoidAppResult {
                 .multiple(true)
                 .help("Verbosity of log messages (one for Debug level, two for Trace level)"),
         )
+        .arg(
+            clap::Arg::with_name(ARG_DUMP_SCENE)
+                .long("dump-scene")
+                .help("Print a summary of the parsed scene and exit without rendering."),
+        )
+        .arg(
+            clap::Arg::with_name(ARG_TRACE_PIXEL)
+                .long("trace-pixel")
+                .value_names(&["X", "Y"])
+                .number_of_values(2)
+                .help("Print the shading decisions for a single pixel and exit without rendering."),
+        )
+        .arg(
+            clap::Arg::with_name(ARG_LAYERS_BY_DEPTH)
+                .long("layers-by-depth")
+                .value_name("DEPTH")
+                .help(
+                    "Only show the light sent back at a recursion depth, 0 being direct shading.",
+                ),
+        )
+        .arg(
+            clap::Arg::with_name(ARG_CAMERA_EYE)
+                .long("camera-eye")
+                .value_name("X,Y,Z")
+                .allow_hyphen_values(true)
+                .requires(ARG_CAMERA_LOOK_AT)
+                .help("Move the perspective camera eye, with --camera-look-at."),
+        )
+        .arg(
+            clap::Arg::with_name(ARG_CAMERA_LOOK_AT)
+                .long("camera-look-at")
+                .value_name("X,Y,Z")
+                .allow_hyphen_values(true)
+                .requires(ARG_CAMERA_EYE)
+                .help("Point the perspective camera at a position, with --camera-eye."),
+        )
+        .arg(
+            clap::Arg::with_name(ARG_DIRECT_ONLY)
+                .long("direct-only")
+                .help("Render only direct lighting, without reflection or refraction (quicker)."),
+        )
+        .arg(
+            clap::Arg::with_name(ARG_CLAY)
+                .long("clay")
+                .value_name("COLOR")
+                .help("Shade all objects as a matte diffuse of COLOR, a name or R,G,B."),
+        )
         .arg(
             clap::Arg::with_name(ARG_NO_STATUS)
                 .long("no-status")
@@ -98,6 +175,20 @@ fn main() -> VoidAppResult {
                 .long("no-gui")
                 .help("Do not display the result of the rendering."),
         )
+        .arg(
+            clap::Arg::with_name(ARG_OUTPUT)
+                .short("o")
+                .long("output")
+                .value_name("FILE")
+                .help("Save the rendering in a PNG file, before any display."),
+        )
+        .arg(
+            clap::Arg::with_name(ARG_STATS_JSON)
+                .long("stats-json")
+                .value_name("FILE")
+                .conflicts_with(ARG_LAYERS_BY_DEPTH)
+                .help("Write render metadata and statistics in a JSON file."),
+        )
         .arg(
             clap::Arg::with_name(ARG_NO_PROGRESSIVE)
                 .long("no-progressive")
@@ -109,6 +200,12 @@ fn main() -> VoidAppResult {
                 .long("no-parallel")
                 .help("Do not use multithreading for parallel computation (slower)."),
         )
+        .arg(
+            clap::Arg::with_name(ARG_COARSE_FIRST)
+                .long("coarse-first")
+                .conflicts_with(ARG_NO_PARALLEL)
+                .help("Compute a sparse grid of pixels first, for a quicker rough preview."),
+        )
         .arg(
             clap::Arg::with_name(ARG_WIDTH)
                 .short("w")
@@ -125,12 +222,73 @@ fn main() -> VoidAppResult {
                 .conflicts_with(ARG_WIDTH)
                 .help("Canvas height."),
         )
+        .arg(
+            clap::Arg::with_name(ARG_EXPOSURE)
+                .long("exposure")
+                .value_name("EV")
+                .allow_hyphen_values(true)
+                .help("Exposure value, colors are multiplied by 2^EV, default: 0."),
+        )
+        .arg(
+            clap::Arg::with_name(ARG_PIXEL_ASPECT_RATIO)
+                .long("pixel-aspect-ratio")
+                .value_name("RATIO")
+                .help("Width divided by height of a displayed pixel, default: 1."),
+        )
+        .arg(
+            clap::Arg::with_name(ARG_GAMUT_CLAMP)
+                .long("gamut-clamp")
+                .help("Desaturate out of gamut colors instead of clamping their components."),
+        )
+        .arg(
+            clap::Arg::with_name(ARG_REINHARD)
+                .long("reinhard")
+                .help("Compress bright colors with Reinhard tone mapping instead of clamping them."),
+        )
+        .arg(
+            clap::Arg::with_name(ARG_LINEAR_OUTPUT)
+                .long("linear-output")
+                .help("Display linear colors, without sRGB encoding."),
+        )
         .arg(
             clap::Arg::with_name(ARG_STRATEGY_RANDOM)
                 .long("strategy-random")
                 .value_name("RAY_COUNT")
                 .help("Average of RAY_COUNT random rays sent."),
         )
+        .arg(
+            clap::Arg::with_name(ARG_STRATEGY_ADAPTIVE)
+                .long("strategy-adaptive")
+                .value_name("MAX_RAYS")
+                .conflicts_with(ARG_STRATEGY_RANDOM)
+                .help("Up to MAX_RAYS rays sent, more rays being used where colors vary."),
+        )
+        .arg(
+            clap::Arg::with_name(ARG_DEBUG)
+                .long("debug")
+                .value_name("MODE")
+                .possible_values(&[DEBUG_SHADOWMAP, DEBUG_NORMALS])
+                .conflicts_with_all(&[ARG_STRATEGY_RANDOM, ARG_STRATEGY_ADAPTIVE])
+                .help(
+                    "Debug rendering: 'shadowmap' shows depth as seen from a light, \
+                     'normals' shows surface normals as colors.",
+                ),
+        )
+        .arg(
+            clap::Arg::with_name(ARG_NORMAL_SPACE)
+                .long("space")
+                .value_name("SPACE")
+                .possible_values(&[SPACE_WORLD, SPACE_VIEW])
+                .requires(ARG_DEBUG)
+                .help("Space of the normals debug mode, 'view' being the camera basis, default: world."),
+        )
+        .arg(
+            clap::Arg::with_name(ARG_LIGHT)
+                .long("light")
+                .value_name("INDEX")
+                .requires(ARG_DEBUG)
+                .help("Index of the light used by the shadowmap debug mode, default: 0."),
+        )
         .get_matches();
 
     // Log level
@@ -143,13 +301,90 @@ fn main() -> VoidAppResult {
         .expect("Error while initializing logger");
 
     // Generate scene to render
-    let scene = {
-        let scene_content = fs::read_to_string(matches.value_of(ARG_FILE_INPUT).unwrap())?;
-        Scene::from_str(&scene_content)?
+    let mut scene = Scene::from_file(matches.value_of(ARG_FILE_INPUT).unwrap())?;
+    scene.validate();
+
+    // Re-frame the scene without editing its description
+    if let (Some(eye), Some(look_at)) = (
+        matches.value_of(ARG_CAMERA_EYE),
+        matches.value_of(ARG_CAMERA_LOOK_AT),
+    ) {
+        let parse_position = |value: &str| {
+            value.parse::<Vec3>().map_err(|e| {
+                AppError::BadArgument(format!("Error when parsing camera position: {}", e))
+            })
+        };
+        scene.camera = scene
+            .camera
+            .reframed(parse_position(eye)?, parse_position(look_at)?)
+            .ok_or_else(|| {
+                AppError::BadArgument("Only a perspective camera can be moved".to_string())
+            })?;
+    }
+
+    // Only display how the scene was understood
+    if matches.is_present(ARG_DUMP_SCENE) {
+        print!("{}", scene.describe());
+        return Ok(());
+    }
+
+    // Skip secondary rays, to measure their cost
+    if matches.is_present(ARG_DIRECT_ONLY) {
+        scene.config.direct_only = true;
+    }
+
+    // Replace all materials, to examine shapes and lighting
+    if let Some(color) = matches.value_of(ARG_CLAY) {
+        let color = color
+            .parse::<Color>()
+            .map_err(|e| AppError::BadArgument(format!("Error when parsing clay color: {}", e)))?;
+        scene.config.clay_render = Some(color);
+    }
+
+    // Replace the camera by a view from a light, with a depth rendering
+    let mut debug_strategy: Option<Box<dyn AnyPixelRenderStrategy>> = None;
+    if matches.value_of(ARG_DEBUG) == Some(DEBUG_SHADOWMAP) {
+        let light_index = match matches.value_of(ARG_LIGHT) {
+            Some(index) => index.parse::<usize>().map_err(|e| {
+                AppError::BadArgument(format!("Error when parsing light index: {}", e))
+            })?,
+            None => 0,
+        };
+        let light_camera = scene
+            .light_camera(light_index)
+            .ok_or_else(|| AppError::BadArgument(format!("No light with index {}", light_index)))?;
+        let light_source = scene.lights[light_index].source();
+        let max_depth = scene
+            .bounding_sphere()
+            .map_or(SHADOWMAP_DEFAULT_DEPTH, |(center, radius)| {
+                light_source.distance(center) + radius
+            });
+        scene.camera = Box::new(light_camera);
+        debug_strategy = Some(Box::new(DepthRenderStrategy { max_depth }));
+    }
+
+    // Normals encoded as colors, in world space or in the camera basis
+    if matches.value_of(ARG_DEBUG) == Some(DEBUG_NORMALS) {
+        let space = match matches.value_of(ARG_NORMAL_SPACE) {
+            Some(SPACE_VIEW) => NormalSpace::View,
+            _ => NormalSpace::World,
+        };
+        debug_strategy = Some(Box::new(NormalRenderStrategy { space }));
+    }
+
+    // Pixel aspect ratio
+    let pixel_aspect_ratio = match matches.value_of(ARG_PIXEL_ASPECT_RATIO) {
+        Some(ratio) => ratio.parse::<f64>().map_err(|e| {
+            AppError::BadArgument(format!(
+                "Error when parsing pixel aspect ratio value: {}",
+                e
+            ))
+        })?,
+        None => 1.0,
     };
 
-    // Camera ratio
-    let camera_ratio = scene.camera.size_ratio();
+    // Camera ratio, in pixels
+    let camera_ratio = scene.camera.size_ratio() / pixel_aspect_ratio;
     let (canvas_width, canvas_height) =
         match (matches.value_of(ARG_WIDTH), matches.value_of(ARG_HEIGHT)) {
             (Some(_), Some(_)) => unreachable!(),
@@ -171,22 +406,40 @@ fn main() -> VoidAppResult {
             }
         };
 
-    // Ray casting strategy
-    let render_strategy: Box<dyn AnyPixelRenderStrategy> =
+    // Ray casting strategy, with its name and rays per pixel for the render report
+    let (render_strategy, strategy_name, samples): (Box<dyn AnyPixelRenderStrategy>, _, _) =
         if let Some(strategy) = matches.value_of(ARG_STRATEGY_RANDOM) {
             let rays_per_pixel: u32 = strategy.parse().map_err(|e| {
                 AppError::BadArgument(format!("Error when parsing strategy value: {}", e))
             })?;
-            Box::new(RandomAntiAliasingRenderStrategy { rays_per_pixel })
+            let strategy = RandomAntiAliasingRenderStrategy {
+                rays_per_pixel,
+                seed: None,
+            };
+            (Box::new(strategy), "random", rays_per_pixel)
+        } else if let Some(strategy) = matches.value_of(ARG_STRATEGY_ADAPTIVE) {
+            let max_rays: u32 = strategy.parse().map_err(|e| {
+                AppError::BadArgument(format!("Error when parsing strategy value: {}", e))
+            })?;
+            let strategy = AdaptiveAntiAliasingRenderStrategy {
+                max_rays,
+                variance_threshold: ADAPTIVE_VARIANCE_THRESHOLD,
+            };
+            (Box::new(strategy), "adaptive", max_rays)
         } else {
-            Box::new(StandardRenderStrategy)
+            (Box::new(StandardRenderStrategy), "standard", 1)
         };
+    let (render_strategy, strategy_name, samples) = match debug_strategy {
+        Some(strategy) => (strategy, matches.value_of(ARG_DEBUG).unwrap_or("debug"), 1),
+        None => (render_strategy, strategy_name, samples),
+    };
 
-    // Terminal progress bar
-    let monitor: Box<dyn ProgressionMonitor> = if matches.is_present(ARG_NO_STATUS) {
-        Box::new(NoMonitor)
-    } else {
-        Box::new(TermMonitor::new((canvas_height * canvas_width) as u64))
+    // Exposure
+    let exposure = match matches.value_of(ARG_EXPOSURE) {
+        Some(ev) => ev.parse::<f64>().map_err(|e| {
+            AppError::BadArgument(format!("Error when parsing exposure value: {}", e))
+        })?,
+        None => 0.0,
     };
 
     // Build options
@@ -194,29 +447,132 @@ fn main() -> VoidAppResult {
         canvas_width,
         canvas_height,
         render_strategy,
+        exposure,
+        tone_mapping: if matches.is_present(ARG_REINHARD) {
+            ToneMapping::Reinhard
+        } else {
+            ToneMapping::Clamp
+        },
+        gamut_clamp: matches.is_present(ARG_GAMUT_CLAMP),
+        small_object_culling: None,
+        pixel_aspect_ratio,
+        high_quality_region: None,
+        nan_check: if cfg!(debug_assertions) {
+            NanCheck::Panic
+        } else {
+            NanCheck::Disabled
+        },
+        coarse_to_fine: matches.is_present(ARG_COARSE_FIRST),
+        transfer_function: if matches.is_present(ARG_LINEAR_OUTPUT) {
+            TransferFunction::Linear
+        } else {
+            TransferFunction::Srgb
+        },
     };
+    let transfer_function = config.transfer_function;
 
     info!("Camera ratio; {:.2}", camera_ratio);
     info!("Canvas size: {}x{}", canvas_width, canvas_height);
 
-    // Sequential or parallel computation
-    let render_iter =
-        render_scene_with_finally(scene, config, !matches.is_present(ARG_NO_PARALLEL), || {
-            monitor.clean()
+    // Debug of a single pixel
+    if let Some(mut coords) = matches.values_of(ARG_TRACE_PIXEL) {
+        let mut parse_coord = || {
+            coords.next().unwrap().parse::<u32>().map_err(|e| {
+                AppError::BadArgument(format!("Error when parsing traced pixel: {}", e))
+            })
+        };
+        let (x, y) = (parse_coord()?, parse_coord()?);
+        let trace = trace_pixel(scene, config, x, y).map_err(|e| match e {
+            RaytracerError::PixelOutOfCanvas(..) => AppError::BadArgument(e.to_string()),
+            e => e.into(),
         })?;
+        print!("{}", trace.describe());
+        return Ok(());
+    }
+
+    // Terminal progress bar
+    let monitor: Box<dyn ProgressionMonitor> = if matches.is_present(ARG_NO_STATUS) {
+        Box::new(NoMonitor)
+    } else {
+        Box::new(TermMonitor::new((canvas_height * canvas_width) as u64))
+    };
+
+    // Sequential or parallel computation, or a single layer rendered beforehand
+    let render_iter: Box<dyn Iterator<Item = Result<Pixel>>> =
+        if let Some(depth) = matches.value_of(ARG_LAYERS_BY_DEPTH) {
+            let depth = depth.parse::<usize>().map_err(|e| {
+                AppError::BadArgument(format!("Error when parsing layer depth: {}", e))
+            })?;
+            let mut layers = render_scene_layers(scene, config)?;
+            if depth >= layers.len() {
+                return Err(AppError::BadArgument(format!(
+                    "Layer depth must be lower than {}",
+                    layers.len()
+                )));
+            }
+            let pixels = layers.swap_remove(depth).into_iter().enumerate();
+            Box::new(pixels.map(move |(index, color)| {
+                let index = index as u32;
+                Ok(Pixel::new(
+                    index % canvas_width,
+                    index / canvas_width,
+                    color,
+                ))
+            }))
+        } else if let Some(path) = matches.value_of(ARG_STATS_JSON) {
+            Box::new(render_scene_with_report(
+                scene,
+                config,
+                !matches.is_present(ARG_NO_PARALLEL),
+                strategy_name,
+                samples,
+                |report| {
+                    monitor.clean();
+                    std::fs::write(path, report.to_json())
+                        .map_err(|e| RaytracerError::WriteError(format!("{}: {}", path, e)))?;
+                    info!("Render report saved in {}", path);
+                    Ok(())
+                },
+            )?)
+        } else {
+            Box::new(render_scene_with_finally(
+                scene,
+                config,
+                !matches.is_present(ARG_NO_PARALLEL),
+                || monitor.clean(),
+            )?)
+        };
+
+    // Save the whole rendering first, then show the saved pixels
+    let no_monitor: Box<dyn ProgressionMonitor> = Box::new(NoMonitor);
+    let (render_iter, monitor): (Box<dyn Iterator<Item = Result<Pixel>>>, _) =
+        if let Some(path) = matches.value_of(ARG_OUTPUT) {
+            let pixels = render_to_file(
+                render_iter,
+                &monitor,
+                canvas_width,
+                canvas_height,
+                transfer_function,
+                path,
+            )?;
+            (Box::new(pixels.into_iter().map(Ok)), &no_monitor)
+        } else {
+            (render_iter, &monitor)
+        };
 
     // Launch the computation / rendering
     if matches.is_present(ARG_NO_GUI) {
-        render_no_gui(render_iter, &monitor)?;
+        render_no_gui(render_iter, monitor)?;
     } else {
         let progressive_rendering = !matches.is_present(ARG_NO_PROGRESSIVE);
         render_sdl(
             render_iter,
-            &monitor,
+            monitor,
             canvas_width,
             canvas_height,
             camera_ratio,
             progressive_rendering,
+            transfer_function,
         )?;
     }
 
@@ -236,6 +592,29 @@ fn render_no_gui<M: AsRef<dyn ProgressionMonitor>>(
     Ok(())
 }
 
+/// Write all the rendered pixels in an image file, and return them
+fn render_to_file<M: AsRef<dyn ProgressionMonitor>>(
+    render_iter: impl Iterator<Item = Result<Pixel>>,
+    monitor: M,
+    canvas_width: u32,
+    canvas_height: u32,
+    transfer_function: TransferFunction,
+    path: &str,
+) -> AppResult<Vec<Pixel>> {
+    let monitor = monitor.as_ref();
+    let mut canvas = ImageCanvas::new(canvas_width, canvas_height, transfer_function);
+    let mut pixels = Vec::with_capacity((canvas_width * canvas_height) as usize);
+    for pixel in render_iter {
+        let pixel = pixel?;
+        canvas.draw(pixel.clone())?;
+        pixels.push(pixel);
+        monitor.update();
+    }
+    canvas.save(path)?;
+    info!("Rendering saved in {}", path);
+    Ok(pixels)
+}
+
 #[allow(clippy::while_let_on_iterator)]
 #[allow(clippy::collapsible_if)]
 fn render_sdl<M: AsRef<dyn ProgressionMonitor>>(
@@ -245,6 +624,7 @@ fn render_sdl<M: AsRef<dyn ProgressionMonitor>>(
     canvas_height: u32,
     camera_ratio: f64,
     progressive_rendering: bool,
+    transfer_function: TransferFunction,
 ) -> VoidAppResult {
     let monitor = monitor.as_ref();
 
@@ -259,7 +639,7 @@ fn render_sdl<M: AsRef<dyn ProgressionMonitor>>(
 
     if !progressive_rendering {
         // We prepare immediately the result before displaying it
-        let mut wrapper_canvas = WrapperCanvas(&mut render_canvas);
+        let mut wrapper_canvas = WrapperCanvas(&mut render_canvas, transfer_function);
         while let Some(pixel) = render_iter.next() {
             wrapper_canvas.draw(pixel?)?;
             monitor.update();
@@ -319,7 +699,7 @@ fn render_sdl<M: AsRef<dyn ProgressionMonitor>>(
         }
         if render_iter.peek().is_some() {
             let instant = Instant::now();
-            let mut wrapper_canvas = WrapperCanvas(&mut render_canvas);
+            let mut wrapper_canvas = WrapperCanvas(&mut render_canvas, transfer_function);
 
             while let Some(pixel) = render_iter.next() {
                 wrapper_canvas.draw(pixel?)?;
@@ -87,6 +87,7 @@ pub mod result {
         LoggerError(String),
         BadArgument(String),
         DrawError(String),
+        OutputError(String),
     }
 
     impl Display for AppError {
@@ -97,6 +98,7 @@ pub mod result {
                 LoggerError(val) => write!(formatter, "Logger: {}", val),
                 BadArgument(val) => write!(formatter, "Argument: {}", val),
                 DrawError(val) => write!(formatter, "DrawError: {}", val),
+                OutputError(val) => write!(formatter, "Output: {}", val),
             }
         }
     }
@@ -107,6 +109,12 @@ pub mod result {
         }
     }
 
+    impl From<image::ImageError> for AppError {
+        fn from(err: image::ImageError) -> Self {
+            OutputError(err.to_string())
+        }
+    }
+
     impl From<WindowBuildError> for AppError {
         fn from(err: WindowBuildError) -> Self {
             SdlError(err.to_string())
@@ -154,18 +162,19 @@ pub mod canvas {
     }
     pub mod sdl {
         use super::*;
+        use raytracer::colors::TransferFunction;
         use raytracer::renderer::Pixel;
         use sdl2::render::Canvas;
 
-        pub struct WrapperCanvas<'a, T: sdl2::render::RenderTarget>(pub &'a mut Canvas<T>);
+        pub struct WrapperCanvas<'a, T: sdl2::render::RenderTarget>(
+            pub &'a mut Canvas<T>,
+            pub TransferFunction,
+        );
 
         impl<T: sdl2::render::RenderTarget> DrawCanvas for WrapperCanvas<'_, T> {
             fn draw(&mut self, p: Pixel) -> std::result::Result<(), DrawCanvasError> {
-                let draw_color = sdl2::pixels::Color::RGB(
-                    (255.0 * p.color.red()) as u8,
-                    (255.0 * p.color.green()) as u8,
-                    (255.0 * p.color.blue()) as u8,
-                );
+                let [red, green, blue] = self.1.to_rgb8(&p.color);
+                let draw_color = sdl2::pixels::Color::RGB(red, green, blue);
                 self.0.set_draw_color(draw_color);
                 self.0
                     .draw_point(sdl2::rect::Point::new(p.x as i32, p.y as i32))
@@ -175,6 +184,58 @@ pub mod canvas {
         }
     }
 
+    pub mod file {
+        use super::*;
+        use raytracer::colors::TransferFunction;
+        use raytracer::renderer::Pixel;
+        use std::path::Path;
+
+        /// RGBA buffer of the canvas size, filled whatever the order of drawn pixels
+        pub struct ImageCanvas {
+            buffer: Vec<u8>,
+            width: u32,
+            height: u32,
+            transfer_function: TransferFunction,
+        }
+
+        impl ImageCanvas {
+            pub fn new(width: u32, height: u32, transfer_function: TransferFunction) -> Self {
+                ImageCanvas {
+                    buffer: vec![0; (4 * width * height) as usize],
+                    width,
+                    height,
+                    transfer_function,
+                }
+            }
+
+            /// Write the buffer in an image file, whose format is given by the extension
+            pub fn save<P: AsRef<Path>>(&self, path: P) -> image::ImageResult<()> {
+                image::save_buffer(
+                    path,
+                    &self.buffer,
+                    self.width,
+                    self.height,
+                    image::ColorType::Rgba8,
+                )
+            }
+        }
+
+        impl DrawCanvas for ImageCanvas {
+            fn draw(&mut self, p: Pixel) -> Result<(), DrawCanvasError> {
+                if p.x >= self.width || p.y >= self.height {
+                    return Err(DrawCanvasError(format!(
+                        "Pixel ({}, {}) is out of the image",
+                        p.x, p.y
+                    )));
+                }
+                let index = 4 * (p.x + p.y * self.width) as usize;
+                let rgba = self.transfer_function.to_rgba8(&p.color, 0xFF);
+                self.buffer[index..index + 4].copy_from_slice(&rgba);
+                Ok(())
+            }
+        }
+    }
+
     pub mod none {
         use super::*;
         use raytracer::renderer::Pixel;
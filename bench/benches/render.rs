@@ -0,0 +1,222 @@
+/*
+MIT License
+
+Copyright (c) 2019, 2020 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use raytracer::cameras::PerspectiveCamera;
+use raytracer::primitives::{Ray, Shape, Sphere};
+use raytracer::ray_algorithm::search_object_collision;
+use raytracer::ray_algorithm::strategy::{
+    GridAntiAliasingRenderStrategy, GridPattern, PixelRng, RandomAntiAliasingRenderStrategy,
+};
+use raytracer::ray_algorithm::AnyPixelRenderStrategy;
+use raytracer::renderer::{render_accelerated, render_scene, RenderConfiguration};
+use raytracer::vector::Vec3;
+use raytracer_bench::{caustic_scene, reference_scene, sphere_row};
+use std::collections::hash_map::DefaultHasher;
+use std::f64::consts::PI;
+use std::hash::{Hash, Hasher};
+
+fn sphere_collision(c: &mut Criterion) {
+    let sphere = Sphere {
+        center: Vec3::new(0.0, 0.0, 10.0),
+        radius: 2.0,
+    };
+    let hit = Ray::new(Vec3::ZERO, Vec3::new(0.05, 0.0, 1.0));
+    let miss = Ray::new(Vec3::ZERO, Vec3::new(1.0, 0.0, 1.0));
+    c.bench_function("sphere_check_collision_hit", |b| {
+        b.iter(|| sphere.check_collision(black_box(&hit)))
+    });
+    c.bench_function("sphere_check_collision_miss", |b| {
+        b.iter(|| sphere.check_collision(black_box(&miss)))
+    });
+}
+
+fn sphere_collision_batch(c: &mut Criterion) {
+    let sphere = Sphere {
+        center: Vec3::new(0.0, 0.0, 10.0),
+        radius: 2.0,
+    };
+    // Fan of rays, half of them hitting the sphere
+    let rays: Vec<Ray> = (0..256)
+        .map(|index| {
+            let x = (index as f64 / 255.0 - 0.5) * 0.8;
+            Ray::new(Vec3::ZERO, Vec3::new(x, 0.0, 1.0))
+        })
+        .collect();
+    let mut group = c.benchmark_group("sphere_collision_256_rays");
+    group.bench_function("per_ray", |b| {
+        let mut out = vec![None; rays.len()];
+        b.iter(|| {
+            for (ray, hit) in black_box(&rays).iter().zip(out.iter_mut()) {
+                *hit = sphere.check_collision(ray);
+            }
+            out.iter().filter(|hit| hit.is_some()).count()
+        })
+    });
+    group.bench_function("batch", |b| {
+        let mut out = vec![None; rays.len()];
+        b.iter(|| {
+            sphere.check_collision_batch(black_box(&rays), &mut out);
+            out.iter().filter(|hit| hit.is_some()).count()
+        })
+    });
+    group.finish();
+}
+
+fn object_search(c: &mut Criterion) {
+    let mut group = c.benchmark_group("search_object_collision");
+    for &count in &[10, 100, 1000] {
+        let objects = sphere_row(count);
+        // Aimed at the farthest sphere of the row
+        let ray = Ray::ray_from_to(Vec3::ZERO, Vec3::new(3.0 * (count - 1) as f64, 0.0, 20.0));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(count),
+            &objects,
+            |b, objects| {
+                b.iter(|| search_object_collision(black_box(&ray), objects).map(|c| c.array_index))
+            },
+        );
+    }
+    group.finish();
+}
+
+fn full_render(c: &mut Criterion) {
+    // Grid sampling, so that each iteration launches exactly the same rays
+    let config = || RenderConfiguration {
+        canvas_width: 64,
+        canvas_height: 36,
+        render_strategy: Box::new(GridAntiAliasingRenderStrategy {
+            samples_per_side: 2,
+            pattern: GridPattern::Aligned,
+        }),
+        ..Default::default()
+    };
+    c.bench_function("render_scene_64x36_grid_2x2", |b| {
+        b.iter_batched(
+            || (reference_scene(), config()),
+            |(scene, config)| render_scene(scene, config, false).unwrap().count(),
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+fn accelerated_frames(c: &mut Criterion) {
+    let config = || RenderConfiguration {
+        canvas_width: 32,
+        canvas_height: 18,
+        ..Default::default()
+    };
+    let frame_camera = |frame: usize| {
+        PerspectiveCamera::new(
+            Vec3::new(frame as f64, 10.0, -10.0),
+            Vec3::new(0.0, 0.0, 30.0),
+            32.0,
+            18.0,
+            PI / 8.0,
+        )
+    };
+    let frame_count = 4;
+    let mut group = c.benchmark_group("caustic_frames_32x18");
+    group.sample_size(10);
+    group.bench_function("rebuilt", |b| {
+        b.iter(|| {
+            for frame in 0..frame_count {
+                let mut scene = caustic_scene();
+                scene.camera = Box::new(frame_camera(frame));
+                render_scene(scene, config(), false).unwrap().count();
+            }
+        })
+    });
+    group.bench_function("accelerated", |b| {
+        b.iter(|| {
+            let accel = caustic_scene().build_accel();
+            for frame in 0..frame_count {
+                let camera = Box::new(frame_camera(frame));
+                render_accelerated(&accel, Some(camera), &config()).unwrap();
+            }
+        })
+    });
+    group.finish();
+}
+
+fn random_strategy_rng(c: &mut Criterion) {
+    let scene = reference_scene();
+    let mut group = c.benchmark_group("random_strategy_pixel");
+    for &(name, seed) in &[("thread_rng", None), ("seeded", Some(42))] {
+        let strategy = RandomAntiAliasingRenderStrategy {
+            rays_per_pixel: 16,
+            seed,
+        };
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                strategy.render_pixel(
+                    &scene,
+                    &*scene.camera,
+                    black_box(0.5),
+                    0.5,
+                    1.0 / 64.0,
+                    1.0 / 36.0,
+                )
+            })
+        });
+    }
+    group.finish();
+}
+
+fn pixel_rng(c: &mut Criterion) {
+    // Random numbers drawn for one pixel of 16 rays, with the generator of each pixel
+    // being created as before and after the switch to PixelRng
+    fn draw(rng: &mut impl Rng) -> f64 {
+        (0..32).map(|_| rng.gen::<f64>()).sum()
+    }
+    let mut group = c.benchmark_group("pixel_rng");
+    group.bench_function("before_thread_rng", |b| {
+        b.iter(|| draw(&mut rand::thread_rng()))
+    });
+    group.bench_function("before_seeded_std_rng", |b| {
+        b.iter(|| {
+            let mut hasher = DefaultHasher::new();
+            (42_u64, black_box(0.5_f64).to_bits(), 0.5_f64.to_bits()).hash(&mut hasher);
+            draw(&mut StdRng::seed_from_u64(hasher.finish()))
+        })
+    });
+    group.bench_function("after_seeded_pixel_rng", |b| {
+        b.iter(|| draw(&mut PixelRng::for_pixel(42, black_box(0.5), 0.5)))
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    sphere_collision,
+    sphere_collision_batch,
+    object_search,
+    full_render,
+    accelerated_frames,
+    random_strategy_rng,
+    pixel_rng
+);
+criterion_main!(benches);
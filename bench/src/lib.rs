@@ -0,0 +1,110 @@
+/*
+MIT License
+
+Copyright (c) 2019, 2020 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Fixed scenes shared by the benchmarks, so that measures can be compared
+//! between revisions.
+
+use raytracer::cameras::PerspectiveCamera;
+use raytracer::colors::Color;
+use raytracer::lights::LightPoint;
+use raytracer::photon::CausticsSettings;
+use raytracer::primitives::{InfinitePlan, Sphere};
+use raytracer::scene::{Scene, SceneConfiguration, SceneObject};
+use raytracer::textures::{CheckedPattern, PlainColorTexture, TextureEffects, Transparency};
+use raytracer::vector::Vec3;
+use std::f64::consts::PI;
+
+/// `count` unit spheres on a row along the x axis, at `z = 20`
+pub fn sphere_row(count: usize) -> Vec<SceneObject> {
+    (0..count)
+        .map(|index| {
+            SceneObject::new(
+                Box::new(Sphere {
+                    center: Vec3::new(3.0 * index as f64, 0.0, 20.0),
+                    radius: 1.0,
+                }),
+                Box::new(PlainColorTexture::default()),
+            )
+        })
+        .collect()
+}
+
+/// Small scene with a checked sphere over a plane, lit by two lights
+pub fn reference_scene() -> Scene {
+    let camera = PerspectiveCamera::new(
+        Vec3::new(0.0, 10.0, -10.0),
+        Vec3::new(0.0, 0.0, 30.0),
+        16.0 * 2.0,
+        9.0 * 2.0,
+        PI / 8.0,
+    );
+    let sphere = SceneObject::new(
+        Box::new(Sphere {
+            center: Vec3::new(0.0, 0.0, 0.0),
+            radius: 5.0,
+        }),
+        Box::new(CheckedPattern::default()),
+    );
+    let ground = SceneObject::new(
+        Box::new(InfinitePlan::new(
+            Vec3::new(0.0, -5.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+        )),
+        Box::new(PlainColorTexture::default()),
+    );
+    Scene {
+        camera: Box::new(camera),
+        lights: vec![
+            Box::new(LightPoint::with_color(
+                Vec3::new(50.0, 100.0, -50.0),
+                Color::new(0.8, 0.8, 0.8),
+            )),
+            Box::new(LightPoint::new(Vec3::new(-30.0, 20.0, -10.0)).with_intensity(0.5)),
+        ],
+        objects: vec![sphere, ground],
+        config: Default::default(),
+    }
+}
+
+/// Reference scene whose sphere is made of glass, with caustics enabled, so that
+/// preparing the scene traces a photon map
+pub fn caustic_scene() -> Scene {
+    let mut scene = reference_scene();
+    scene.objects[0].effects = TextureEffects {
+        transparency: Some(Transparency {
+            refractive_index: 1.5,
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    scene.config = SceneConfiguration {
+        caustics: Some(CausticsSettings {
+            photon_count: 20_000,
+            gather_radius: 0.5,
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    scene
+}
@@ -0,0 +1,174 @@
+/*
+MIT License
+
+Copyright (c) 2019, 2020 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+use crate::colors::Color;
+use crate::scene::Scene;
+use crate::vector::Vec3;
+
+/// Value which can be linearly interpolated between two keyframes
+pub trait Interpolate: Clone {
+    /// Value at `ratio` between `self` (0) and `other` (1)
+    fn interpolate(&self, other: &Self, ratio: f64) -> Self;
+}
+
+impl Interpolate for f64 {
+    fn interpolate(&self, other: &Self, ratio: f64) -> Self {
+        (1.0 - ratio) * self + ratio * other
+    }
+}
+
+impl Interpolate for Vec3 {
+    fn interpolate(&self, other: &Self, ratio: f64) -> Self {
+        (1.0 - ratio) * *self + ratio * *other
+    }
+}
+
+impl Interpolate for Color {
+    fn interpolate(&self, other: &Self, ratio: f64) -> Self {
+        (1.0 - ratio) * self + ratio * other
+    }
+}
+
+/// Values at given times, linearly interpolated in between. Before the first
+/// keyframe and after the last one, the value of the nearest keyframe is kept.
+#[derive(Debug, Clone)]
+pub struct Track<T> {
+    /// Sorted by time
+    keyframes: Vec<(f64, T)>,
+}
+
+impl<T> Default for Track<T> {
+    fn default() -> Self {
+        Track {
+            keyframes: Vec::new(),
+        }
+    }
+}
+
+impl<T: Interpolate> Track<T> {
+    /// Keyframes are given as `(time, value)`, in any order
+    pub fn new(mut keyframes: Vec<(f64, T)>) -> Self {
+        keyframes.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+        Track { keyframes }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keyframes.is_empty()
+    }
+
+    /// Interpolated value at `time`, `None` if the track has no keyframe
+    pub fn value_at(&self, time: f64) -> Option<T> {
+        let next_index = self
+            .keyframes
+            .iter()
+            .position(|(key_time, _)| *key_time > time);
+        match next_index {
+            None => self.keyframes.last().map(|(_, value)| value.clone()),
+            Some(0) => Some(self.keyframes[0].1.clone()),
+            Some(index) => {
+                let (previous_time, previous) = &self.keyframes[index - 1];
+                let (next_time, next) = &self.keyframes[index];
+                let ratio = (time - previous_time) / (next_time - previous_time);
+                Some(previous.interpolate(next, ratio))
+            }
+        }
+    }
+}
+
+/// Keyframe tracks of the properties of a light
+#[derive(Debug, Default, Clone)]
+pub struct LightTracks {
+    pub source: Track<Vec3>,
+    pub color: Track<Color>,
+    pub intensity: Track<f64>,
+}
+
+/// Keyframe tracks of the camera position and target, the camera being moved
+/// with `RayEmitter::reframed`
+#[derive(Debug, Default, Clone)]
+pub struct CameraTracks {
+    pub eye: Track<Vec3>,
+    pub look_at: Track<Vec3>,
+}
+
+/// Changes of a scene over time
+#[derive(Debug, Default, Clone)]
+pub struct Animation {
+    /// Tracks of animated lights, with the index of the light in the scene
+    pub lights: Vec<(usize, LightTracks)>,
+    pub camera: CameraTracks,
+}
+
+impl Animation {
+    pub fn is_empty(&self) -> bool {
+        self.lights.is_empty() && self.camera.eye.is_empty() && self.camera.look_at.is_empty()
+    }
+
+    /// Set the animated properties of the scene to their values at `time`. Properties
+    /// without keyframes are left unchanged.
+    pub fn apply(&self, scene: &mut Scene, time: f64) {
+        for (index, tracks) in &self.lights {
+            let light = match scene.lights.get_mut(*index) {
+                Some(light) => light,
+                None => continue,
+            };
+            if let Some(source) = tracks.source.value_at(time) {
+                light.set_source(source);
+            }
+            if let Some(color) = tracks.color.value_at(time) {
+                light.set_color(color);
+            }
+            if let Some(intensity) = tracks.intensity.value_at(time) {
+                light.set_intensity(intensity);
+            }
+        }
+        let eye = self.camera.eye.value_at(time);
+        let look_at = self.camera.look_at.value_at(time);
+        if let (Some(eye), Some(look_at)) = (eye, look_at) {
+            if let Some(camera) = scene.camera.reframed(eye, look_at) {
+                scene.camera = camera;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn two_keyframes_give_midpoint_value() {
+        let track = Track::new(vec![(1.0, 3.0), (0.0, 1.0)]);
+        assert!((track.value_at(0.5).unwrap() - 2.0).abs() < 1e-9);
+        assert!((track.value_at(-1.0).unwrap() - 1.0).abs() < 1e-9);
+        assert!((track.value_at(2.0).unwrap() - 3.0).abs() < 1e-9);
+        assert!(Track::<f64>::default().value_at(0.5).is_none());
+    }
+
+    #[test]
+    fn nan_keyframe_time_does_not_panic() {
+        let track = Track::new(vec![(f64::NAN, 5.0), (1.0, 3.0), (0.0, 1.0)]);
+        assert!((track.value_at(0.5).unwrap() - 2.0).abs() < 1e-9);
+    }
+}
@@ -22,13 +22,35 @@ OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 SOFTWARE.
 */
 
-use crate::primitives::Ray;
+use crate::primitives::{Ray, Shape};
+use crate::ray_algorithm::strategy::PixelRng;
 use crate::scene::RayEmitter;
-use crate::utils::{f64_gt, f64_lt};
+use crate::utils::{f64_gt, f64_lt, unit_interval_clamp};
 use crate::vector::{Mat3, Vec3};
 use crate::UnitInterval;
+use rand::Rng;
 use std::f64::consts::PI;
 
+/// Axes of a camera: `right` and `up` along the screen, `forward` toward the scene
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CameraBasis {
+    pub right: Vec3,
+    pub up: Vec3,
+    pub forward: Vec3,
+}
+
+impl CameraBasis {
+    /// Direction in view space: x to the right, y up and z toward the camera, so that
+    /// the normals of surfaces facing the camera have a positive z
+    pub fn to_view_space(&self, direction: Vec3) -> Vec3 {
+        Vec3::new(
+            direction.dot_product(self.right),
+            direction.dot_product(self.up),
+            -direction.dot_product(self.forward),
+        )
+    }
+}
+
 #[derive(Debug)]
 pub struct PerspectiveCamera {
     eye: Vec3,
@@ -49,6 +71,24 @@ impl PerspectiveCamera {
         angle: f64,
     ) -> PerspectiveCamera {
         let eye_direction = Vec3::between_points(screen_center, look_at).normalize();
+        PerspectiveCamera::along_direction(screen_center, eye_direction, width, height, angle)
+    }
+
+    /// Camera whose rays all start from `eye`, the screen being placed in front of it
+    pub fn from_eye(eye: Vec3, look_at: Vec3, width: f64, height: f64, angle: f64) -> Self {
+        let eye_direction = Vec3::between_points(eye, look_at).normalize();
+        let distance_eye_center = height / (2.0 * angle.tan());
+        let screen_center = eye + distance_eye_center * eye_direction;
+        PerspectiveCamera::along_direction(screen_center, eye_direction, width, height, angle)
+    }
+
+    fn along_direction(
+        screen_center: Vec3,
+        eye_direction: Vec3,
+        width: f64,
+        height: f64,
+        angle: f64,
+    ) -> PerspectiveCamera {
         let transform = Mat3::transformation_between(Vec3::new(0.0, 0.0, 1.0), eye_direction);
         let distance_eye_center = height / (2.0 * angle.tan());
         let eye = screen_center - distance_eye_center * eye_direction;
@@ -65,6 +105,57 @@ impl PerspectiveCamera {
             axis_z,
         }
     }
+
+    /// Orient the camera so that `up` appears vertical on the screen
+    pub fn with_up(self, up: Vec3) -> Self {
+        let (axis_x, axis_y) = basis_with_up(self.axis_x, self.axis_y, self.axis_z, up);
+        PerspectiveCamera {
+            axis_x,
+            axis_y,
+            ..self
+        }
+    }
+
+    /// Camera whose axis points to the centroid of a shape
+    pub fn looking_at_object(
+        screen_center: Vec3,
+        shape: &dyn Shape,
+        width: f64,
+        height: f64,
+        angle: f64,
+    ) -> PerspectiveCamera {
+        PerspectiveCamera::new(screen_center, shape.centroid(), width, height, angle)
+    }
+
+    /// Point from which all rays start
+    pub fn eye(&self) -> Vec3 {
+        self.eye
+    }
+
+    pub fn screen_center(&self) -> Vec3 {
+        self.screen_center
+    }
+
+    /// Horizontal axis of the screen, toward its right side
+    pub fn right(&self) -> Vec3 {
+        self.axis_x
+    }
+
+    /// Vertical axis of the screen, toward its top side
+    pub fn up(&self) -> Vec3 {
+        self.axis_y
+    }
+
+    /// Direction from the eye toward the screen center
+    pub fn forward(&self) -> Vec3 {
+        self.axis_z
+    }
+
+    /// Half the vertical field of view
+    fn angle(&self) -> f64 {
+        let distance_eye_center = self.eye.distance(self.screen_center);
+        (self.height / (2.0 * distance_eye_center)).atan()
+    }
 }
 
 impl Default for PerspectiveCamera {
@@ -105,6 +196,32 @@ impl RayEmitter for PerspectiveCamera {
             - canvas_y * self.height * self.axis_y;
         Ray::ray_from_to(self.eye, ray_destination)
     }
+
+    /// Width, height and angle of view are kept
+    fn reframed(&self, eye: Vec3, look_at: Vec3) -> Option<Box<dyn RayEmitter>> {
+        Some(Box::new(PerspectiveCamera::from_eye(
+            eye,
+            look_at,
+            self.width,
+            self.height,
+            self.angle(),
+        )))
+    }
+
+    /// The footprint grows with the depth of the point along the camera axis
+    fn pixel_footprint(&self, point: Vec3, canvas_width: u32) -> f64 {
+        let depth = Vec3::between_points(self.eye, point).dot_product(self.axis_z);
+        let screen_distance = self.eye.distance(self.screen_center);
+        depth.max(0.0) / screen_distance * self.width / canvas_width as f64
+    }
+
+    fn basis(&self) -> Option<CameraBasis> {
+        Some(CameraBasis {
+            right: self.axis_x,
+            up: self.axis_y,
+            forward: self.axis_z,
+        })
+    }
 }
 
 #[derive(Debug)]
@@ -132,6 +249,42 @@ impl OrthogonalCamera {
             height,
         }
     }
+
+    /// Orient the camera so that `up` appears vertical on the screen
+    pub fn with_up(self, up: Vec3) -> Self {
+        let (axis_x, axis_y) = basis_with_up(self.axis_x, self.axis_y, self.axis_z, up);
+        OrthogonalCamera {
+            axis_x,
+            axis_y,
+            ..self
+        }
+    }
+}
+
+impl OrthogonalCamera {
+    /// Center of the screen, where the camera is placed
+    pub fn eye(&self) -> Vec3 {
+        self.screen_center
+    }
+
+    pub fn screen_center(&self) -> Vec3 {
+        self.screen_center
+    }
+
+    /// Horizontal axis of the screen, toward its right side
+    pub fn right(&self) -> Vec3 {
+        self.axis_x
+    }
+
+    /// Vertical axis of the screen, toward its top side
+    pub fn up(&self) -> Vec3 {
+        self.axis_y
+    }
+
+    /// Direction of all rays
+    pub fn forward(&self) -> Vec3 {
+        self.axis_z
+    }
 }
 
 impl Default for OrthogonalCamera {
@@ -170,4 +323,560 @@ impl RayEmitter for OrthogonalCamera {
             - canvas_y * self.height * self.axis_y;
         Ray::new(ray_source, self.axis_z)
     }
+
+    fn basis(&self) -> Option<CameraBasis> {
+        Some(CameraBasis {
+            right: self.axis_x,
+            up: self.axis_y,
+            forward: self.axis_z,
+        })
+    }
+}
+
+/// Camera interpolating between an orthogonal projection, when `blend` is 0,
+/// and a perspective projection, when `blend` is 1.
+#[derive(Debug)]
+pub struct BlendedCamera {
+    orthogonal: OrthogonalCamera,
+    perspective: PerspectiveCamera,
+    blend: UnitInterval,
+}
+
+impl BlendedCamera {
+    pub fn new(
+        screen_center: Vec3,
+        look_at: Vec3,
+        width: f64,
+        height: f64,
+        angle: f64,
+        blend: UnitInterval,
+    ) -> Self {
+        BlendedCamera {
+            orthogonal: OrthogonalCamera::new(screen_center, look_at, width, height),
+            perspective: PerspectiveCamera::new(screen_center, look_at, width, height, angle),
+            blend: unit_interval_clamp(blend),
+        }
+    }
+
+    /// Orient the camera so that `up` appears vertical on the screen
+    pub fn with_up(self, up: Vec3) -> Self {
+        BlendedCamera {
+            orthogonal: self.orthogonal.with_up(up),
+            perspective: self.perspective.with_up(up),
+            ..self
+        }
+    }
+}
+
+/// Line-scan camera moving along a path: each row of the image is seen from a
+/// different position, with a perspective projection only across the row.
+///
+/// The first row is seen from `position`, the last one from `position + travel`.
+#[derive(Debug)]
+pub struct PushBroomCamera {
+    position: Vec3,
+    travel: Vec3,
+    width: f64,
+    height: f64,
+    angle_tan: f64,
+    axis_x: Vec3,
+    axis_y: Vec3,
+    axis_z: Vec3,
+}
+
+impl PushBroomCamera {
+    /// `angle` is half the horizontal field of view
+    pub fn new(
+        position: Vec3,
+        look_at: Vec3,
+        travel: Vec3,
+        width: f64,
+        height: f64,
+        angle: f64,
+    ) -> Self {
+        let axis_z = Vec3::between_points(position, look_at).normalize();
+        let transform = Mat3::transformation_between(Vec3::new(0.0, 0.0, 1.0), axis_z);
+        let axis_y = (transform * Vec3::new(0.0, 1.0, 0.0)).normalize();
+        let axis_x = axis_y.cross_product(axis_z);
+        PushBroomCamera {
+            position,
+            travel,
+            width,
+            height,
+            angle_tan: angle.tan(),
+            axis_x,
+            axis_y,
+            axis_z,
+        }
+    }
+
+    /// Orient the camera so that `up` appears vertical on the screen
+    pub fn with_up(self, up: Vec3) -> Self {
+        let (axis_x, axis_y) = basis_with_up(self.axis_x, self.axis_y, self.axis_z, up);
+        PushBroomCamera {
+            axis_x,
+            axis_y,
+            ..self
+        }
+    }
+}
+
+impl RayEmitter for PushBroomCamera {
+    fn width(&self) -> f64 {
+        self.width
+    }
+
+    fn height(&self) -> f64 {
+        self.height
+    }
+
+    fn generate_ray(&self, canvas_x: UnitInterval, canvas_y: UnitInterval) -> Ray {
+        let source = self.position + canvas_y * self.travel;
+        let direction = self.axis_z + ((2.0 * canvas_x - 1.0) * self.angle_tan) * self.axis_x;
+        Ray::new(source, direction)
+    }
+
+    /// The forward axis is the direction of the rays at the center of each row
+    fn basis(&self) -> Option<CameraBasis> {
+        Some(CameraBasis {
+            right: self.axis_x,
+            up: self.axis_y,
+            forward: self.axis_z,
+        })
+    }
+}
+
+/// Part of the screen of another camera, centered on it. Canvas coordinates are
+/// scaled around the screen center by `scale_x` and `scale_y`.
+#[derive(Debug)]
+pub struct CroppedCamera {
+    camera: Box<dyn RayEmitter>,
+    scale_x: f64,
+    scale_y: f64,
+}
+
+impl CroppedCamera {
+    pub fn new(camera: Box<dyn RayEmitter>, scale_x: f64, scale_y: f64) -> Self {
+        CroppedCamera {
+            camera,
+            scale_x,
+            scale_y,
+        }
+    }
+
+    /// Largest part of the screen of `camera` with the proportions of a canvas whose
+    /// pixels are `pixel_aspect_ratio` times wider than high once displayed
+    pub fn for_pixel_aspect_ratio(
+        camera: Box<dyn RayEmitter>,
+        canvas_width: u32,
+        canvas_height: u32,
+        pixel_aspect_ratio: f64,
+    ) -> Self {
+        let display_ratio = canvas_width as f64 * pixel_aspect_ratio / canvas_height as f64;
+        let camera_ratio = camera.size_ratio();
+        if display_ratio > camera_ratio {
+            CroppedCamera::new(camera, 1.0, camera_ratio / display_ratio)
+        } else {
+            CroppedCamera::new(camera, display_ratio / camera_ratio, 1.0)
+        }
+    }
+}
+
+impl RayEmitter for CroppedCamera {
+    fn width(&self) -> f64 {
+        self.scale_x * self.camera.width()
+    }
+
+    fn height(&self) -> f64 {
+        self.scale_y * self.camera.height()
+    }
+
+    fn generate_ray(&self, canvas_x: UnitInterval, canvas_y: UnitInterval) -> Ray {
+        self.camera.generate_ray(
+            0.5 + self.scale_x * (canvas_x - 0.5),
+            0.5 + self.scale_y * (canvas_y - 0.5),
+        )
+    }
+
+    fn pixel_footprint(&self, point: Vec3, canvas_width: u32) -> f64 {
+        self.scale_x * self.camera.pixel_footprint(point, canvas_width)
+    }
+
+    fn basis(&self) -> Option<CameraBasis> {
+        self.camera.basis()
+    }
+}
+
+/// Perspective camera with depth of field: rays start from a point of a lens of
+/// diameter `aperture` centered on the eye, and converge on the plane at
+/// `focus_distance` from the eye along the camera axis, the only sharp one.
+///
+/// The lens point is drawn from a generator seeded by the canvas coordinates, so that
+/// a seeded strategy keeps giving the same image. A single ray per pixel still gives
+/// a noisy image: the camera is meant to be used with a strategy launching several
+/// jittered rays per pixel, like `RandomAntiAliasingRenderStrategy`.
+#[derive(Debug)]
+pub struct ThinLensCamera {
+    camera: PerspectiveCamera,
+    aperture: Aperture,
+    focus_distance: f64,
+}
+
+/// Seed of the lens samples, mixed with the canvas coordinates of each ray
+const LENS_SEED: u64 = 0x6c65_6e73;
+
+impl ThinLensCamera {
+    pub fn new(camera: PerspectiveCamera, aperture: f64, focus_distance: f64) -> Self {
+        ThinLensCamera {
+            camera,
+            aperture: Aperture {
+                radius: aperture / 2.0,
+                blades: 0,
+            },
+            focus_distance,
+        }
+    }
+
+    /// Polygonal lens opening with `blades` sides, circular below 3
+    pub fn with_blades(self, blades: u32) -> Self {
+        ThinLensCamera {
+            aperture: Aperture {
+                blades,
+                ..self.aperture
+            },
+            ..self
+        }
+    }
+}
+
+impl RayEmitter for ThinLensCamera {
+    fn width(&self) -> f64 {
+        self.camera.width()
+    }
+
+    fn height(&self) -> f64 {
+        self.camera.height()
+    }
+
+    fn generate_ray(&self, canvas_x: UnitInterval, canvas_y: UnitInterval) -> Ray {
+        let pinhole_ray = self.camera.generate_ray(canvas_x, canvas_y);
+        let focus_point = pinhole_ray.source
+            + (self.focus_distance / pinhole_ray.direction.dot_product(self.camera.axis_z))
+                * pinhole_ray.direction;
+        let mut rng = PixelRng::for_pixel(LENS_SEED, canvas_x, canvas_y);
+        let (lens_x, lens_y) = self.aperture.sample(&mut rng);
+        let source = self.camera.eye + lens_x * self.camera.axis_x + lens_y * self.camera.axis_y;
+        Ray::ray_from_to(source, focus_point)
+    }
+
+    /// Lens, field of view and focus distance are kept
+    fn reframed(&self, eye: Vec3, look_at: Vec3) -> Option<Box<dyn RayEmitter>> {
+        let camera = PerspectiveCamera::from_eye(
+            eye,
+            look_at,
+            self.camera.width,
+            self.camera.height,
+            self.camera.angle(),
+        );
+        Some(Box::new(ThinLensCamera { camera, ..*self }))
+    }
+
+    fn pixel_footprint(&self, point: Vec3, canvas_width: u32) -> f64 {
+        self.camera.pixel_footprint(point, canvas_width)
+    }
+
+    fn basis(&self) -> Option<CameraBasis> {
+        self.camera.basis()
+    }
+}
+
+/// Lens opening of a camera with depth of field, which gives its shape to out of
+/// focus highlights
+#[derive(Debug, Copy, Clone)]
+pub struct Aperture {
+    pub radius: f64,
+    /// Number of sides of the polygonal opening, below 3 the opening is circular
+    pub blades: u32,
+}
+
+impl Aperture {
+    /// Uniformly distributed point of the aperture, in lens plane coordinates
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> (f64, f64) {
+        if self.blades < 3 {
+            let distance = self.radius * rng.gen::<f64>().sqrt();
+            let angle = 2.0 * PI * rng.gen::<f64>();
+            return (distance * angle.cos(), distance * angle.sin());
+        }
+        // The polygon is split in triangles of same area, sharing the center
+        let sector_angle = 2.0 * PI / self.blades as f64;
+        let sector = rng.gen_range(0, self.blades) as f64;
+        let (start_sin, start_cos) = (sector * sector_angle).sin_cos();
+        let (end_sin, end_cos) = ((sector + 1.0) * sector_angle).sin_cos();
+        let (mut a, mut b) = (rng.gen::<f64>(), rng.gen::<f64>());
+        if a + b > 1.0 {
+            a = 1.0 - a;
+            b = 1.0 - b;
+        }
+        (
+            self.radius * (a * start_cos + b * end_cos),
+            self.radius * (a * start_sin + b * end_sin),
+        )
+    }
+}
+
+/// Screen axes for a camera looking along `axis_z`, with `axis_y` as close as possible
+/// to `up`. The current axes are kept if `up` is parallel to `axis_z`.
+fn basis_with_up(axis_x: Vec3, axis_y: Vec3, axis_z: Vec3, up: Vec3) -> (Vec3, Vec3) {
+    let projected_up = up - up.dot_product(axis_z) * axis_z;
+    if projected_up.norm() < 1e-9 {
+        return (axis_x, axis_y);
+    }
+    let axis_y = projected_up.normalize();
+    (axis_y.cross_product(axis_z), axis_y)
+}
+
+impl RayEmitter for BlendedCamera {
+    fn width(&self) -> f64 {
+        self.perspective.width()
+    }
+
+    fn height(&self) -> f64 {
+        self.perspective.height()
+    }
+
+    fn generate_ray(&self, canvas_x: UnitInterval, canvas_y: UnitInterval) -> Ray {
+        let orthogonal_ray = self.orthogonal.generate_ray(canvas_x, canvas_y);
+        let perspective_ray = self.perspective.generate_ray(canvas_x, canvas_y);
+        let source =
+            (1.0 - self.blend) * orthogonal_ray.source + self.blend * perspective_ray.source;
+        let direction =
+            (1.0 - self.blend) * orthogonal_ray.direction + self.blend * perspective_ray.direction;
+        Ray::new(source, direction)
+    }
+
+    fn pixel_footprint(&self, point: Vec3, canvas_width: u32) -> f64 {
+        (1.0 - self.blend) * self.orthogonal.pixel_footprint(point, canvas_width)
+            + self.blend * self.perspective.pixel_footprint(point, canvas_width)
+    }
+
+    /// Both blended cameras share the same basis
+    fn basis(&self) -> Option<CameraBasis> {
+        self.perspective.basis()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives::Sphere;
+
+    fn assert_same_ray(left: &Ray, right: &Ray) {
+        assert!(left.source.distance(right.source) < 1e-9);
+        assert!(left.direction.distance(right.direction) < 1e-9);
+    }
+
+    #[test]
+    fn reframed_perspective_camera_moves_primary_rays() {
+        let original = PerspectiveCamera::from_eye(
+            Vec3::new(0.0, 0.0, -20.0),
+            Vec3::ZERO,
+            16.0,
+            9.0,
+            PI / 8.0,
+        );
+        let eye = Vec3::new(0.0, 10.0, -10.0);
+        let look_at = Vec3::new(0.0, 0.0, 5.0);
+        let reframed = original.reframed(eye, look_at).unwrap();
+        let expected = PerspectiveCamera::from_eye(eye, look_at, 16.0, 9.0, PI / 8.0);
+        for &(x, y) in &[(0.1, 0.1), (0.5, 0.5), (0.9, 0.3)] {
+            assert_same_ray(&reframed.generate_ray(x, y), &expected.generate_ray(x, y));
+            let original_ray = original.generate_ray(x, y);
+            assert!(
+                original_ray
+                    .source
+                    .distance(reframed.generate_ray(x, y).source)
+                    > 1.0
+            );
+        }
+        let center_ray = reframed.generate_ray(0.5, 0.5);
+        let to_look_at = Vec3::between_points(eye, look_at).normalize();
+        assert!(center_ray.direction.distance(to_look_at) < 1e-9);
+        assert!(OrthogonalCamera::default().reframed(eye, look_at).is_none());
+    }
+
+    #[test]
+    fn default_perspective_camera_looks_forward_from_its_eye() {
+        let camera = PerspectiveCamera::default();
+        let look_at = Vec3::new(0.0, 0.0, 50.0);
+        let to_look_at = Vec3::between_points(camera.eye(), look_at).normalize();
+        assert!(camera.forward().distance(to_look_at) < 1e-9);
+        assert!(camera.eye().z < camera.screen_center().z);
+        assert!(camera.right().dot_product(camera.forward()).abs() < 1e-9);
+        assert!(camera.up().dot_product(camera.forward()).abs() < 1e-9);
+        let basis = camera.basis().unwrap();
+        assert_eq!(basis.forward, camera.forward());
+        assert_eq!(basis.up, camera.up());
+    }
+
+    #[test]
+    fn orthogonal_camera_rays_follow_forward() {
+        let camera = OrthogonalCamera::new(Vec3::new(0.0, 5.0, -5.0), Vec3::ZERO, 16.0, 9.0);
+        let ray = camera.generate_ray(0.2, 0.7);
+        assert!(ray.direction.distance(camera.forward()) < 1e-9);
+        assert!(camera.eye().distance(Vec3::new(0.0, 5.0, -5.0)) < 1e-9);
+        // Rays at the right of the screen start further along the right axis
+        let right_ray = camera.generate_ray(0.8, 0.7);
+        let shift = Vec3::between_points(ray.source, right_ray.source);
+        assert!(shift.normalize().distance(camera.right()) < 1e-9);
+    }
+
+    #[test]
+    fn blended_camera_endpoints_match_pure_cameras() {
+        let screen_center = Vec3::new(0.0, 5.0, -10.0);
+        let look_at = Vec3::new(0.0, 0.0, 15.0);
+        let orthogonal = OrthogonalCamera::new(screen_center, look_at, 16.0, 9.0);
+        let perspective = PerspectiveCamera::new(screen_center, look_at, 16.0, 9.0, PI / 8.0);
+        let blended_orthogonal =
+            BlendedCamera::new(screen_center, look_at, 16.0, 9.0, PI / 8.0, 0.0);
+        let blended_perspective =
+            BlendedCamera::new(screen_center, look_at, 16.0, 9.0, PI / 8.0, 1.0);
+        for &(x, y) in &[(0.0, 0.0), (0.25, 0.75), (0.5, 0.5), (1.0, 1.0)] {
+            assert_same_ray(
+                &blended_orthogonal.generate_ray(x, y),
+                &orthogonal.generate_ray(x, y),
+            );
+            assert_same_ray(
+                &blended_perspective.generate_ray(x, y),
+                &perspective.generate_ray(x, y),
+            );
+        }
+    }
+
+    #[test]
+    fn camera_looking_at_object_points_to_centroid() {
+        let sphere = Sphere {
+            center: Vec3::new(4.0, -2.0, 20.0),
+            radius: 3.0,
+        };
+        let screen_center = Vec3::new(0.0, 5.0, -10.0);
+        let camera =
+            PerspectiveCamera::looking_at_object(screen_center, &sphere, 16.0, 9.0, PI / 8.0);
+        let expected_axis = Vec3::between_points(screen_center, sphere.center).normalize();
+        assert!(camera.axis_z.distance(expected_axis) < 1e-9);
+        let center_ray = camera.generate_ray(0.5, 0.5);
+        assert!(center_ray.direction.distance(expected_axis) < 1e-9);
+    }
+
+    #[test]
+    fn orthogonal_camera_with_up_rotates_screen() {
+        let camera = OrthogonalCamera::new(Vec3::new(0.0, 0.0, -10.0), Vec3::ZERO, 16.0, 9.0)
+            .with_up(Vec3::new(1.0, 1.0, 0.0));
+        let expected_y = Vec3::new(1.0, 1.0, 0.0).normalize();
+        assert!(camera.axis_y.distance(expected_y) < 1e-9);
+        assert!(camera.axis_x.dot_product(camera.axis_y).abs() < 1e-9);
+        assert!(camera.axis_x.dot_product(camera.axis_z).abs() < 1e-9);
+    }
+
+    #[test]
+    fn push_broom_rows_are_linearly_spaced() {
+        let position = Vec3::new(0.0, 10.0, 0.0);
+        let travel = Vec3::new(0.0, 0.0, 20.0);
+        let camera = PushBroomCamera::new(position, Vec3::ZERO, travel, 16.0, 9.0, PI / 8.0);
+        let rows = [0.1, 0.2, 0.3, 0.7];
+        let sources: Vec<Vec3> = rows
+            .iter()
+            .map(|&y| camera.generate_ray(0.5, y).source)
+            .collect();
+        for (&y, &source) in rows.iter().zip(&sources) {
+            assert!(source.distance(position + y * travel) < 1e-9);
+        }
+        let step = sources[0].distance(sources[1]);
+        assert!((sources[1].distance(sources[2]) - step).abs() < 1e-9);
+        // Within a row, rays share their source but not their direction
+        let left = camera.generate_ray(0.1, 0.5);
+        let right = camera.generate_ray(0.9, 0.5);
+        assert!(left.source.distance(right.source) < 1e-9);
+        assert!(left.direction.distance(right.direction) > 0.1);
+    }
+
+    #[test]
+    fn polygonal_aperture_samples_stay_inside_polygon() {
+        let aperture = Aperture {
+            radius: 2.0,
+            blades: 6,
+        };
+        let vertices: Vec<(f64, f64)> = (0..6)
+            .map(|i| {
+                let angle = i as f64 * PI / 3.0;
+                (2.0 * angle.cos(), 2.0 * angle.sin())
+            })
+            .collect();
+        let mut rng = rand::thread_rng();
+        let mut outside_inscribed_circle = false;
+        for _ in 0..1000 {
+            let (x, y) = aperture.sample(&mut rng);
+            for i in 0..6 {
+                let (ax, ay) = vertices[i];
+                let (bx, by) = vertices[(i + 1) % 6];
+                // Counterclockwise polygon, the point must be left of each edge
+                assert!((bx - ax) * (y - ay) - (by - ay) * (x - ax) >= -1e-9);
+            }
+            outside_inscribed_circle |= (x * x + y * y).sqrt() > 2.0 * (PI / 6.0).cos();
+        }
+        // Corners of the polygon are also sampled
+        assert!(outside_inscribed_circle);
+    }
+
+    #[test]
+    fn circular_aperture_samples_stay_inside_disk() {
+        let aperture = Aperture {
+            radius: 0.5,
+            blades: 0,
+        };
+        let mut rng = rand::thread_rng();
+        for _ in 0..1000 {
+            let (x, y) = aperture.sample(&mut rng);
+            assert!((x * x + y * y).sqrt() <= 0.5 + 1e-9);
+        }
+    }
+
+    #[test]
+    fn thin_lens_rays_converge_on_the_focus_plane() {
+        let eye = Vec3::new(0.0, 0.0, -20.0);
+        let pinhole = || PerspectiveCamera::from_eye(eye, Vec3::ZERO, 16.0, 9.0, PI / 8.0);
+        let camera = ThinLensCamera::new(pinhole(), 2.0, 15.0);
+        let mut sources_differ = false;
+        for i in 0..100 {
+            let canvas_x = 0.3 + f64::from(i) * 1e-6;
+            let pinhole_ray = pinhole().generate_ray(canvas_x, 0.6);
+            let focus_point = pinhole_ray.source
+                + (15.0 / pinhole_ray.direction.dot_product(Vec3::new(0.0, 0.0, 1.0)))
+                    * pinhole_ray.direction;
+            assert!((focus_point.z + 5.0).abs() < 1e-9);
+            let ray = camera.generate_ray(canvas_x, 0.6);
+            assert!(ray.source.distance(eye) <= 1.0 + 1e-9);
+            assert!((ray.source.z - eye.z).abs() < 1e-9);
+            sources_differ |= ray.source.distance(eye) > 1e-3;
+            let to_focus = Vec3::between_points(ray.source, focus_point).normalize();
+            assert!(ray.direction.distance(to_focus) < 1e-9);
+        }
+        assert!(sources_differ);
+        // Without aperture, the camera is a pinhole camera
+        let pinhole_lens = ThinLensCamera::new(pinhole(), 0.0, 15.0);
+        assert_same_ray(
+            &pinhole_lens.generate_ray(0.3, 0.6),
+            &pinhole().generate_ray(0.3, 0.6),
+        );
+    }
+
+    #[test]
+    fn thin_lens_rays_only_depend_on_canvas_coordinates() {
+        let eye = Vec3::new(0.0, 0.0, -20.0);
+        let pinhole = PerspectiveCamera::from_eye(eye, Vec3::ZERO, 16.0, 9.0, PI / 8.0);
+        let camera = ThinLensCamera::new(pinhole, 2.0, 15.0);
+        assert_same_ray(
+            &camera.generate_ray(0.3, 0.6),
+            &camera.generate_ray(0.3, 0.6),
+        );
+    }
 }
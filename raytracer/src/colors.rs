@@ -24,6 +24,7 @@ SOFTWARE.
 
 use crate::parser::ModelColor;
 use crate::utils::unit_interval_clamp;
+use crate::vector::Vec3;
 use crate::UnitInterval;
 use serde::Deserialize;
 use std::str::FromStr;
@@ -57,6 +58,124 @@ impl Color {
         self.green
     }
 
+    /// Color from 8-bit components, 255 being the maximum intensity
+    pub fn from_rgb8(rgb: [u8; 3]) -> Color {
+        Color::new(
+            rgb[0] as f64 / 255.0,
+            rgb[1] as f64 / 255.0,
+            rgb[2] as f64 / 255.0,
+        )
+    }
+
+    /// 8-bit components, rounded to the nearest value
+    pub fn to_rgb8(&self) -> [u8; 3] {
+        [
+            unit_to_byte(self.red),
+            unit_to_byte(self.green),
+            unit_to_byte(self.blue),
+        ]
+    }
+
+    /// 8-bit components followed by the alpha channel
+    pub fn to_rgba8(&self, alpha: u8) -> [u8; 4] {
+        let [red, green, blue] = self.to_rgb8();
+        [red, green, blue, alpha]
+    }
+
+    /// Color from unbounded components, mapped into the sRGB / Rec.709 gamut.
+    ///
+    /// Instead of clamping each component, which shifts the hue, an out of gamut
+    /// color is desaturated toward the gray of same luminance until it fits.
+    pub fn gamut_mapped(red: f64, green: f64, blue: f64) -> Color {
+        let luminance = unit_interval_clamp(rec709_luminance(red, green, blue));
+        let scale = [red, green, blue]
+            .iter()
+            .map(|&component| {
+                let offset = component - luminance;
+                if component > 1.0 {
+                    (1.0 - luminance) / offset
+                } else if component < 0.0 {
+                    -luminance / offset
+                } else {
+                    1.0
+                }
+            })
+            .fold(1.0, f64::min);
+        Color::new(
+            luminance + scale * (red - luminance),
+            luminance + scale * (green - luminance),
+            luminance + scale * (blue - luminance),
+        )
+    }
+
+    /// Approximate color of a black body at a temperature in Kelvin, 6500K being
+    /// close to white, lower temperatures warmer and higher ones cooler
+    // https://tannerhelland.com/2012/09/18/convert-temperature-rgb-algorithm-code.html
+    pub fn from_temperature(kelvin: f64) -> Color {
+        let temperature = kelvin.clamp(1000.0, 40000.0) / 100.0;
+        let red = if temperature <= 66.0 {
+            255.0
+        } else {
+            329.698_727_446 * (temperature - 60.0).powf(-0.133_204_759_2)
+        };
+        let green = if temperature <= 66.0 {
+            99.470_802_586_1 * temperature.ln() - 161.119_568_166_1
+        } else {
+            288.122_169_528_3 * (temperature - 60.0).powf(-0.075_514_849_2)
+        };
+        let blue = if temperature >= 66.0 {
+            255.0
+        } else if temperature <= 19.0 {
+            0.0
+        } else {
+            138.517_731_223_1 * (temperature - 10.0).ln() - 305.044_792_730_7
+        };
+        Color::new(red / 255.0, green / 255.0, blue / 255.0)
+    }
+
+    /// Relative luminance, with Rec.709 primaries
+    pub fn luminance(&self) -> f64 {
+        rec709_luminance(self.red, self.green, self.blue)
+    }
+
+    /// Decode a color whose components are sRGB-encoded into linear space
+    pub fn srgb_to_linear(&self) -> Color {
+        Color::new(
+            srgb_decode(self.red),
+            srgb_decode(self.green),
+            srgb_decode(self.blue),
+        )
+    }
+
+    /// Encode a linear color with the sRGB transfer function
+    pub fn linear_to_srgb(&self) -> Color {
+        Color::new(
+            srgb_encode(self.red),
+            srgb_encode(self.green),
+            srgb_encode(self.blue),
+        )
+    }
+
+    /// 8-bit components, sRGB-encoded as expected by most displays
+    pub fn to_srgb8(&self) -> [u8; 3] {
+        self.linear_to_srgb().to_rgb8()
+    }
+
+    /// Name of the nearest named color, as accepted by `from_str`
+    pub fn nearest_name(&self) -> &'static str {
+        let distance = |rgb: &[u8; 3]| {
+            let named = Color::from_rgb8(*rgb);
+            (self.red - named.red).powi(2)
+                + (self.green - named.green).powi(2)
+                + (self.blue - named.blue).powi(2)
+        };
+        NAMED_COLORS
+            .iter()
+            .min_by(|(_, a), (_, b)| distance(a).total_cmp(&distance(b)))
+            .map(|(name, _)| *name)
+            .unwrap()
+    }
+
     pub const WHITE: Self = Color {
         red: 1.0,
         green: 1.0,
@@ -87,21 +206,365 @@ impl Color {
         green: 1.0,
         blue: 0.0,
     };
+    pub const MAGENTA: Self = Color {
+        red: 1.0,
+        green: 0.0,
+        blue: 1.0,
+    };
+}
+
+/// Transfer function used when a color is written to an 8-bit output
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum TransferFunction {
+    /// Components are written as is
+    Linear,
+    /// Components are sRGB-encoded
+    #[default]
+    Srgb,
+}
+
+impl TransferFunction {
+    pub fn to_rgb8(self, color: &Color) -> [u8; 3] {
+        match self {
+            TransferFunction::Linear => color.to_rgb8(),
+            TransferFunction::Srgb => color.to_srgb8(),
+        }
+    }
+
+    pub fn to_rgba8(self, color: &Color, alpha: u8) -> [u8; 4] {
+        let [red, green, blue] = self.to_rgb8(color);
+        [red, green, blue, alpha]
+    }
+}
+
+/// Mapping of radiance values, which may exceed 1, to displayable colors
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum ToneMapping {
+    /// Radiance is kept as is, components above 1 being clamped at output
+    #[default]
+    Clamp,
+    /// Components are compressed with `c / (1 + c)`, so that highlights keep
+    /// some detail instead of becoming pure white
+    Reinhard,
+}
+
+impl ToneMapping {
+    /// Radiance brought into the displayable range, which is then clamped or gamut
+    /// mapped to produce the final color
+    pub fn map(self, radiance: &Radiance) -> Radiance {
+        match self {
+            ToneMapping::Clamp => *radiance,
+            ToneMapping::Reinhard => {
+                // Negative components, from negative lights, are left to the output
+                let reinhard = |c: f64| if c > 0.0 { c / (1.0 + c) } else { c };
+                Radiance::new(
+                    reinhard(radiance.red),
+                    reinhard(radiance.green),
+                    reinhard(radiance.blue),
+                )
+            }
+        }
+    }
+}
+
+fn unit_to_byte(value: UnitInterval) -> u8 {
+    (value * 255.0).round().clamp(0.0, 255.0) as u8
+}
+
+/// Light carried by rays, in linear space and without clamping, so that
+/// intermediate light computations do not lose or gain energy before the final
+/// color is known. Components may exceed 1, or be negative with negative lights.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct Radiance {
+    red: f64,
+    green: f64,
+    blue: f64,
+}
+
+impl Radiance {
+    pub fn new(red: f64, green: f64, blue: f64) -> Self {
+        Radiance { red, green, blue }
+    }
+
+    pub fn red(&self) -> f64 {
+        self.red
+    }
+
+    pub fn green(&self) -> f64 {
+        self.green
+    }
+
+    pub fn blue(&self) -> f64 {
+        self.blue
+    }
+
+    /// Add the light of `color` scaled by `coeff`
+    pub fn accumulate(&mut self, coeff: f64, color: &Color) {
+        self.red += coeff * color.red;
+        self.green += coeff * color.green;
+        self.blue += coeff * color.blue;
+    }
+
+    /// Average radiance, if this one is the sum of `count` radiances
+    pub fn mean(&self, count: u32) -> Radiance {
+        (1.0 / count.max(1) as f64) * *self
+    }
+
+    /// Light going through a filter, like a surface color
+    pub fn filtered(&self, filter: &Color) -> Radiance {
+        Radiance::new(
+            self.red * filter.red,
+            self.green * filter.green,
+            self.blue * filter.blue,
+        )
+    }
+
+    /// Clamp each component into a displayable color
+    pub fn to_color(&self) -> Color {
+        Color::new(self.red, self.green, self.blue)
+    }
+
+    /// Relative luminance, with Rec.709 primaries
+    pub fn luminance(&self) -> f64 {
+        rec709_luminance(self.red, self.green, self.blue)
+    }
+
+    pub fn is_finite(&self) -> bool {
+        self.red.is_finite() && self.green.is_finite() && self.blue.is_finite()
+    }
+}
+
+impl From<&Color> for Radiance {
+    fn from(color: &Color) -> Self {
+        Radiance::new(color.red, color.green, color.blue)
+    }
+}
+
+impl From<Color> for Radiance {
+    fn from(color: Color) -> Self {
+        Radiance::from(&color)
+    }
+}
+
+impl std::ops::Add for Radiance {
+    type Output = Radiance;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Radiance::new(
+            self.red + rhs.red,
+            self.green + rhs.green,
+            self.blue + rhs.blue,
+        )
+    }
+}
+
+impl std::ops::AddAssign for Radiance {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl std::ops::Mul<Radiance> for f64 {
+    type Output = Radiance;
+
+    fn mul(self, rhs: Radiance) -> Self::Output {
+        Radiance::new(self * rhs.red, self * rhs.green, self * rhs.blue)
+    }
+}
+
+// https://en.wikipedia.org/wiki/Rec._709#Luma_coefficients
+fn rec709_luminance(red: f64, green: f64, blue: f64) -> f64 {
+    0.2126 * red + 0.7152 * green + 0.0722 * blue
+}
+
+// https://en.wikipedia.org/wiki/SRGB#From_sRGB_to_CIE_XYZ
+fn srgb_decode(val: UnitInterval) -> UnitInterval {
+    if val <= 0.04045 {
+        val / 12.92
+    } else {
+        ((val + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+// https://en.wikipedia.org/wiki/SRGB#From_CIE_XYZ_to_sRGB
+fn srgb_encode(val: UnitInterval) -> UnitInterval {
+    if val <= 0.003_130_8 {
+        val * 12.92
+    } else {
+        1.055 * val.powf(1.0 / 2.4) - 0.055
+    }
 }
 
+/// CSS named colors, in alphabetical order, as 8-bit components.
+///
+/// "green" is kept as pure green, like "lime", for compatibility with older scenes.
+// https://www.w3.org/TR/css-color-4/#named-colors
+const NAMED_COLORS: [(&str, [u8; 3]); 148] = [
+    ("aliceblue", [240, 248, 255]),
+    ("antiquewhite", [250, 235, 215]),
+    ("aqua", [0, 255, 255]),
+    ("aquamarine", [127, 255, 212]),
+    ("azure", [240, 255, 255]),
+    ("beige", [245, 245, 220]),
+    ("bisque", [255, 228, 196]),
+    ("black", [0, 0, 0]),
+    ("blanchedalmond", [255, 235, 205]),
+    ("blue", [0, 0, 255]),
+    ("blueviolet", [138, 43, 226]),
+    ("brown", [165, 42, 42]),
+    ("burlywood", [222, 184, 135]),
+    ("cadetblue", [95, 158, 160]),
+    ("chartreuse", [127, 255, 0]),
+    ("chocolate", [210, 105, 30]),
+    ("coral", [255, 127, 80]),
+    ("cornflowerblue", [100, 149, 237]),
+    ("cornsilk", [255, 248, 220]),
+    ("crimson", [220, 20, 60]),
+    ("cyan", [0, 255, 255]),
+    ("darkblue", [0, 0, 139]),
+    ("darkcyan", [0, 139, 139]),
+    ("darkgoldenrod", [184, 134, 11]),
+    ("darkgray", [169, 169, 169]),
+    ("darkgreen", [0, 100, 0]),
+    ("darkgrey", [169, 169, 169]),
+    ("darkkhaki", [189, 183, 107]),
+    ("darkmagenta", [139, 0, 139]),
+    ("darkolivegreen", [85, 107, 47]),
+    ("darkorange", [255, 140, 0]),
+    ("darkorchid", [153, 50, 204]),
+    ("darkred", [139, 0, 0]),
+    ("darksalmon", [233, 150, 122]),
+    ("darkseagreen", [143, 188, 143]),
+    ("darkslateblue", [72, 61, 139]),
+    ("darkslategray", [47, 79, 79]),
+    ("darkslategrey", [47, 79, 79]),
+    ("darkturquoise", [0, 206, 209]),
+    ("darkviolet", [148, 0, 211]),
+    ("deeppink", [255, 20, 147]),
+    ("deepskyblue", [0, 191, 255]),
+    ("dimgray", [105, 105, 105]),
+    ("dimgrey", [105, 105, 105]),
+    ("dodgerblue", [30, 144, 255]),
+    ("firebrick", [178, 34, 34]),
+    ("floralwhite", [255, 250, 240]),
+    ("forestgreen", [34, 139, 34]),
+    ("fuchsia", [255, 0, 255]),
+    ("gainsboro", [220, 220, 220]),
+    ("ghostwhite", [248, 248, 255]),
+    ("gold", [255, 215, 0]),
+    ("goldenrod", [218, 165, 32]),
+    ("gray", [128, 128, 128]),
+    ("green", [0, 255, 0]),
+    ("greenyellow", [173, 255, 47]),
+    ("grey", [128, 128, 128]),
+    ("honeydew", [240, 255, 240]),
+    ("hotpink", [255, 105, 180]),
+    ("indianred", [205, 92, 92]),
+    ("indigo", [75, 0, 130]),
+    ("ivory", [255, 255, 240]),
+    ("khaki", [240, 230, 140]),
+    ("lavender", [230, 230, 250]),
+    ("lavenderblush", [255, 240, 245]),
+    ("lawngreen", [124, 252, 0]),
+    ("lemonchiffon", [255, 250, 205]),
+    ("lightblue", [173, 216, 230]),
+    ("lightcoral", [240, 128, 128]),
+    ("lightcyan", [224, 255, 255]),
+    ("lightgoldenrodyellow", [250, 250, 210]),
+    ("lightgray", [211, 211, 211]),
+    ("lightgreen", [144, 238, 144]),
+    ("lightgrey", [211, 211, 211]),
+    ("lightpink", [255, 182, 193]),
+    ("lightsalmon", [255, 160, 122]),
+    ("lightseagreen", [32, 178, 170]),
+    ("lightskyblue", [135, 206, 250]),
+    ("lightslategray", [119, 136, 153]),
+    ("lightslategrey", [119, 136, 153]),
+    ("lightsteelblue", [176, 196, 222]),
+    ("lightyellow", [255, 255, 224]),
+    ("lime", [0, 255, 0]),
+    ("limegreen", [50, 205, 50]),
+    ("linen", [250, 240, 230]),
+    ("magenta", [255, 0, 255]),
+    ("maroon", [128, 0, 0]),
+    ("mediumaquamarine", [102, 205, 170]),
+    ("mediumblue", [0, 0, 205]),
+    ("mediumorchid", [186, 85, 211]),
+    ("mediumpurple", [147, 112, 219]),
+    ("mediumseagreen", [60, 179, 113]),
+    ("mediumslateblue", [123, 104, 238]),
+    ("mediumspringgreen", [0, 250, 154]),
+    ("mediumturquoise", [72, 209, 204]),
+    ("mediumvioletred", [199, 21, 133]),
+    ("midnightblue", [25, 25, 112]),
+    ("mintcream", [245, 255, 250]),
+    ("mistyrose", [255, 228, 225]),
+    ("moccasin", [255, 228, 181]),
+    ("navajowhite", [255, 222, 173]),
+    ("navy", [0, 0, 128]),
+    ("oldlace", [253, 245, 230]),
+    ("olive", [128, 128, 0]),
+    ("olivedrab", [107, 142, 35]),
+    ("orange", [255, 165, 0]),
+    ("orangered", [255, 69, 0]),
+    ("orchid", [218, 112, 214]),
+    ("palegoldenrod", [238, 232, 170]),
+    ("palegreen", [152, 251, 152]),
+    ("paleturquoise", [175, 238, 238]),
+    ("palevioletred", [219, 112, 147]),
+    ("papayawhip", [255, 239, 213]),
+    ("peachpuff", [255, 218, 185]),
+    ("peru", [205, 133, 63]),
+    ("pink", [255, 192, 203]),
+    ("plum", [221, 160, 221]),
+    ("powderblue", [176, 224, 230]),
+    ("purple", [128, 0, 128]),
+    ("rebeccapurple", [102, 51, 153]),
+    ("red", [255, 0, 0]),
+    ("rosybrown", [188, 143, 143]),
+    ("royalblue", [65, 105, 225]),
+    ("saddlebrown", [139, 69, 19]),
+    ("salmon", [250, 128, 114]),
+    ("sandybrown", [244, 164, 96]),
+    ("seagreen", [46, 139, 87]),
+    ("seashell", [255, 245, 238]),
+    ("sienna", [160, 82, 45]),
+    ("silver", [192, 192, 192]),
+    ("skyblue", [135, 206, 235]),
+    ("slateblue", [106, 90, 205]),
+    ("slategray", [112, 128, 144]),
+    ("slategrey", [112, 128, 144]),
+    ("snow", [255, 250, 250]),
+    ("springgreen", [0, 255, 127]),
+    ("steelblue", [70, 130, 180]),
+    ("tan", [210, 180, 140]),
+    ("teal", [0, 128, 128]),
+    ("thistle", [216, 191, 216]),
+    ("tomato", [255, 99, 71]),
+    ("turquoise", [64, 224, 208]),
+    ("violet", [238, 130, 238]),
+    ("wheat", [245, 222, 179]),
+    ("white", [255, 255, 255]),
+    ("whitesmoke", [245, 245, 245]),
+    ("yellow", [255, 255, 0]),
+    ("yellowgreen", [154, 205, 50]),
+];
+
+/// Parse a CSS color name, or comma-separated components between 0 and 1
 impl FromStr for Color {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(match s.to_lowercase().as_ref() {
-            "black" => Self::BLACK,
-            "white" => Self::WHITE,
-            "red" => Self::RED,
-            "green" => Self::GREEN,
-            "blue" => Self::BLUE,
-            "yellow" => Self::YELLOW,
-            other => return Err(format!("{} is not a valid color reference", other)),
-        })
+        if s.contains(',') {
+            let components: Vec3 = s.parse()?;
+            return Ok(Color::new(components.x, components.y, components.z));
+        }
+        let name = s.to_lowercase();
+        NAMED_COLORS
+            .iter()
+            .find(|(candidate, _)| *candidate == name)
+            .map(|(_, rgb)| Color::from_rgb8(*rgb))
+            .ok_or_else(|| format!("{} is not a valid color reference", name))
     }
 }
 
@@ -200,7 +663,40 @@ impl std::ops::Mul<&Color> for UnitInterval {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::utils::{f64_gt, f64_lt};
+    use crate::utils::{f64_eq, f64_gt, f64_lt};
+
+    #[test]
+    fn color_to_rgb8_is_rounded() {
+        assert_eq!(Color::WHITE.to_rgb8(), [255, 255, 255]);
+        assert_eq!(Color::new(0.5, 0.0, 0.2).to_rgb8(), [128, 0, 51]);
+        assert_eq!(Color::BLACK.to_rgba8(0xFF), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn srgb_encoding_brightens_mid_tones() {
+        assert_eq!(Color::new(0.22, 0.0, 1.0).to_srgb8(), [129, 0, 255]);
+        assert_eq!(
+            TransferFunction::Linear.to_rgb8(&Color::new(0.22, 0.0, 1.0)),
+            [56, 0, 255]
+        );
+        let gray = Color::new(0.5, 0.5, 0.5);
+        let round_trip = gray.linear_to_srgb().srgb_to_linear();
+        assert!((round_trip.red() - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn color_from_rgb8_round_trip() {
+        let rgb = [12, 128, 255];
+        assert_eq!(Color::from_rgb8(rgb).to_rgb8(), rgb);
+    }
+
+    #[test]
+    fn reinhard_compresses_high_radiance() {
+        let mapped = ToneMapping::Reinhard.map(&Radiance::new(4.0, 1.0, 0.0));
+        assert!(f64_eq(mapped.red, 0.8));
+        assert!(f64_eq(mapped.green, 0.5));
+        assert!(f64_eq(mapped.blue, 0.0));
+    }
 
     #[test]
     fn new_color_with_high_value_is_clamped() {
@@ -236,4 +732,66 @@ mod tests {
         assert!(f64_lt(result.green, 1.0));
         assert!(f64_lt(result.blue, 1.0));
     }
+
+    #[test]
+    fn gamut_mapped_color_keeps_luminance_better_than_clamp() {
+        let (red, green, blue) = (1.6, 0.4, 0.1);
+        let luminance = rec709_luminance(red, green, blue);
+        let mapped = Color::gamut_mapped(red, green, blue);
+        let clamped = Color::new(red, green, blue);
+        for component in &[mapped.red, mapped.green, mapped.blue] {
+            assert!((0.0..=1.0).contains(component));
+        }
+        assert!((mapped.luminance() - luminance).abs() < 1e-9);
+        assert!((mapped.luminance() - luminance).abs() < (clamped.luminance() - luminance).abs());
+        // Red stays the dominant component
+        assert!(mapped.red > mapped.green && mapped.green > mapped.blue);
+    }
+
+    #[test]
+    fn gamut_mapped_color_in_gamut_is_unchanged() {
+        let mapped = Color::gamut_mapped(0.2, 0.5, 0.9);
+        assert!((mapped.red - 0.2).abs() < 1e-9);
+        assert!((mapped.green - 0.5).abs() < 1e-9);
+        assert!((mapped.blue - 0.9).abs() < 1e-9);
+    }
+
+    #[test]
+    fn temperature_color_goes_from_warm_to_neutral() {
+        let neutral = Color::from_temperature(6500.0);
+        for component in &[neutral.red, neutral.green, neutral.blue] {
+            assert!(*component > 0.95);
+        }
+        let warm = Color::from_temperature(2700.0);
+        assert!(warm.red > 0.99);
+        assert!(warm.red > warm.green && warm.green > warm.blue);
+        assert!(warm.blue < 0.5);
+    }
+
+    #[test]
+    fn css_color_names_are_parsed() {
+        let color = Color::from_str("DodgerBlue").unwrap();
+        assert_eq!(color.to_rgb8(), [30, 144, 255]);
+        assert_eq!(Color::from_str("green").unwrap().to_rgb8(), [0, 255, 0]);
+        assert!(Color::from_str("not a color").is_err());
+    }
+
+    #[test]
+    fn color_from_comma_separated_components() {
+        let color = Color::from_str("0.5, 0.25,1").unwrap();
+        assert_eq!(color.to_rgb8(), Color::new(0.5, 0.25, 1.0).to_rgb8());
+        assert!(Color::from_str("0.5,0.25").is_err());
+    }
+
+    #[test]
+    fn nearest_name_of_near_blue_is_blue() {
+        assert_eq!(Color::new(0.05, 0.0, 0.95).nearest_name(), "blue");
+        assert_eq!(
+            Color::from_rgb8([31, 143, 250]).nearest_name(),
+            "dodgerblue"
+        );
+        assert_eq!(Color::WHITE.nearest_name(), "white");
+        // Invalid colors, as those of a NaN check, still get a name
+        assert!(!Color::new(f64::NAN, 0.0, 0.0).nearest_name().is_empty());
+    }
 }
@@ -0,0 +1,158 @@
+/*
+MIT License
+
+Copyright (c) 2019, 2020 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Composition of rendered layers having an alpha channel
+
+use crate::colors::Color;
+use crate::UnitInterval;
+
+/// Image whose pixels have a color and an opacity, stored row by row.
+///
+/// Colors are not premultiplied by the alpha value.
+#[derive(Debug, Clone)]
+pub struct RgbaBuffer {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<(Color, UnitInterval)>,
+}
+
+impl RgbaBuffer {
+    pub fn new(width: u32, height: u32, pixels: Vec<(Color, UnitInterval)>) -> Self {
+        assert_eq!(pixels.len(), (width * height) as usize);
+        RgbaBuffer {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Fully opaque buffer, e.g. from a rendered image
+    pub fn opaque(width: u32, height: u32, colors: Vec<Color>) -> Self {
+        RgbaBuffer::new(
+            width,
+            height,
+            colors.into_iter().map(|color| (color, 1.0)).collect(),
+        )
+    }
+}
+
+/// Standard "source over" operation, `top` hiding `bottom` according to its opacity
+pub fn over(top: &RgbaBuffer, bottom: &RgbaBuffer) -> RgbaBuffer {
+    compose(top, bottom, |top_component, _| top_component)
+}
+
+/// Colors of `top` are added to the ones of `bottom`, where `top` is opaque
+pub fn add(top: &RgbaBuffer, bottom: &RgbaBuffer) -> RgbaBuffer {
+    compose(top, bottom, |top_component, bottom_component| {
+        top_component + bottom_component
+    })
+}
+
+/// Colors of `bottom` are filtered by the ones of `top`, where `top` is opaque
+pub fn multiply(top: &RgbaBuffer, bottom: &RgbaBuffer) -> RgbaBuffer {
+    compose(top, bottom, |top_component, bottom_component| {
+        top_component * bottom_component
+    })
+}
+
+/// Source over composition of the `top` color blended with `bottom` where both are
+/// present, as defined by https://www.w3.org/TR/compositing-1/
+fn compose<F>(top: &RgbaBuffer, bottom: &RgbaBuffer, blend: F) -> RgbaBuffer
+where
+    F: Fn(f64, f64) -> f64,
+{
+    assert_eq!(top.width, bottom.width);
+    assert_eq!(top.height, bottom.height);
+    let pixels = top
+        .pixels
+        .iter()
+        .zip(&bottom.pixels)
+        .map(|((top_color, top_alpha), (bottom_color, bottom_alpha))| {
+            let alpha = top_alpha + bottom_alpha * (1.0 - top_alpha);
+            if alpha <= 0.0 {
+                return (Color::BLACK, 0.0);
+            }
+            let component = |top_component: f64, bottom_component: f64| {
+                let blended = (1.0 - bottom_alpha) * top_component
+                    + bottom_alpha * blend(top_component, bottom_component);
+                (top_alpha * blended + (1.0 - top_alpha) * bottom_alpha * bottom_component) / alpha
+            };
+            let color = Color::new(
+                component(top_color.red(), bottom_color.red()),
+                component(top_color.green(), bottom_color.green()),
+                component(top_color.blue(), bottom_color.blue()),
+            );
+            (color, alpha)
+        })
+        .collect();
+    RgbaBuffer::new(top.width, top.height, pixels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uniform(color: Color, alpha: UnitInterval) -> RgbaBuffer {
+        RgbaBuffer::new(2, 2, vec![(color, alpha); 4])
+    }
+
+    fn assert_same(left: &RgbaBuffer, right: &RgbaBuffer) {
+        for ((left_color, left_alpha), (right_color, right_alpha)) in
+            left.pixels.iter().zip(&right.pixels)
+        {
+            assert_eq!(left_color.to_rgb8(), right_color.to_rgb8());
+            assert!((left_alpha - right_alpha).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn opaque_top_hides_bottom() {
+        let top = uniform(Color::RED, 1.0);
+        let bottom = uniform(Color::BLUE, 1.0);
+        assert_same(&over(&top, &bottom), &top);
+    }
+
+    #[test]
+    fn transparent_top_shows_bottom() {
+        let top = uniform(Color::RED, 0.0);
+        let bottom = uniform(Color::BLUE, 0.6);
+        assert_same(&over(&top, &bottom), &bottom);
+        assert_same(&add(&top, &bottom), &bottom);
+        assert_same(&multiply(&top, &bottom), &bottom);
+    }
+
+    #[test]
+    fn blend_modes_on_opaque_layers() {
+        let top = uniform(Color::new(0.5, 0.2, 0.0), 1.0);
+        let bottom = uniform(Color::new(0.4, 0.5, 1.0), 1.0);
+        assert_same(
+            &add(&top, &bottom),
+            &uniform(Color::new(0.9, 0.7, 1.0), 1.0),
+        );
+        assert_same(
+            &multiply(&top, &bottom),
+            &uniform(Color::new(0.2, 0.1, 0.0), 1.0),
+        );
+    }
+}
@@ -0,0 +1,289 @@
+/*
+MIT License
+
+Copyright (c) 2019, 2020 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+use crate::colors::Color;
+use crate::textures::Texture;
+use crate::vector::Vec3;
+use std::f64::consts::{FRAC_PI_2, PI};
+use std::fmt::Debug;
+
+/// Background seen by rays which do not hit any object
+pub trait Environment: Send + Sync + Debug {
+    fn color_in_direction(&self, direction: Vec3) -> Color;
+}
+
+/// Environment made of six textures, one for each face of a cube around the scene
+#[derive(Debug)]
+pub struct CubeMap {
+    faces: [Box<dyn Texture>; 6],
+}
+
+impl CubeMap {
+    /// Faces are given in the order: +X, -X, +Y, -Y, +Z, -Z
+    pub fn new(faces: [Box<dyn Texture>; 6]) -> Self {
+        CubeMap { faces }
+    }
+
+    /// Select the cube face hit by a direction, and the coordinates on this face
+    fn face_mapping(direction: Vec3) -> (usize, f64, f64) {
+        // https://www.khronos.org/opengl/wiki/Cubemap_Texture
+        let (x, y, z) = (direction.x, direction.y, direction.z);
+        let (abs_x, abs_y, abs_z) = (x.abs(), y.abs(), z.abs());
+        let (face, u_coord, v_coord, major_axis) = if abs_x >= abs_y && abs_x >= abs_z {
+            if x > 0.0 {
+                (0, -z, -y, abs_x)
+            } else {
+                (1, z, -y, abs_x)
+            }
+        } else if abs_y >= abs_z {
+            if y > 0.0 {
+                (2, x, z, abs_y)
+            } else {
+                (3, x, -z, abs_y)
+            }
+        } else if z > 0.0 {
+            (4, x, -y, abs_z)
+        } else {
+            (5, -x, -y, abs_z)
+        };
+        let u = (u_coord / major_axis + 1.0) / 2.0;
+        let v = (v_coord / major_axis + 1.0) / 2.0;
+        (face, u, v)
+    }
+}
+
+impl Environment for CubeMap {
+    fn color_in_direction(&self, direction: Vec3) -> Color {
+        let (face, u, v) = Self::face_mapping(direction);
+        self.faces[face].color_at(u, v)
+    }
+}
+
+/// Environment made of a single texture wrapped around the scene, with the same
+/// mapping as a sphere: `u` follows the angle around the Y axis, and `v` goes from
+/// 0 at the zenith to 1 at the nadir
+#[derive(Debug)]
+pub struct TextureEnvironment {
+    texture: Box<dyn Texture>,
+}
+
+impl TextureEnvironment {
+    pub fn new(texture: Box<dyn Texture>) -> Self {
+        TextureEnvironment { texture }
+    }
+}
+
+impl Environment for TextureEnvironment {
+    fn color_in_direction(&self, direction: Vec3) -> Color {
+        let direction = direction.normalize();
+        let u = 0.5 + direction.z.atan2(direction.x) / (2.0 * PI);
+        let v = 0.5 - direction.y.clamp(-1.0, 1.0).asin() / PI;
+        self.texture.color_at(u, v)
+    }
+}
+
+/// Luminance of the sky at the zenith, the sky model giving only relative values
+const SKY_ZENITH_LUMINANCE: f64 = 0.3;
+
+/// Daylight sky color from the analytic model of Preetham, Shirley and Smits,
+/// "A Practical Analytic Model for Daylight" (1999).
+///
+/// The Y axis is the zenith. The sun is kept above the horizon, and directions below
+/// the horizon get the color of the horizon.
+#[derive(Debug)]
+pub struct PreethamSky {
+    sun_direction: Vec3,
+    /// Perez coefficients A to E, for the luminance Y and the chromaticities x and y
+    coefficients: [[f64; 5]; 3],
+    /// Luminance and chromaticities at the zenith, divided by the Perez function
+    /// there, so that multiplying by the Perez function of a direction gives its value
+    zenith_ratio: [f64; 3],
+}
+
+impl PreethamSky {
+    /// `turbidity` goes from 2 for a very clear sky to about 10 for a hazy one
+    pub fn new(sun_direction: Vec3, turbidity: f64) -> Self {
+        let sun_direction = sun_direction.normalize();
+        let t = turbidity;
+        let coefficients = [
+            [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+            [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+        ];
+        let theta_sun = sun_direction.y.clamp(0.0, 1.0).acos();
+        let chromaticity = |c: [[f64; 4]; 3]| {
+            let theta = [theta_sun.powi(3), theta_sun.powi(2), theta_sun, 1.0];
+            let polynomial = |row: [f64; 4]| {
+                row.iter()
+                    .zip(theta.iter())
+                    .map(|(coeff, power)| coeff * power)
+                    .sum::<f64>()
+            };
+            t * t * polynomial(c[0]) + t * polynomial(c[1]) + polynomial(c[2])
+        };
+        let zenith = [
+            SKY_ZENITH_LUMINANCE,
+            chromaticity([
+                [0.00166, -0.00375, 0.00209, 0.0],
+                [-0.02903, 0.06377, -0.03202, 0.00394],
+                [0.11693, -0.21196, 0.06052, 0.25886],
+            ]),
+            chromaticity([
+                [0.00275, -0.00610, 0.00317, 0.0],
+                [-0.04214, 0.08970, -0.04153, 0.00516],
+                [0.15346, -0.26756, 0.06670, 0.26688],
+            ]),
+        ];
+        let mut zenith_ratio = [0.0; 3];
+        for (index, ratio) in zenith_ratio.iter_mut().enumerate() {
+            *ratio = zenith[index] / Self::perez(&coefficients[index], 0.0, theta_sun);
+        }
+        PreethamSky {
+            sun_direction,
+            coefficients,
+            zenith_ratio,
+        }
+    }
+
+    /// Distribution of the sky value for a view angle `theta` from the zenith and an
+    /// angle `gamma` from the sun
+    fn perez(coefficients: &[f64; 5], theta: f64, gamma: f64) -> f64 {
+        let [a, b, c, d, e] = *coefficients;
+        (1.0 + a * (b / theta.cos()).exp())
+            * (1.0 + c * (d * gamma).exp() + e * gamma.cos().powi(2))
+    }
+}
+
+impl Environment for PreethamSky {
+    fn color_in_direction(&self, direction: Vec3) -> Color {
+        let direction = direction.normalize();
+        // Slightly above the horizon, where the model diverges
+        let theta = direction.y.max(0.0).acos().min(FRAC_PI_2 - 0.01);
+        let gamma = direction
+            .dot_product(self.sun_direction)
+            .clamp(-1.0, 1.0)
+            .acos();
+        let mut values = [0.0; 3];
+        for (index, value) in values.iter_mut().enumerate() {
+            *value =
+                self.zenith_ratio[index] * Self::perez(&self.coefficients[index], theta, gamma);
+        }
+        let [luminance, x, y] = values;
+        // xyY to XYZ, then XYZ to linear sRGB
+        let big_x = x / y * luminance;
+        let big_z = (1.0 - x - y) / y * luminance;
+        Color::new(
+            3.2406 * big_x - 1.5372 * luminance - 0.4986 * big_z,
+            -0.9689 * big_x + 1.8758 * luminance + 0.0415 * big_z,
+            0.0557 * big_x - 0.2040 * luminance + 1.0570 * big_z,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::textures::PlainColorTexture;
+
+    #[test]
+    fn cube_map_axis_directions_sample_matching_face() {
+        let face_colors = [
+            Color::new(1.0, 0.0, 0.0),
+            Color::new(0.0, 1.0, 0.0),
+            Color::new(0.0, 0.0, 1.0),
+            Color::new(1.0, 1.0, 0.0),
+            Color::new(0.0, 1.0, 1.0),
+            Color::new(1.0, 0.0, 1.0),
+        ];
+        let plain = |index: usize| -> Box<dyn Texture> {
+            Box::new(PlainColorTexture {
+                color: face_colors[index].clone(),
+            })
+        };
+        let cube_map = CubeMap::new([plain(0), plain(1), plain(2), plain(3), plain(4), plain(5)]);
+        let directions = [
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(-1.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, -1.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(0.0, 0.0, -1.0),
+        ];
+        for (direction, expected) in directions.iter().zip(face_colors.iter()) {
+            let color = cube_map.color_in_direction(*direction);
+            assert!((color.red() - expected.red()).abs() < 1e-9);
+            assert!((color.green() - expected.green()).abs() < 1e-9);
+            assert!((color.blue() - expected.blue()).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn cube_map_face_coordinates_are_in_unit_interval() {
+        let directions = [
+            Vec3::new(1.0, 0.9, -0.9),
+            Vec3::new(-0.2, -1.0, 0.99),
+            Vec3::new(0.5, -0.5, -1.0),
+        ];
+        for direction in directions.iter() {
+            let (_, u, v) = CubeMap::face_mapping(*direction);
+            assert!((0.0..=1.0).contains(&u) && (0.0..=1.0).contains(&v));
+        }
+    }
+
+    #[test]
+    fn sky_is_brighter_toward_the_sun() {
+        let sun_direction = Vec3::new(0.0, 0.5, 1.0);
+        let sky = PreethamSky::new(sun_direction, 3.0);
+        let toward_sun = sky.color_in_direction(sun_direction).luminance();
+        let opposite = sky
+            .color_in_direction(Vec3::new(0.0, 0.5, -1.0))
+            .luminance();
+        assert!(
+            toward_sun > 2.0 * opposite,
+            "{} vs {}",
+            toward_sun,
+            opposite
+        );
+        let zenith = sky.color_in_direction(Vec3::new(0.0, 1.0, 0.0));
+        assert!(zenith.blue() > zenith.red());
+    }
+}
@@ -22,10 +22,14 @@ OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 SOFTWARE.
 */
 
+pub mod animation;
 pub mod cameras;
 pub mod colors;
+pub mod compose;
+pub mod environments;
 pub mod lights;
 pub(crate) mod parser;
+pub mod photon;
 pub mod primitives;
 pub mod ray_algorithm;
 pub mod renderer;
@@ -34,7 +38,7 @@ pub mod scene;
 pub mod textures;
 pub mod vector;
 
-#[cfg(target_arch = "wasm32")]
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
 pub mod wasm;
 
 mod utils;
@@ -43,7 +47,7 @@ mod utils;
 /* Does not work for now
 use wasm_bindgen::prelude::*;
 #[wasm_bindgen(start)]
-#[cfg(target_arch = "wasm32")]
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
 pub fn wasm_auto_init() -> Result<(), JsValue> {
     wasm::wasm_init();
     Ok(())
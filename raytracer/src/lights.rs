@@ -27,16 +27,38 @@ use crate::primitives::Ray;
 use crate::vector::Vec3;
 use serde::Deserialize;
 use std::f64::consts::PI;
+use std::fmt::Debug;
 
-pub trait AnyLightObject: Send + Sync {
+pub trait AnyLightObject: Send + Sync + Debug {
     fn source(&self) -> Vec3;
+    /// Direction from `point` toward the light, and distance to the light, which is
+    /// infinite for a light infinitely far away
+    fn direction_from(&self, point: Vec3) -> (Vec3, f64) {
+        let to_light = Vec3::between_points(point, self.source());
+        (to_light.normalize(), to_light.norm())
+    }
     fn color_for_ray(&self, ray: Ray) -> Color;
+    /// Color of the light where it is the brightest
+    fn peak_color(&self) -> Color;
+    /// Scale applied to the light contribution; a negative value removes light
+    fn intensity(&self) -> f64;
+    fn set_intensity(&mut self, intensity: f64);
+    /// Move the light, lights without a source position ignore it
+    fn set_source(&mut self, _source: Vec3) {}
+    /// Change the light color, lights without a single color ignore it
+    fn set_color(&mut self, _color: Color) {}
+}
+
+pub(crate) fn default_intensity() -> f64 {
+    1.0
 }
 
 #[derive(Debug, Deserialize)]
 pub struct LightPoint {
     source: Vec3,
     color: Color,
+    #[serde(default = "default_intensity")]
+    intensity: f64,
 }
 
 impl LightPoint {
@@ -44,11 +66,24 @@ impl LightPoint {
         LightPoint {
             source,
             color: Color::WHITE,
+            intensity: default_intensity(),
         }
     }
 
     pub fn with_color(source: Vec3, color: Color) -> Self {
-        LightPoint { source, color }
+        LightPoint {
+            color,
+            ..LightPoint::new(source)
+        }
+    }
+
+    /// Light with the color of a black body at a temperature in Kelvin
+    pub fn with_temperature(source: Vec3, kelvin: f64) -> Self {
+        LightPoint::with_color(source, Color::from_temperature(kelvin))
+    }
+
+    pub fn with_intensity(self, intensity: f64) -> Self {
+        LightPoint { intensity, ..self }
     }
 }
 
@@ -60,6 +95,96 @@ impl AnyLightObject for LightPoint {
     fn color_for_ray(&self, _ray: Ray) -> Color {
         self.color.clone()
     }
+
+    fn peak_color(&self) -> Color {
+        self.color.clone()
+    }
+
+    fn intensity(&self) -> f64 {
+        self.intensity
+    }
+
+    fn set_intensity(&mut self, intensity: f64) {
+        self.intensity = intensity;
+    }
+
+    fn set_source(&mut self, source: Vec3) {
+        self.source = source;
+    }
+
+    fn set_color(&mut self, color: Color) {
+        self.color = color;
+    }
+}
+
+/// Distance at which the source of a `DirectionalLight` is placed, for the uses
+/// which need a position, like the camera seen from a light
+const DIRECTIONAL_LIGHT_DISTANCE: f64 = 1e6;
+
+/// Light coming from infinitely far away, like the sun, whose rays all follow
+/// `direction`
+#[derive(Debug)]
+pub struct DirectionalLight {
+    direction: Vec3,
+    color: Color,
+    intensity: f64,
+}
+
+impl DirectionalLight {
+    pub fn new(direction: Vec3) -> Self {
+        DirectionalLight {
+            direction: direction.normalize(),
+            color: Color::WHITE,
+            intensity: default_intensity(),
+        }
+    }
+
+    pub fn with_color(direction: Vec3, color: Color) -> Self {
+        DirectionalLight {
+            color,
+            ..DirectionalLight::new(direction)
+        }
+    }
+
+    pub fn with_intensity(self, intensity: f64) -> Self {
+        DirectionalLight { intensity, ..self }
+    }
+}
+
+impl AnyLightObject for DirectionalLight {
+    /// Far point from which the light comes, seen from the origin
+    fn source(&self) -> Vec3 {
+        -DIRECTIONAL_LIGHT_DISTANCE * self.direction
+    }
+
+    fn direction_from(&self, _point: Vec3) -> (Vec3, f64) {
+        (-self.direction, f64::INFINITY)
+    }
+
+    fn color_for_ray(&self, _ray: Ray) -> Color {
+        self.color.clone()
+    }
+
+    fn peak_color(&self) -> Color {
+        self.color.clone()
+    }
+
+    fn intensity(&self) -> f64 {
+        self.intensity
+    }
+
+    fn set_intensity(&mut self, intensity: f64) {
+        self.intensity = intensity;
+    }
+
+    /// The light then comes from `source`, toward the origin
+    fn set_source(&mut self, source: Vec3) {
+        self.direction = -source.normalize();
+    }
+
+    fn set_color(&mut self, color: Color) {
+        self.color = color;
+    }
 }
 
 pub struct AmbientLight {
@@ -73,6 +198,8 @@ pub struct SpotLight {
     direction: Vec3,
     inner_angle: f64,
     outer_angle: f64,
+    #[serde(default = "default_intensity")]
+    intensity: f64,
 }
 
 impl SpotLight {
@@ -88,6 +215,7 @@ impl SpotLight {
             color: Color::WHITE,
             inner_angle: inner_angle_degree * 2.0 * PI / 360.0,
             outer_angle: outer_angle_degree * 2.0 * PI / 360.0,
+            intensity: default_intensity(),
         }
     }
 
@@ -103,6 +231,10 @@ impl SpotLight {
             ..SpotLight::new(source, direction, inner_angle_degree, outer_angle_degree)
         }
     }
+
+    pub fn with_intensity(self, intensity: f64) -> Self {
+        SpotLight { intensity, ..self }
+    }
 }
 
 impl AnyLightObject for SpotLight {
@@ -121,4 +253,24 @@ impl AnyLightObject for SpotLight {
             luminosity * self.color.clone()
         }
     }
+
+    fn peak_color(&self) -> Color {
+        self.color.clone()
+    }
+
+    fn intensity(&self) -> f64 {
+        self.intensity
+    }
+
+    fn set_intensity(&mut self, intensity: f64) {
+        self.intensity = intensity;
+    }
+
+    fn set_source(&mut self, source: Vec3) {
+        self.source = source;
+    }
+
+    fn set_color(&mut self, color: Color) {
+        self.color = color;
+    }
 }
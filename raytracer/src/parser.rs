@@ -22,45 +22,129 @@ OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 SOFTWARE
 */
 
-use crate::cameras::{OrthogonalCamera, PerspectiveCamera};
+use crate::animation::{Animation, CameraTracks, LightTracks, Track};
+use crate::cameras::{
+    BlendedCamera, OrthogonalCamera, PerspectiveCamera, PushBroomCamera, ThinLensCamera,
+};
 use crate::colors::Color;
-use crate::lights::{AnyLightObject, LightPoint, SpotLight};
-use crate::primitives::{InfinitePlan, Shape, Sphere, SquarePlan};
+use crate::environments::{CubeMap, Environment, PreethamSky, TextureEnvironment};
+use crate::lights::{default_intensity, AnyLightObject, DirectionalLight, LightPoint, SpotLight};
+use crate::primitives::{
+    tessellate_sphere, AxisAlignedBox, Cylinder, Frustum, InfinitePlan, Shape, Sphere, SquarePlan,
+    Triangle,
+};
 use crate::result::RaytracerError;
 use crate::result::Result;
-use crate::scene::{RayEmitter, Scene, SceneConfiguration, SceneObject};
-use crate::textures::{CheckedPattern, PlainColorTexture, Texture, TextureEffects, GradientColorTexture};
+use crate::scene::{RayEmitter, RayVisibility, Scene, SceneConfiguration, SceneObject};
+use crate::textures::{
+    CheckedPattern, ColorRampTexture, GradientColorTexture, ImageTexture, PlainColorTexture,
+    RampInput, Texture, TextureEffects, TextureWrap, TransformedTexture, UvTransform,
+};
 use crate::vector::Vec3;
 use log::{info, trace};
 use serde::Deserialize;
+use std::cmp::Ordering;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 pub(crate) fn parse_scene_description(scene_str: &str) -> Result<Scene> {
+    parse_scene_description_from(scene_str, Path::new(""))
+}
+
+/// Parse a scene, relative file paths being resolved from `base_path`
+pub(crate) fn parse_scene_description_from(scene_str: &str, base_path: &Path) -> Result<Scene> {
     let root_document = toml::from_str::<ModelRoot>(scene_str)
         .map_err(|e| RaytracerError::ParsingError(e.to_string()))?;
     trace!("Parsed scene description: {:#?}", root_document);
     if let Some(description) = root_document.description {
         info!("Generating scene for: {}", description);
     }
-    let config = root_document.config;
-    let camera = root_document.camera.into_ray_emitter();
+    let ModelConfig {
+        scene: config,
+        default_material,
+        background,
+    } = root_document.config;
+    let mut animation = Animation::default();
     let lights = root_document
         .light
         .into_iter()
-        .map(DescriptionLight::into_any_light_object)
-        .collect();
-    let objects = root_document
+        .enumerate()
+        .map(|(index, ModelLight { light, keyframes })| {
+            if !keyframes.is_empty() {
+                animation.lights.push((index, light_tracks(&keyframes)));
+            }
+            light.into_any_light_object()
+        })
+        .collect::<Result<_>>()?;
+    let mut named_objects: Vec<(String, SceneObject)> = root_document
         .object
         .into_iter()
-        .map(DescriptionObject::into_scene_object)
+        .map(|object| {
+            let name = object.description.clone();
+            Ok((
+                name,
+                object.into_scene_object(&default_material, base_path)?,
+            ))
+        })
+        .collect::<Result<_>>()?;
+    if config.sort_objects {
+        named_objects.sort_by(|(name_a, object_a), (name_b, object_b)| {
+            let centroid_a = object_a.shape.centroid();
+            let centroid_b = object_b.shape.centroid();
+            [
+                centroid_a.x.partial_cmp(&centroid_b.x),
+                centroid_a.y.partial_cmp(&centroid_b.y),
+                centroid_a.z.partial_cmp(&centroid_b.z),
+            ]
+            .iter()
+            .map(|ordering| ordering.unwrap_or(Ordering::Equal))
+            .find(|&ordering| ordering != Ordering::Equal)
+            .unwrap_or_else(|| name_a.cmp(name_b))
+        });
+    }
+    let ModelCamera {
+        camera,
+        keyframes: camera_keyframes,
+    } = root_document.camera;
+    if !camera_keyframes.is_empty() {
+        if !matches!(
+            camera,
+            DescriptionCamera::Perspective { .. } | DescriptionCamera::ThinLens { .. }
+        ) {
+            return Err(RaytracerError::ParsingError(
+                "Only a perspective or thin lens camera can have keyframes".to_string(),
+            ));
+        }
+        animation.camera = camera_tracks(&camera_keyframes);
+    }
+    let camera = camera.into_ray_emitter(&named_objects)?;
+    let objects = named_objects
+        .into_iter()
+        .map(|(_, object)| object)
         .collect();
 
-    Ok(Scene {
+    let mut config = config;
+    config.animation = animation;
+    config.environment = match (root_document.environment, background) {
+        (Some(_), Some(_)) => {
+            return Err(RaytracerError::ParsingError(
+                "A scene cannot have both an environment and a background texture".to_string(),
+            ))
+        }
+        (Some(environment), None) => Some(environment.into_environment(base_path)?),
+        (None, Some(texture)) => Some(Box::new(TextureEnvironment::new(
+            texture.into_texture(base_path)?,
+        ))),
+        (None, None) => None,
+    };
+    let mut scene = Scene {
         camera,
         lights,
         objects,
         config,
-    })
+    };
+    scene.apply_light_normalization();
+    Ok(scene)
 }
 
 #[derive(Debug, Deserialize)]
@@ -68,10 +152,39 @@ pub(crate) fn parse_scene_description(scene_str: &str) -> Result<Scene> {
 pub struct ModelRoot {
     description: Option<String>,
     #[serde(default)]
-    config: SceneConfiguration,
-    camera: DescriptionCamera,
+    config: ModelConfig,
+    camera: ModelCamera,
+    environment: Option<DescriptionEnvironment>,
     object: Vec<DescriptionObject>,
-    light: Vec<DescriptionLight>,
+    light: Vec<ModelLight>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ModelConfig {
+    #[serde(flatten)]
+    scene: SceneConfiguration,
+    #[serde(default)]
+    default_material: DescriptionMaterial,
+    /// Texture wrapped around the scene, as an alternative to `[environment]`
+    #[serde(default)]
+    background: Option<ModelTexture>,
+}
+
+/// Texture and effects used by objects which do not define their own
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+struct DescriptionMaterial {
+    texture: ModelTexture,
+    effect: TextureEffects,
+}
+
+impl Default for DescriptionMaterial {
+    fn default() -> Self {
+        DescriptionMaterial {
+            texture: ModelTexture::Plain(Default::default()),
+            effect: Default::default(),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -89,42 +202,217 @@ impl From<ModelColor> for Color {
         match model_color {
             ModelColor::ByString(value) => Color::from_str(&value).unwrap(),
             ModelColor::ByRGB(rgb) => Color::new(rgb[0], rgb[1], rgb[2]),
+            ModelColor::ByRGB255 { r, g, b } => Color::from_rgb8([r, g, b]),
         }
     }
 }
 
+#[derive(Debug, Deserialize)]
+struct ModelLight {
+    #[serde(flatten)]
+    light: DescriptionLight,
+    #[serde(default)]
+    keyframes: Vec<ModelLightKeyframe>,
+}
+
+/// Values of light properties at a given time, properties being optional
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ModelLightKeyframe {
+    time: f64,
+    source: Option<Vec3>,
+    color: Option<Color>,
+    intensity: Option<f64>,
+}
+
+fn light_tracks(keyframes: &[ModelLightKeyframe]) -> LightTracks {
+    LightTracks {
+        source: Track::new(
+            keyframes
+                .iter()
+                .filter_map(|keyframe| Some((keyframe.time, keyframe.source?)))
+                .collect(),
+        ),
+        color: Track::new(
+            keyframes
+                .iter()
+                .filter_map(|keyframe| Some((keyframe.time, keyframe.color.clone()?)))
+                .collect(),
+        ),
+        intensity: Track::new(
+            keyframes
+                .iter()
+                .filter_map(|keyframe| Some((keyframe.time, keyframe.intensity?)))
+                .collect(),
+        ),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelCamera {
+    #[serde(flatten)]
+    camera: DescriptionCamera,
+    #[serde(default)]
+    keyframes: Vec<ModelCameraKeyframe>,
+}
+
+/// Position and target of the camera at a given time
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ModelCameraKeyframe {
+    time: f64,
+    eye: Vec3,
+    look_at: Vec3,
+}
+
+fn camera_tracks(keyframes: &[ModelCameraKeyframe]) -> CameraTracks {
+    CameraTracks {
+        eye: Track::new(
+            keyframes
+                .iter()
+                .map(|keyframe| (keyframe.time, keyframe.eye))
+                .collect(),
+        ),
+        look_at: Track::new(
+            keyframes
+                .iter()
+                .map(|keyframe| (keyframe.time, keyframe.look_at))
+                .collect(),
+        ),
+    }
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "snake_case")]
 #[serde(tag = "type")]
 
 enum DescriptionLight {
-    Point(LightPoint),
+    Point {
+        source: Vec3,
+        color: Option<Color>,
+        /// Black body temperature in Kelvin, as an alternative to `color`
+        temperature: Option<f64>,
+        #[serde(default = "default_intensity")]
+        intensity: f64,
+    },
     Spot {
         source: Vec3,
         direction: Vec3,
-        color: Color,
+        color: Option<Color>,
+        /// Black body temperature in Kelvin, as an alternative to `color`
+        temperature: Option<f64>,
+        /// Half angle of the fully lit cone, in degrees
+        #[serde(alias = "inner_angle")]
         inner_angle_degree: f64,
+        /// Half angle beyond which there is no light, in degrees
+        #[serde(alias = "outer_angle")]
         outer_angle_degree: f64,
+        #[serde(default = "default_intensity")]
+        intensity: f64,
+    },
+    /// Light coming from infinitely far away, its rays following `direction`
+    Directional {
+        direction: Vec3,
+        color: Option<Color>,
+        /// Black body temperature in Kelvin, as an alternative to `color`
+        temperature: Option<f64>,
+        #[serde(default = "default_intensity")]
+        intensity: f64,
     },
 }
 
 impl DescriptionLight {
-    fn into_any_light_object(self) -> Box<dyn AnyLightObject> {
-        match self {
-            DescriptionLight::Point(val) => Box::new(val),
+    fn into_any_light_object(self) -> Result<Box<dyn AnyLightObject>> {
+        Ok(match self {
+            DescriptionLight::Point {
+                source,
+                color,
+                temperature,
+                intensity,
+            } => Box::new(
+                LightPoint::with_color(source, light_color(color, temperature)?)
+                    .with_intensity(intensity),
+            ),
             DescriptionLight::Spot {
                 source,
                 direction,
                 color,
+                temperature,
                 inner_angle_degree,
                 outer_angle_degree,
-            } => Box::new(SpotLight::with_color(
-                source,
+                intensity,
+            } => Box::new(
+                SpotLight::with_color(
+                    source,
+                    direction,
+                    inner_angle_degree,
+                    outer_angle_degree,
+                    light_color(color, temperature)?,
+                )
+                .with_intensity(intensity),
+            ),
+            DescriptionLight::Directional {
                 direction,
-                inner_angle_degree,
-                outer_angle_degree,
                 color,
-            )),
+                temperature,
+                intensity,
+            } => Box::new(
+                DirectionalLight::with_color(direction, light_color(color, temperature)?)
+                    .with_intensity(intensity),
+            ),
+        })
+    }
+}
+
+/// Light color given either directly or as a black body temperature
+fn light_color(color: Option<Color>, temperature: Option<f64>) -> Result<Color> {
+    match (color, temperature) {
+        (Some(color), None) => Ok(color),
+        (None, Some(kelvin)) => Ok(Color::from_temperature(kelvin)),
+        (Some(_), Some(_)) => Err(RaytracerError::ParsingError(
+            "A light cannot have both a color and a temperature".to_string(),
+        )),
+        (None, None) => Err(RaytracerError::ParsingError(
+            "A light needs a color or a temperature".to_string(),
+        )),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[serde(tag = "type")]
+#[serde(deny_unknown_fields)]
+enum DescriptionEnvironment {
+    /// Image paths in the order: +X, -X, +Y, -Y, +Z, -Z
+    Cubemap { faces: [PathBuf; 6] },
+    /// Analytic daylight sky, the Y axis being the zenith
+    Sky {
+        sun_direction: Vec3,
+        #[serde(default = "default_sky_turbidity")]
+        turbidity: f64,
+    },
+}
+
+impl DescriptionEnvironment {
+    fn into_environment(self, base_path: &Path) -> Result<Box<dyn Environment>> {
+        match self {
+            DescriptionEnvironment::Cubemap { faces } => {
+                let load = |path: &PathBuf| -> Result<Box<dyn Texture>> {
+                    Ok(Box::new(ImageTexture::from_file(base_path.join(path))?))
+                };
+                Ok(Box::new(CubeMap::new([
+                    load(&faces[0])?,
+                    load(&faces[1])?,
+                    load(&faces[2])?,
+                    load(&faces[3])?,
+                    load(&faces[4])?,
+                    load(&faces[5])?,
+                ])))
+            }
+            DescriptionEnvironment::Sky {
+                sun_direction,
+                turbidity,
+            } => Ok(Box::new(PreethamSky::new(sun_direction, turbidity))),
         }
     }
 }
@@ -135,59 +423,231 @@ impl DescriptionLight {
 #[serde(deny_unknown_fields)]
 enum DescriptionCamera {
     Perspective {
-        screen_center: Vec3,
-        look_at: Vec3,
+        #[serde(alias = "screen_center")]
+        position: Vec3,
+        look_at: ModelLookAt,
+        up: Option<Vec3>,
         width: f64,
         height: f64,
         #[serde(default = "default_perspective_angle")]
         angle_degree: f64,
     },
     Orthogonal {
-        eye: Vec3,
-        look_at: Vec3,
+        #[serde(alias = "eye")]
+        position: Vec3,
+        look_at: ModelLookAt,
+        up: Option<Vec3>,
+        width: f64,
+        height: f64,
+    },
+    Blended {
+        #[serde(alias = "screen_center")]
+        position: Vec3,
+        look_at: ModelLookAt,
+        up: Option<Vec3>,
         width: f64,
         height: f64,
+        #[serde(default = "default_perspective_angle")]
+        angle_degree: f64,
+        blend: f64,
+    },
+    PushBroom {
+        #[serde(alias = "screen_center")]
+        position: Vec3,
+        look_at: ModelLookAt,
+        up: Option<Vec3>,
+        travel: Vec3,
+        width: f64,
+        height: f64,
+        #[serde(default = "default_perspective_angle")]
+        angle_degree: f64,
+    },
+    ThinLens {
+        #[serde(alias = "screen_center")]
+        position: Vec3,
+        look_at: ModelLookAt,
+        up: Option<Vec3>,
+        width: f64,
+        height: f64,
+        #[serde(default = "default_perspective_angle")]
+        angle_degree: f64,
+        aperture: f64,
+        focus_distance: f64,
+        #[serde(default)]
+        blades: u32,
     },
 }
 
-impl DescriptionCamera {
-    fn into_ray_emitter(self) -> Box<dyn RayEmitter> {
+/// Camera target, either a point or the centroid of a named object
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ModelLookAt {
+    Point(Vec3),
+    Object { object: String },
+}
+
+impl ModelLookAt {
+    fn resolve(self, named_objects: &[(String, SceneObject)]) -> Result<Vec3> {
         match self {
+            ModelLookAt::Point(point) => Ok(point),
+            ModelLookAt::Object { object } => named_objects
+                .iter()
+                .find(|(name, _)| *name == object)
+                .map(|(_, scene_object)| scene_object.shape.centroid())
+                .ok_or_else(|| {
+                    RaytracerError::ParsingError(format!(
+                        "Camera looks at unknown object: {}",
+                        object
+                    ))
+                }),
+        }
+    }
+}
+
+impl DescriptionCamera {
+    fn into_ray_emitter(
+        self,
+        named_objects: &[(String, SceneObject)],
+    ) -> Result<Box<dyn RayEmitter>> {
+        let camera: Box<dyn RayEmitter> = match self {
             DescriptionCamera::Perspective {
-                screen_center,
+                position,
                 look_at,
+                up,
                 width,
                 height,
                 angle_degree,
-            } => Box::new(PerspectiveCamera::new(
-                screen_center,
+            } => {
+                let camera = PerspectiveCamera::new(
+                    position,
+                    look_at.resolve(named_objects)?,
+                    width,
+                    height,
+                    angle_degree,
+                );
+                match up {
+                    Some(up) => Box::new(camera.with_up(up)),
+                    None => Box::new(camera),
+                }
+            }
+            DescriptionCamera::Orthogonal {
+                position,
                 look_at,
+                up,
+                width,
+                height,
+            } => {
+                let camera =
+                    OrthogonalCamera::new(position, look_at.resolve(named_objects)?, width, height);
+                match up {
+                    Some(up) => Box::new(camera.with_up(up)),
+                    None => Box::new(camera),
+                }
+            }
+            DescriptionCamera::Blended {
+                position,
+                look_at,
+                up,
                 width,
                 height,
                 angle_degree,
-            )),
-            DescriptionCamera::Orthogonal {
-                eye,
+                blend,
+            } => {
+                let camera = BlendedCamera::new(
+                    position,
+                    look_at.resolve(named_objects)?,
+                    width,
+                    height,
+                    angle_degree,
+                    blend,
+                );
+                match up {
+                    Some(up) => Box::new(camera.with_up(up)),
+                    None => Box::new(camera),
+                }
+            }
+            DescriptionCamera::PushBroom {
+                position,
                 look_at,
+                up,
+                travel,
                 width,
                 height,
-            } => Box::new(OrthogonalCamera::new(eye, look_at, width, height)),
-        }
+                angle_degree,
+            } => {
+                let camera = PushBroomCamera::new(
+                    position,
+                    look_at.resolve(named_objects)?,
+                    travel,
+                    width,
+                    height,
+                    angle_degree,
+                );
+                match up {
+                    Some(up) => Box::new(camera.with_up(up)),
+                    None => Box::new(camera),
+                }
+            }
+            DescriptionCamera::ThinLens {
+                position,
+                look_at,
+                up,
+                width,
+                height,
+                angle_degree,
+                aperture,
+                focus_distance,
+                blades,
+            } => {
+                let camera = PerspectiveCamera::new(
+                    position,
+                    look_at.resolve(named_objects)?,
+                    width,
+                    height,
+                    angle_degree,
+                );
+                let camera = match up {
+                    Some(up) => camera.with_up(up),
+                    None => camera,
+                };
+                Box::new(ThinLensCamera::new(camera, aperture, focus_distance).with_blades(blades))
+            }
+        };
+        Ok(camera)
     }
 }
 
 #[derive(Debug, Deserialize)]
 struct DescriptionObject {
     description: String,
-    texture: ModelTexture,
+    #[serde(default)]
+    texture: Option<ModelTexture>,
     #[serde(default)]
     effect: Option<TextureEffects>,
+    #[serde(default)]
+    holdout: bool,
+    #[serde(default)]
+    shadow_catcher: bool,
+    #[serde(default = "default_ray_visibility")]
+    visible_to_camera: bool,
+    #[serde(default = "default_ray_visibility")]
+    visible_to_reflections: bool,
+    #[serde(default = "default_ray_visibility")]
+    visible_to_refractions: bool,
+    #[serde(default = "default_ray_visibility")]
+    casts_shadows: bool,
+    #[serde(default)]
+    uv_transform: Option<UvTransform>,
     #[serde(flatten)]
     shape: ModelShape,
 }
 
 impl DescriptionObject {
-    fn into_scene_object(self) -> SceneObject {
+    fn into_scene_object(
+        self,
+        default_material: &DescriptionMaterial,
+        base_path: &Path,
+    ) -> Result<SceneObject> {
         let shape: Box<dyn Shape> = match self.shape {
             ModelShape::Sphere(val) => Box::new(val),
             ModelShape::InfinitePlan { center, normal } => {
@@ -198,18 +658,48 @@ impl DescriptionObject {
                 normal,
                 width,
             } => Box::new(SquarePlan::new(center, normal, width)),
+            ModelShape::TessellatedSphere {
+                center,
+                radius,
+                segments,
+            } => Box::new(tessellate_sphere(center, radius, segments)),
+            ModelShape::Frustum {
+                base,
+                axis,
+                bottom_radius,
+                top_radius,
+                height,
+            } => Box::new(Frustum::new(base, axis, bottom_radius, top_radius, height)),
+            ModelShape::Cylinder {
+                center,
+                axis,
+                radius,
+                height,
+            } => Box::new(Cylinder::new(center, axis, radius, height)),
+            ModelShape::Triangle { v0, v1, v2 } => Box::new(Triangle::new(v0, v1, v2)),
+            ModelShape::AxisAlignedBox { min, max } => Box::new(AxisAlignedBox::new(min, max)),
         };
-        let texture: Box<dyn Texture> = match self.texture {
-            ModelTexture::Checked(val) => Box::new(val),
-            ModelTexture::Plain(val) => Box::new(val),
-            ModelTexture::Gradient(val) => Box::new(val),
+        let texture = self
+            .texture
+            .unwrap_or_else(|| default_material.texture.clone())
+            .into_texture(base_path)?;
+        let texture: Box<dyn Texture> = match self.uv_transform {
+            Some(transform) => Box::new(TransformedTexture { texture, transform }),
+            None => texture,
         };
-        let effects = self.effect.unwrap_or_default();
-        SceneObject {
-            texture,
-            shape,
-            effects,
-        }
+        let effects = self
+            .effect
+            .unwrap_or_else(|| default_material.effect.clone());
+        Ok(SceneObject::new(shape, texture)
+            .with_effects(effects)
+            .with_holdout(self.holdout)
+            .with_shadow_catcher(self.shadow_catcher)
+            .with_visibility(RayVisibility {
+                camera: self.visible_to_camera,
+                reflections: self.visible_to_reflections,
+                refractions: self.visible_to_refractions,
+                shadows: self.casts_shadows,
+            }))
     }
 }
 
@@ -227,15 +717,80 @@ enum ModelShape {
         normal: Vec3,
         width: f64,
     },
+    TessellatedSphere {
+        center: Vec3,
+        radius: f64,
+        segments: u32,
+    },
+    Frustum {
+        base: Vec3,
+        #[serde(default = "default_shape_axis")]
+        axis: Vec3,
+        bottom_radius: f64,
+        top_radius: f64,
+        height: f64,
+    },
+    Cylinder {
+        center: Vec3,
+        #[serde(default = "default_shape_axis")]
+        axis: Vec3,
+        radius: f64,
+        height: f64,
+    },
+    Triangle {
+        v0: Vec3,
+        v1: Vec3,
+        v2: Vec3,
+    },
+    #[serde(rename = "box")]
+    AxisAlignedBox {
+        min: Vec3,
+        max: Vec3,
+    },
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "snake_case")]
 #[serde(tag = "type")]
 enum ModelTexture {
     Checked(CheckedPattern),
     Plain(PlainColorTexture),
     Gradient(GradientColorTexture),
+    /// Color stops as `[position, color]` pairs
+    Ramp {
+        stops: Vec<(f64, Color)>,
+        #[serde(default)]
+        input: RampInput,
+    },
+    /// PNG or JPEG file, relative to the scene file
+    Image {
+        path: PathBuf,
+        #[serde(default)]
+        wrap: TextureWrap,
+        /// False for data maps, like normal or roughness maps, stored in linear space
+        #[serde(default = "default_image_is_srgb")]
+        is_srgb: bool,
+    },
+}
+
+impl ModelTexture {
+    fn into_texture(self, base_path: &Path) -> Result<Box<dyn Texture>> {
+        Ok(match self {
+            ModelTexture::Checked(val) => Box::new(val),
+            ModelTexture::Plain(val) => Box::new(val),
+            ModelTexture::Gradient(val) => Box::new(val),
+            ModelTexture::Ramp { stops, input } => Box::new(ColorRampTexture::new(stops, input)),
+            ModelTexture::Image {
+                path,
+                wrap,
+                is_srgb,
+            } => {
+                let mut texture = ImageTexture::from_file(base_path.join(path))?.with_wrap(wrap);
+                texture.is_srgb = is_srgb;
+                Box::new(texture)
+            }
+        })
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -243,22 +798,868 @@ enum ModelTexture {
 pub(crate) enum ModelColor {
     ByString(String),
     ByRGB([f64; 3]),
+    /// Integer components between 0 and 255, e.g. `{ r = 255, g = 128, b = 0 }`
+    ByRGB255 {
+        r: u8,
+        g: u8,
+        b: u8,
+    },
 }
 
 fn default_perspective_angle() -> f64 {
     std::f64::consts::PI / 8.0
 }
 
+fn default_sky_turbidity() -> f64 {
+    3.0
+}
+
+fn default_shape_axis() -> Vec3 {
+    Vec3::new(0.0, 1.0, 0.0)
+}
+
+fn default_ray_visibility() -> bool {
+    true
+}
+
+fn default_image_is_srgb() -> bool {
+    true
+}
+
 #[cfg(test)]
 mod tests {
 
     use super::*;
+    use crate::primitives::Ray;
 
     const INVALID_TOML: &str = r##"invalid_toml"##;
 
+    fn parsed_camera(scene_str: &str) -> DescriptionCamera {
+        toml::from_str::<ModelRoot>(scene_str)
+            .unwrap()
+            .camera
+            .camera
+    }
+
     #[test]
     fn invalid_toml_string() {
         let result = parse_scene_description(INVALID_TOML);
         assert!(result.is_err());
     }
+
+    #[derive(Deserialize)]
+    struct ColorHolder {
+        color: Color,
+    }
+
+    #[test]
+    fn color_from_255_integer_table() {
+        let holder: ColorHolder = toml::from_str("color = { r = 255, g = 51, b = 0 }").unwrap();
+        assert!((holder.color.red() - 1.0).abs() < 1e-9);
+        assert!((holder.color.green() - 0.2).abs() < 1e-9);
+        assert!((holder.color.blue() - 0.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn color_from_float_array_still_supported() {
+        let holder: ColorHolder = toml::from_str("color = [0.5, 0.25, 1.0]").unwrap();
+        assert!((holder.color.red() - 0.5).abs() < 1e-9);
+        assert!((holder.color.green() - 0.25).abs() < 1e-9);
+    }
+
+    #[test]
+    fn color_from_255_out_of_range_is_error() {
+        let result = toml::from_str::<ColorHolder>("color = { r = 256, g = 0, b = 0 }");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn blended_camera_is_parsed() {
+        let scene_str = r#"
+        light = []
+        object = []
+
+        [camera]
+        type = "blended"
+        blend = 0.5
+        screen_center = [0, 0, -10]
+        look_at = [0, 0, 0]
+        width = 16
+        height = 9
+        "#;
+        match parsed_camera(scene_str) {
+            DescriptionCamera::Blended {
+                position,
+                blend,
+                angle_degree,
+                ..
+            } => {
+                assert!(position.distance(Vec3::new(0.0, 0.0, -10.0)) < 1e-9);
+                assert!((blend - 0.5).abs() < 1e-9);
+                assert!((angle_degree - default_perspective_angle()).abs() < 1e-9);
+            }
+            camera => panic!("Unexpected camera: {:?}", camera),
+        }
+        assert!(parse_scene_description(scene_str).is_ok());
+    }
+
+    #[test]
+    fn push_broom_camera_is_parsed() {
+        let scene_str = r#"
+        light = []
+        object = []
+
+        [camera]
+        type = "push_broom"
+        travel = [0, 0, 10]
+        position = [0, 0, -10]
+        look_at = [0, 0, 0]
+        width = 16
+        height = 9
+        "#;
+        match parsed_camera(scene_str) {
+            DescriptionCamera::PushBroom { travel, .. } => {
+                assert!(travel.distance(Vec3::new(0.0, 0.0, 10.0)) < 1e-9);
+            }
+            camera => panic!("Unexpected camera: {:?}", camera),
+        }
+        let scene = parse_scene_description(scene_str).unwrap();
+        let first_row = scene.camera.generate_ray(0.5, 0.0);
+        let last_row = scene.camera.generate_ray(0.5, 1.0);
+        assert!(first_row.source.distance(Vec3::new(0.0, 0.0, -10.0)) < 1e-9);
+        assert!(last_row.source.distance(Vec3::new(0.0, 0.0, 0.0)) < 1e-9);
+    }
+
+    #[test]
+    fn thin_lens_camera_is_parsed() {
+        let scene_str = r#"
+        light = []
+        object = []
+
+        [camera]
+        type = "thin_lens"
+        aperture = 0.5
+        focus_distance = 10
+        position = [0, 0, -10]
+        look_at = [0, 0, 0]
+        width = 16
+        height = 9
+        "#;
+        match parsed_camera(scene_str) {
+            DescriptionCamera::ThinLens {
+                aperture,
+                focus_distance,
+                blades,
+                ..
+            } => {
+                assert!((aperture - 0.5).abs() < 1e-9);
+                assert!((focus_distance - 10.0).abs() < 1e-9);
+                assert_eq!(blades, 0);
+            }
+            camera => panic!("Unexpected camera: {:?}", camera),
+        }
+        let scene = parse_scene_description(scene_str).unwrap();
+        // Moved to the origin, rays converge 10 units ahead
+        let camera = scene
+            .camera
+            .reframed(Vec3::ZERO, Vec3::new(0.0, 0.0, 1.0))
+            .unwrap();
+        let focus_point = Vec3::new(0.0, 0.0, 10.0);
+        for _ in 0..10 {
+            let ray = camera.generate_ray(0.5, 0.5);
+            assert!(ray.source.norm() <= 0.25 + 1e-9);
+            let to_focus = Vec3::between_points(ray.source, focus_point).normalize();
+            assert!(ray.direction.distance(to_focus) < 1e-9);
+        }
+    }
+
+    #[test]
+    fn frustum_is_parsed() {
+        let scene_str = r#"
+        light = []
+
+        [camera]
+        type = "orthogonal"
+        eye = [0, 0, -10]
+        look_at = [0, 0, 0]
+        width = 16
+        height = 9
+
+        [[object]]
+        description = "Lampshade"
+        type = "frustum"
+        base = [10, 0, 0]
+        bottom_radius = 2
+        top_radius = 1
+        height = 3
+        "#;
+        let scene = parse_scene_description(scene_str).unwrap();
+        let lampshade = &scene.objects[0].shape;
+        assert_eq!(lampshade.contains(Vec3::new(11.5, 0.5, 0.0)), Some(true));
+        assert_eq!(lampshade.contains(Vec3::new(11.5, 2.5, 0.0)), Some(false));
+    }
+
+    #[test]
+    fn cylinder_is_parsed() {
+        let scene_str = r#"
+        light = []
+
+        [camera]
+        type = "orthogonal"
+        eye = [0, 0, -10]
+        look_at = [0, 0, 0]
+        width = 16
+        height = 9
+
+        [[object]]
+        description = "Pillar"
+        type = "cylinder"
+        center = [10, 0, 0]
+        radius = 1
+        height = 5
+        "#;
+        let scene = parse_scene_description(scene_str).unwrap();
+        let pillar = &scene.objects[0].shape;
+        assert_eq!(pillar.contains(Vec3::new(10.5, 4.5, 0.0)), Some(true));
+        assert_eq!(pillar.contains(Vec3::new(10.5, 5.5, 0.0)), Some(false));
+    }
+
+    #[test]
+    fn triangle_is_parsed() {
+        let scene_str = r#"
+        light = []
+
+        [camera]
+        type = "orthogonal"
+        eye = [0, 0, -10]
+        look_at = [0, 0, 0]
+        width = 16
+        height = 9
+
+        [[object]]
+        description = "Triangle"
+        type = "triangle"
+        v0 = [10, 0, 0]
+        v1 = [12, 0, 0]
+        v2 = [10, 2, 0]
+        "#;
+        let scene = parse_scene_description(scene_str).unwrap();
+        let ray = Ray::new(Vec3::new(10.5, 0.5, -5.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!(scene.objects[0].shape.check_collision(&ray).is_some());
+    }
+
+    #[test]
+    fn caustics_settings_are_parsed() {
+        let scene_str = r#"
+        light = []
+        object = []
+
+        [config]
+        caustics = { photon_count = 1000 }
+
+        [camera]
+        type = "orthogonal"
+        eye = [0, 0, -10]
+        look_at = [0, 0, 0]
+        width = 16
+        height = 9
+        "#;
+        let scene = parse_scene_description(scene_str).unwrap();
+        let caustics = scene.config.caustics.as_ref().unwrap();
+        assert_eq!(caustics.photon_count, 1000);
+        assert!((caustics.gather_radius - 0.1).abs() < 1e-9);
+        assert!(scene.config.photon_map.is_none());
+    }
+
+    #[test]
+    fn box_is_parsed() {
+        let scene_str = r#"
+        light = []
+
+        [camera]
+        type = "orthogonal"
+        eye = [0, 0, -10]
+        look_at = [0, 0, 0]
+        width = 16
+        height = 9
+
+        [[object]]
+        description = "Crate"
+        type = "box"
+        min = [10, 0, 0]
+        max = [12, 2, 2]
+        "#;
+        let scene = parse_scene_description(scene_str).unwrap();
+        let crate_box = &scene.objects[0].shape;
+        assert_eq!(crate_box.contains(Vec3::new(11.0, 1.0, 1.0)), Some(true));
+        assert_eq!(crate_box.contains(Vec3::new(13.0, 1.0, 1.0)), Some(false));
+    }
+
+    #[test]
+    fn transparency_priority_is_parsed() {
+        let scene_str = r#"
+        light = []
+
+        [camera]
+        type = "orthogonal"
+        eye = [0, 0, -10]
+        look_at = [0, 0, 0]
+        width = 16
+        height = 9
+
+        [[object]]
+        description = "Glass sphere"
+        type = "sphere"
+        center = [0, 0, 0]
+        radius = 1
+        effect.transparency = { refractive_index = 1.3, priority = 2 }
+        "#;
+        let scene = parse_scene_description(scene_str).unwrap();
+        let transparency = scene.objects[0].effects().transparency.as_ref().unwrap();
+        assert_eq!(transparency.priority, 2);
+        assert!((transparency.refractive_index - 1.3).abs() < 1e-9);
+    }
+
+    #[test]
+    fn fresnel_mirror_is_parsed() {
+        let scene_str = r#"
+        light = []
+
+        [camera]
+        type = "orthogonal"
+        eye = [0, 0, -10]
+        look_at = [0, 0, 0]
+        width = 16
+        height = 9
+
+        [[object]]
+        description = "Water sphere"
+        type = "sphere"
+        center = [0, 0, 0]
+        radius = 1
+        effect.mirror = { coeff = 1.0, ior = 1.33 }
+        "#;
+        let scene = parse_scene_description(scene_str).unwrap();
+        let mirror = scene.objects[0].effects().mirror.as_ref().unwrap();
+        assert_eq!(mirror.ior, Some(1.33));
+    }
+
+    #[test]
+    fn ray_visibility_is_parsed() {
+        let scene_str = r#"
+        light = []
+
+        [camera]
+        type = "orthogonal"
+        eye = [0, 0, -10]
+        look_at = [0, 0, 0]
+        width = 16
+        height = 9
+
+        [[object]]
+        description = "Visible sphere"
+        type = "sphere"
+        center = [0, 0, 0]
+        radius = 1
+
+        [[object]]
+        description = "Hidden sphere"
+        type = "sphere"
+        center = [3, 0, 0]
+        radius = 1
+        visible_to_camera = false
+        casts_shadows = false
+        "#;
+        let scene = parse_scene_description(scene_str).unwrap();
+        let visibility = &scene.objects[1].visibility;
+        assert!(!visibility.camera && !visibility.shadows);
+        assert!(visibility.reflections && visibility.refractions);
+        assert!(scene.objects[0].visibility.camera);
+    }
+
+    #[test]
+    fn transmission_color_is_parsed() {
+        let scene_str = r#"
+        light = []
+
+        [camera]
+        type = "orthogonal"
+        eye = [0, 0, -10]
+        look_at = [0, 0, 0]
+        width = 16
+        height = 9
+
+        [[object]]
+        description = "Tinted glass"
+        type = "sphere"
+        center = [0, 0, 0]
+        radius = 1
+        effect.transparency = { refractive_index = 1.3, transmission_color = "red" }
+        "#;
+        let scene = parse_scene_description(scene_str).unwrap();
+        let transparency = scene.objects[0].effects().transparency.as_ref().unwrap();
+        let transmission_color = transparency.transmission_color.as_ref().unwrap();
+        assert!(transmission_color.red() > 0.99 && transmission_color.green() < 0.01);
+    }
+
+    #[test]
+    fn subsurface_wrap_is_parsed() {
+        let scene_str = r#"
+        light = []
+
+        [camera]
+        type = "orthogonal"
+        eye = [0, 0, -10]
+        look_at = [0, 0, 0]
+        width = 16
+        height = 9
+
+        [[object]]
+        description = "Bare sphere"
+        type = "sphere"
+        center = [0, 0, 0]
+        radius = 1
+
+        [[object]]
+        description = "Wax sphere"
+        type = "sphere"
+        center = [3, 0, 0]
+        radius = 1
+        effect.subsurface = { wrap = 0.3 }
+        "#;
+        let scene = parse_scene_description(scene_str).unwrap();
+        let subsurface = scene.objects[1].effects().subsurface.as_ref().unwrap();
+        assert!((subsurface.wrap - 0.3).abs() < 1e-9);
+        assert!(subsurface.back_illumination.abs() < 1e-9);
+        assert!(scene.objects[0].effects().subsurface.is_none());
+    }
+
+    #[test]
+    fn uv_transform_scales_texture_of_its_object_only() {
+        let scene_str = r#"
+        light = []
+
+        [camera]
+        type = "orthogonal"
+        eye = [0, 0, -10]
+        look_at = [0, 0, 0]
+        width = 16
+        height = 9
+
+        [[object]]
+        description = "Checked sphere"
+        type = "sphere"
+        center = [0, 3, 0]
+        radius = 1
+        texture = { type = "checked", count = 4 }
+
+        [[object]]
+        description = "Finely checked sphere"
+        type = "sphere"
+        center = [3, 3, 0]
+        radius = 1
+        texture = { type = "checked", count = 4 }
+        uv_transform = { scale = [2, 2] }
+        "#;
+        let scene = parse_scene_description(scene_str).unwrap();
+        let (plain, scaled) = (&scene.objects[0].texture, &scene.objects[1].texture);
+        for &(u, v) in &[(0.1, 0.1), (0.2, 0.1), (0.3, 0.45), (0.05, 0.4)] {
+            let expected = plain.color_at(2.0 * u, 2.0 * v);
+            assert!((scaled.color_at(u, v).red() - expected.red()).abs() < 1e-9);
+        }
+        // Tiles are twice smaller on the transformed object
+        assert!((scaled.color_at(0.1, 0.1).red() - scaled.color_at(0.2, 0.1).red()).abs() > 0.5);
+        assert!((plain.color_at(0.1, 0.1).red() - plain.color_at(0.2, 0.1).red()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn color_ramp_by_height_is_parsed() {
+        let scene_str = r#"
+        light = []
+
+        [camera]
+        type = "orthogonal"
+        eye = [0, 0, -10]
+        look_at = [0, 0, 0]
+        width = 16
+        height = 9
+
+        [[object]]
+        description = "Terrain"
+        type = "sphere"
+        center = [0, 0, 5]
+        radius = 2
+        texture = { type = "ramp", input = "height", stops = [[-2.0, "blue"], [2.0, "white"]] }
+        "#;
+        let scene = parse_scene_description(scene_str).unwrap();
+        let terrain = &scene.objects[0];
+        let top = terrain.color_at(Vec3::new(0.0, 2.0, 5.0));
+        let middle = terrain.color_at(Vec3::new(2.0, 0.0, 5.0));
+        assert_eq!(top.to_rgb8(), [255, 255, 255]);
+        assert!((middle.red() - 0.5).abs() < 1e-9 && (middle.blue() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn light_keyframes_are_interpolated() {
+        let scene_str = r#"
+        object = []
+
+        [camera]
+        type = "orthogonal"
+        eye = [0, 0, -10]
+        look_at = [0, 0, 0]
+        width = 16
+        height = 9
+
+        [[light]]
+        type = "point"
+        source = [0, 10, 0]
+        color = "white"
+
+        [[light.keyframes]]
+        time = 0.0
+        intensity = 1.0
+
+        [[light.keyframes]]
+        time = 1.0
+        intensity = 0.0
+        source = [0, 20, 0]
+        "#;
+        let mut scene = parse_scene_description(scene_str).unwrap();
+        scene.set_time(0.5);
+        assert!((scene.lights[0].intensity() - 0.5).abs() < 1e-9);
+        // A single keyframe keeps its value
+        assert!(scene.lights[0].source().distance(Vec3::new(0.0, 20.0, 0.0)) < 1e-9);
+    }
+
+    #[test]
+    fn camera_keyframes_move_the_camera() {
+        let scene_str = r#"
+        [camera]
+        type = "perspective"
+        position = [0, 0, -10]
+        look_at = [0, 0, 0]
+        width = 4
+        height = 4
+
+        [[camera.keyframes]]
+        time = 0.0
+        eye = [0, 0, -10]
+        look_at = [0, 0, 0]
+
+        [[camera.keyframes]]
+        time = 1.0
+        eye = [0, 10, -10]
+        look_at = [0, 0, 0]
+
+        [[object]]
+        description = "Sphere"
+        type = "sphere"
+        center = [0, 0, 0]
+        radius = 1
+
+        [[light]]
+        type = "point"
+        source = [0, 10, 0]
+        color = "white"
+        "#;
+        let mut scene = parse_scene_description(scene_str).unwrap();
+        scene.set_time(0.5);
+        let center_ray = scene.camera.generate_ray(0.5, 0.5);
+        let expected_direction = Vec3::new(0.0, -5.0, 10.0).normalize();
+        assert!(center_ray.direction.distance(expected_direction) < 1e-9);
+    }
+
+    #[test]
+    fn keyframes_on_an_orthogonal_camera_are_an_error() {
+        let scene_str = r#"
+        [camera]
+        type = "orthogonal"
+        position = [0, 0, -10]
+        look_at = [0, 0, 0]
+        width = 4
+        height = 4
+
+        [[camera.keyframes]]
+        time = 0.0
+        eye = [0, 0, -10]
+        look_at = [0, 0, 0]
+
+        [[object]]
+        description = "Sphere"
+        type = "sphere"
+        center = [0, 0, 0]
+        radius = 1
+
+        [[light]]
+        type = "point"
+        source = [0, 10, 0]
+        color = "white"
+        "#;
+        assert!(parse_scene_description(scene_str).is_err());
+    }
+
+    #[test]
+    fn holdout_flag_is_parsed() {
+        let scene_str = r#"
+        light = []
+
+        [camera]
+        type = "orthogonal"
+        eye = [0, 0, -10]
+        look_at = [0, 0, 0]
+        width = 16
+        height = 9
+
+        [[object]]
+        description = "Holdout sphere"
+        type = "sphere"
+        center = [0, 0, 0]
+        radius = 1
+        holdout = true
+
+        [[object]]
+        description = "Regular sphere"
+        type = "sphere"
+        center = [3, 0, 0]
+        radius = 1
+        "#;
+        let scene = parse_scene_description(scene_str).unwrap();
+        assert!(scene.objects[0].holdout);
+        assert!(!scene.objects[1].holdout);
+    }
+
+    #[test]
+    fn shadow_catcher_flag_is_parsed() {
+        let scene_str = r#"
+        light = []
+
+        [camera]
+        type = "orthogonal"
+        eye = [0, 0, -10]
+        look_at = [0, 0, 0]
+        width = 16
+        height = 9
+
+        [[object]]
+        description = "Regular sphere"
+        type = "sphere"
+        center = [0, 0, 0]
+        radius = 1
+
+        [[object]]
+        description = "Floor"
+        type = "infinite_plan"
+        center = [0, -1, 0]
+        normal = [0, 1, 0]
+        shadow_catcher = true
+        "#;
+        let scene = parse_scene_description(scene_str).unwrap();
+        assert!(!scene.objects[0].shadow_catcher);
+        assert!(scene.objects[1].shadow_catcher);
+    }
+
+    #[test]
+    fn default_material_used_when_no_texture() {
+        let scene_str = r#"
+        light = []
+
+        [config]
+        default_material.texture.type = "plain"
+        default_material.texture.color = "red"
+
+        [camera]
+        type = "orthogonal"
+        eye = [0, 0, -10]
+        look_at = [0, 0, 0]
+        width = 16
+        height = 9
+
+        [[object]]
+        description = "Bare sphere"
+        type = "sphere"
+        center = [0, 0, 0]
+        radius = 1
+
+        [[object]]
+        description = "Blue sphere"
+        type = "sphere"
+        center = [3, 0, 0]
+        radius = 1
+        texture.type = "plain"
+        texture.color = "blue"
+        "#;
+        let scene = parse_scene_description(scene_str).unwrap();
+        let bare_color = scene.objects[0].texture.color_at(0.5, 0.5);
+        assert!(bare_color.red() > 0.99 && bare_color.blue() < 0.01);
+        let textured_color = scene.objects[1].texture.color_at(0.5, 0.5);
+        assert!(textured_color.blue() > 0.99 && textured_color.red() < 0.01);
+    }
+
+    #[test]
+    fn camera_looks_at_named_object() {
+        let scene_str = r#"
+        light = []
+
+        [camera]
+        type = "orthogonal"
+        eye = [0, 0, -10]
+        look_at = { object = "Blue sphere" }
+        width = 16
+        height = 9
+
+        [[object]]
+        description = "Blue sphere"
+        type = "sphere"
+        center = [3, 0, 0]
+        radius = 1
+        "#;
+        match parsed_camera(scene_str) {
+            DescriptionCamera::Orthogonal {
+                look_at: ModelLookAt::Object { object },
+                ..
+            } => assert_eq!(object, "Blue sphere"),
+            camera => panic!("Unexpected camera: {:?}", camera),
+        }
+        let scene = parse_scene_description(scene_str).unwrap();
+        let center_ray = scene.camera.generate_ray(0.5, 0.5);
+        let expected =
+            Vec3::between_points(Vec3::new(0.0, 0.0, -10.0), Vec3::new(3.0, 0.0, 0.0)).normalize();
+        assert!(center_ray.direction.distance(expected) < 1e-9);
+    }
+
+    #[test]
+    fn camera_looking_at_unknown_object_is_error() {
+        let scene_str = r#"
+        light = []
+
+        [camera]
+        type = "orthogonal"
+        eye = [0, 0, -10]
+        look_at = { object = "Missing sphere" }
+        width = 16
+        height = 9
+
+        [[object]]
+        description = "Blue sphere"
+        type = "sphere"
+        center = [3, 0, 0]
+        radius = 1
+        "#;
+        assert!(parse_scene_description(scene_str).is_err());
+    }
+
+    #[test]
+    fn orthogonal_camera_with_position_and_up() {
+        let scene_str = r#"
+        light = []
+        object = []
+
+        [camera]
+        type = "orthogonal"
+        position = [0, 0, -10]
+        up = [1, 0, 0]
+        look_at = [0, 0, 0]
+        width = 16
+        height = 9
+        "#;
+        match parsed_camera(scene_str) {
+            DescriptionCamera::Orthogonal { up: Some(up), .. } => {
+                assert!(up.distance(Vec3::new(1.0, 0.0, 0.0)) < 1e-9);
+            }
+            camera => panic!("Unexpected camera: {:?}", camera),
+        }
+        let scene = parse_scene_description(scene_str).unwrap();
+        // The screen vertical axis is now along x
+        let ray = scene.camera.generate_ray(0.5, 0.25);
+        assert!(ray.source.distance(Vec3::new(0.25 * 9.0, 0.0, -10.0)) < 1e-9);
+        assert!(ray.direction.distance(Vec3::new(0.0, 0.0, 1.0)) < 1e-9);
+    }
+
+    #[test]
+    fn directional_light_is_parsed() {
+        let scene_str = r#"
+        object = []
+
+        [camera]
+        type = "orthogonal"
+        eye = [0, 0, -10]
+        look_at = [0, 0, 0]
+        width = 16
+        height = 9
+
+        [[light]]
+        type = "directional"
+        direction = [0, -2, 0]
+        intensity = 0.5
+        color = "white"
+        "#;
+        let scene = parse_scene_description(scene_str).unwrap();
+        let light = &scene.lights[0];
+        let (direction, distance) = light.direction_from(Vec3::new(3.0, 0.0, 0.0));
+        assert!(direction.distance(Vec3::new(0.0, 1.0, 0.0)) < 1e-9);
+        assert!(distance.is_infinite());
+        assert!((light.intensity() - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn gradient_background_is_seen_by_missing_rays() {
+        let scene_str = r#"
+        light = []
+        object = []
+
+        [config]
+        background = { type = "gradient", start_color = "red", end_color = "blue" }
+
+        [camera]
+        type = "orthogonal"
+        eye = [0, 0, -10]
+        look_at = [0, 0, 0]
+        width = 16
+        height = 9
+        "#;
+        let scene = parse_scene_description(scene_str).unwrap();
+        let ray_color = |direction: Vec3| {
+            crate::ray_algorithm::launch_ray(
+                &Ray::new(Vec3::new(0.0, 0.0, -10.0), direction),
+                &scene,
+                0,
+            )
+            .unwrap()
+        };
+        let zenith = ray_color(Vec3::new(0.0, 1.0, 0.0));
+        assert!(zenith.blue() > 0.99 && zenith.red() < 0.01);
+        let nadir = ray_color(Vec3::new(0.0, -1.0, 0.0));
+        assert!(nadir.red() > 0.99 && nadir.blue() < 0.01);
+        let with_environment = scene_str.to_string()
+            + r#"
+        [environment]
+        type = "sky"
+        sun_direction = [0, 1, 0]"#;
+        assert!(parse_scene_description(&with_environment).is_err());
+    }
+
+    #[test]
+    fn light_temperature_replaces_color() {
+        let scene_str = r#"
+        object = []
+
+        [camera]
+        type = "orthogonal"
+        eye = [0, 0, -10]
+        look_at = [0, 0, 0]
+        width = 16
+        height = 9
+
+        [[light]]
+        type = "point"
+        source = [0, 10, 0]
+        temperature = 2700
+        "#;
+        let scene = parse_scene_description(scene_str).unwrap();
+        let color = scene.lights[0].peak_color();
+        assert!(color.red() > color.green() && color.green() > color.blue());
+        let with_color = scene_str.to_string() + r#"color = "white""#;
+        assert!(parse_scene_description(&with_color).is_err());
+    }
 }
@@ -0,0 +1,347 @@
+/*
+MIT License
+
+Copyright (c) 2019, 2020 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+use crate::colors::{Color, Radiance};
+use crate::primitives::Ray;
+use crate::ray_algorithm::{sample_cone, search_object_collision};
+use crate::scene::Scene;
+use crate::textures::TextureEffects;
+use crate::vector::Vec3;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::fmt;
+
+/// Parameters of the photon map used to render caustics
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct CausticsSettings {
+    /// Photons emitted by each light toward each mirror or transparent object
+    pub photon_count: u32,
+    /// Distance around a shaded point in which photons are gathered
+    pub gather_radius: f64,
+    /// Mirrors and transparent surfaces a photon can go through before being lost
+    pub max_bounces: u32,
+}
+
+impl Default for CausticsSettings {
+    fn default() -> Self {
+        CausticsSettings {
+            photon_count: 100_000,
+            gather_radius: 0.1,
+            max_bounces: 8,
+        }
+    }
+}
+
+/// Light brought to a diffuse surface after at least one mirror reflection or
+/// refraction
+#[derive(Debug, Clone)]
+struct Photon {
+    position: Vec3,
+    direction: Vec3,
+    power: f64,
+    color: Color,
+}
+
+type CellIndex = (i64, i64, i64);
+
+/// Caustic photons stored in a spatial hash, whose cells are as large as the
+/// gather radius, so that gathering only visits the neighboring cells
+pub struct PhotonMap {
+    gather_radius: f64,
+    cells: HashMap<CellIndex, Vec<Photon>>,
+    photon_count: usize,
+}
+
+impl PhotonMap {
+    /// Emit photons from each light toward the bounding sphere of each mirror and
+    /// transparent object, and keep those reaching a diffuse surface.
+    ///
+    /// Lights of the renderer do not fade with distance, so the power of a photon is
+    /// scaled by the square of its path length, which gives back the light intensity
+    /// as irradiance when photons are not focused.
+    pub fn build(scene: &Scene, settings: &CausticsSettings) -> PhotonMap {
+        let mut photon_map = PhotonMap {
+            gather_radius: settings.gather_radius,
+            cells: HashMap::new(),
+            photon_count: 0,
+        };
+        let mut rng = StdRng::seed_from_u64(0);
+        let specular_targets = scene
+            .objects
+            .iter()
+            .enumerate()
+            .filter(|(_, object)| is_specular(object.effects()))
+            .filter_map(|(index, object)| {
+                let bounding_box = object.shape.bounding_box()?;
+                let radius = 0.5 * bounding_box.min.distance(bounding_box.max);
+                Some((index, bounding_box.center(), radius))
+            });
+        for (target_index, center, radius) in specular_targets {
+            for light in &scene.lights {
+                let source = light.source();
+                let distance = source.distance(center);
+                let cos_max = if distance > radius {
+                    (1.0 - (radius / distance).powi(2)).sqrt()
+                } else {
+                    -1.0
+                };
+                let axis = Vec3::between_points(source, center).normalize();
+                let solid_angle = 2.0 * PI * (1.0 - cos_max);
+                let power = light.intensity() * solid_angle / f64::from(settings.photon_count);
+                for _ in 0..settings.photon_count {
+                    let direction = sample_cone(axis, cos_max, &mut rng);
+                    let color = light.color_for_ray(Ray::new(source, direction));
+                    let ray = Ray::new(source, direction);
+                    photon_map.trace(scene, settings, target_index, ray, power, color);
+                }
+            }
+        }
+        photon_map
+    }
+
+    fn trace(
+        &mut self,
+        scene: &Scene,
+        settings: &CausticsSettings,
+        target_index: usize,
+        mut ray: Ray,
+        mut power: f64,
+        mut color: Color,
+    ) {
+        let mut path_length = 0.0;
+        for bounce in 0..=settings.max_bounces {
+            let collision_context = match search_object_collision(&ray, &scene.objects) {
+                Some(collision_context) => collision_context,
+                None => return,
+            };
+            // Photons of another object are emitted toward that object
+            if bounce == 0 && collision_context.array_index != target_index {
+                return;
+            }
+            let point = collision_context.collision_point;
+            path_length += point.distance(ray.source);
+            let object = collision_context.object;
+            let normal = match object.normal_at(point) {
+                Some(normal) => normal.normalize(),
+                None => return,
+            };
+            let effects = object.effects();
+            let direction = if let Some(transparency) = &effects.transparency {
+                power *= transparency.alpha;
+                if let Some(transmission_color) = &transparency.transmission_color {
+                    color = color * transmission_color.clone();
+                }
+                let entering = ray.direction.dot_product(normal) < 0.0;
+                let (n_ratio, facing_normal) = if entering {
+                    let n_ratio =
+                        scene.config.world_refractive_index / transparency.refractive_index;
+                    (n_ratio, normal)
+                } else {
+                    let n_ratio =
+                        transparency.refractive_index / scene.config.world_refractive_index;
+                    (n_ratio, -normal)
+                };
+                refract(ray.direction, facing_normal, n_ratio)
+                    .unwrap_or_else(|| ray.direction.reflect(facing_normal).normalize())
+            } else if let Some(mirror) = &effects.mirror {
+                power *= mirror.reflectance(ray.direction.dot_product(normal));
+                ray.direction.reflect(normal).normalize()
+            } else {
+                if bounce > 0 {
+                    self.store(Photon {
+                        position: point,
+                        direction: ray.direction,
+                        power: power * path_length.powi(2),
+                        color,
+                    });
+                }
+                return;
+            };
+            ray = Ray::new(point, direction).shift_source();
+        }
+    }
+
+    fn cell_index(&self, point: Vec3) -> CellIndex {
+        let index = |coord: f64| (coord / self.gather_radius).floor() as i64;
+        (index(point.x), index(point.y), index(point.z))
+    }
+
+    fn store(&mut self, photon: Photon) {
+        let cell_index = self.cell_index(photon.position);
+        self.cells.entry(cell_index).or_default().push(photon);
+        self.photon_count += 1;
+    }
+
+    pub fn len(&self) -> usize {
+        self.photon_count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.photon_count == 0
+    }
+
+    /// Light received at a point of a surface from the photons around it, only
+    /// counting those arriving on the side `normal` points to
+    pub fn irradiance(&self, point: Vec3, normal: Vec3) -> Radiance {
+        let mut irradiance = Radiance::default();
+        let area = PI * self.gather_radius.powi(2);
+        let (x, y, z) = self.cell_index(point);
+        for cell_x in x - 1..=x + 1 {
+            for cell_y in y - 1..=y + 1 {
+                for cell_z in z - 1..=z + 1 {
+                    let photons = match self.cells.get(&(cell_x, cell_y, cell_z)) {
+                        Some(photons) => photons,
+                        None => continue,
+                    };
+                    let gathered = photons.iter().filter(|photon| {
+                        photon.direction.dot_product(normal) < 0.0
+                            && photon.position.distance(point) <= self.gather_radius
+                    });
+                    for photon in gathered {
+                        irradiance.accumulate(photon.power / area, &photon.color);
+                    }
+                }
+            }
+        }
+        irradiance
+    }
+}
+
+impl fmt::Debug for PhotonMap {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("PhotonMap")
+            .field("gather_radius", &self.gather_radius)
+            .field("photon_count", &self.photon_count)
+            .finish()
+    }
+}
+
+fn is_specular(effects: &TextureEffects) -> bool {
+    effects.transparency.is_some() || effects.mirror.is_some()
+}
+
+/// Direction of a ray refracted by a surface, `None` on total internal reflection.
+/// `normal` is on the side the ray comes from, and `n_ratio` is the refractive index
+/// of that side divided by the one of the other side.
+fn refract(direction: Vec3, normal: Vec3, n_ratio: f64) -> Option<Vec3> {
+    let cos_incidence = -direction.dot_product(normal);
+    let sin_square_refraction = n_ratio.powi(2) * (1.0 - cos_incidence.powi(2));
+    if sin_square_refraction > 1.0 {
+        return None;
+    }
+    let cos_refraction = (1.0 - sin_square_refraction).sqrt();
+    Some((n_ratio * direction + (n_ratio * cos_incidence - cos_refraction) * normal).normalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cameras::OrthogonalCamera;
+    use crate::lights::LightPoint;
+    use crate::primitives::{InfinitePlan, Sphere};
+    use crate::ray_algorithm::launch_ray;
+    use crate::scene::{SceneConfiguration, SceneObject};
+    use crate::textures::{PlainColorTexture, Transparency};
+
+    fn glass_sphere_over_plane(caustics: Option<CausticsSettings>) -> Scene {
+        let plane = SceneObject::new(
+            Box::new(InfinitePlan::new(Vec3::ZERO, Vec3::new(0.0, 1.0, 0.0))),
+            Box::new(PlainColorTexture::default()),
+        );
+        let glass_sphere = SceneObject::new(
+            Box::new(Sphere {
+                center: Vec3::new(0.0, 1.5, 0.0),
+                radius: 1.0,
+            }),
+            Box::new(PlainColorTexture::default()),
+        )
+        .with_effects(TextureEffects {
+            transparency: Some(Transparency {
+                refractive_index: 1.5,
+                alpha: 1.0,
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        let mut scene = Scene {
+            camera: Box::new(OrthogonalCamera::default()),
+            lights: vec![Box::new(LightPoint::new(Vec3::new(0.0, 20.0, 0.0)))],
+            objects: vec![plane, glass_sphere],
+            config: SceneConfiguration {
+                ambient_light: None,
+                caustics,
+                ..Default::default()
+            },
+        };
+        scene.build_photon_map();
+        scene
+    }
+
+    #[test]
+    fn glass_sphere_focuses_light_on_plane() {
+        let settings = CausticsSettings {
+            photon_count: 20_000,
+            ..Default::default()
+        };
+        let scene = glass_sphere_over_plane(Some(settings));
+        assert!(!scene.config.photon_map.as_ref().unwrap().is_empty());
+        let unlit_scene = glass_sphere_over_plane(None);
+        // Seen from the side, so that the sphere does not hide the spot
+        let ray_to_spot = || Ray::ray_from_to(Vec3::new(4.0, 3.0, 0.0), Vec3::ZERO);
+        // With caustics, the light going through the glass only comes from photons
+        let spot = launch_ray(&ray_to_spot(), &scene, 0).unwrap();
+        assert!(spot.red() > 0.9);
+        let photonless_scene = glass_sphere_over_plane(Some(CausticsSettings {
+            photon_count: 0,
+            ..Default::default()
+        }));
+        let shadow = launch_ray(&ray_to_spot(), &photonless_scene, 0).unwrap();
+        assert!(shadow.red() < 0.1);
+        // Without caustics, the direct light is filtered by the glass instead
+        let transmitted = launch_ray(&ray_to_spot(), &unlit_scene, 0).unwrap();
+        assert!(transmitted.red() > 0.9);
+        // Far from the sphere, the plane is only directly lit
+        let ray_to_lit_plane =
+            || Ray::ray_from_to(Vec3::new(7.0, 3.0, 3.0), Vec3::new(3.0, 0.0, 3.0));
+        let lit = launch_ray(&ray_to_lit_plane(), &scene, 0).unwrap();
+        let unlit = launch_ray(&ray_to_lit_plane(), &unlit_scene, 0).unwrap();
+        assert!((lit.red() - unlit.red()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn refraction_bends_toward_normal_in_denser_medium() {
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+        let direction = Vec3::new(1.0, -1.0, 0.0).normalize();
+        let refracted = refract(direction, normal, 1.0 / 1.5).unwrap();
+        let sin_incidence = direction.x;
+        assert!((refracted.x - sin_incidence / 1.5).abs() < 1e-9);
+        assert!(refracted.y < 0.0);
+        assert!(refract(direction, normal, 1.5).is_none());
+    }
+}
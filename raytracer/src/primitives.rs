@@ -22,16 +22,84 @@ OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 SOFTWARE.
 */
 
-use crate::vector::{Mat3, Vec3};
+use crate::vector::{Mat3, Mat4, Vec3};
 use crate::UnitInterval;
 use serde::Deserialize;
 use std::f64::consts::PI;
 use std::fmt::Debug;
 
-pub trait Shape: Sync + Send {
+pub trait Shape: Sync + Send + Debug {
     fn check_collision(&self, ray: &Ray) -> Option<Vec3>;
+    /// Geometric normal at a point of the surface.
+    ///
+    /// Closed shapes return a normal pointing outward; for open surfaces such as
+    /// plans, the side is arbitrary. The renderer orients the normal toward the
+    /// incoming ray when computing lighting, so open surfaces are two-sided.
     fn normal_at(&self, point: Vec3) -> Option<Vec3>;
     fn surface_mapping_at(&self, point: Vec3) -> Option<(UnitInterval, UnitInterval)>;
+    /// Geometric center of the shape, usable as a pivot point
+    fn centroid(&self) -> Vec3;
+    /// Axis-aligned box enclosing the shape, `None` if the shape is unbounded
+    fn bounding_box(&self) -> Option<BoundingBox> {
+        None
+    }
+    /// Whether a point is inside the volume of the shape, `None` for open shapes
+    fn contains(&self, _point: Vec3) -> Option<bool> {
+        None
+    }
+    /// Whether the ray hits the shape at a distance up to `max_distance`, as needed
+    /// by shadow rays. Shapes can skip computations not needed to answer.
+    fn occludes(&self, ray: &Ray, max_distance: f64) -> bool {
+        match self.check_collision(ray) {
+            Some(point) => {
+                let distance = point.distance(ray.source);
+                distance > OCCLUSION_MIN_DISTANCE && distance <= max_distance
+            }
+            None => false,
+        }
+    }
+}
+
+/// Hits closer than this distance to the ray source are ignored by occlusion tests,
+/// as they are usually the surface the ray starts from
+pub(crate) const OCCLUSION_MIN_DISTANCE: f64 = 1e-12;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BoundingBox {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl BoundingBox {
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        BoundingBox { min, max }
+    }
+
+    pub fn union(&self, other: &BoundingBox) -> BoundingBox {
+        BoundingBox {
+            min: Vec3::new(
+                self.min.x.min(other.min.x),
+                self.min.y.min(other.min.y),
+                self.min.z.min(other.min.z),
+            ),
+            max: Vec3::new(
+                self.max.x.max(other.max.x),
+                self.max.y.max(other.max.y),
+                self.max.z.max(other.max.z),
+            ),
+        }
+    }
+
+    pub fn center(&self) -> Vec3 {
+        0.5 * (self.min + self.max)
+    }
+
+    /// Whether a point is inside the box, bounds included
+    pub fn contains(&self, point: Vec3) -> bool {
+        (self.min.x..=self.max.x).contains(&point.x)
+            && (self.min.y..=self.max.y).contains(&point.y)
+            && (self.min.z..=self.max.z).contains(&point.z)
+    }
 }
 
 #[derive(Debug)]
@@ -105,6 +173,16 @@ impl Shape for InfinitePlan {
         }
     }
 
+    fn occludes(&self, ray: &Ray, max_distance: f64) -> bool {
+        let denom = self.normal_normalized.dot_product(ray.direction);
+        if denom.abs() < 1e-6 {
+            return false;
+        }
+        let p_l = self.center - ray.source;
+        let distance = p_l.dot_product(self.normal_normalized) / denom * ray.direction.norm();
+        distance > OCCLUSION_MIN_DISTANCE && distance <= max_distance
+    }
+
     fn normal_at(&self, _point: Vec3) -> Option<Vec3> {
         Some(self.normal_normalized)
     }
@@ -120,8 +198,16 @@ impl Shape for InfinitePlan {
         let v = positive_space(v);
         Some((u, v))
     }
+
+    fn centroid(&self) -> Vec3 {
+        self.center
+    }
 }
 
+/// Distance from its plane under which a point is considered on a `SquarePlan`,
+/// to accept collision points computed with float errors
+const SQUARE_PLAN_TOLERANCE: f64 = 1e-6;
+
 #[derive(Debug)]
 pub struct SquarePlan {
     center: Vec3,
@@ -143,9 +229,13 @@ impl SquarePlan {
         }
     }
 
+    /// Coordinates of a point in the square, `None` if the point is outside the
+    /// square or farther than `SQUARE_PLAN_TOLERANCE` from its plane
     fn to_plan_coords(&self, point: Vec3) -> Option<(f64, f64)> {
-        // TODO return None if point not in plan
         let local_coords = Vec3::between_points(self.center, point);
+        if local_coords.dot_product(self.normal_normalized).abs() > SQUARE_PLAN_TOLERANCE {
+            return None;
+        }
         let local_x = local_coords.dot_product(self.u_vec);
         let local_y = local_coords.dot_product(self.v_vec);
         let radius = self.width / 2.0;
@@ -181,12 +271,31 @@ impl Shape for SquarePlan {
     }
 
     fn surface_mapping_at(&self, point: Vec3) -> Option<(UnitInterval, UnitInterval)> {
-        let (local_x, local_y) = self.to_plan_coords(point).unwrap();
+        let (local_x, local_y) = self.to_plan_coords(point)?;
         let radius = self.width / 2.0;
         let u = (local_x + radius) / self.width;
         let v = (local_y + radius) / self.width;
         Some((u, v))
     }
+
+    fn centroid(&self) -> Vec3 {
+        self.center
+    }
+
+    fn bounding_box(&self) -> Option<BoundingBox> {
+        let radius = self.width / 2.0;
+        let corners = [
+            self.center + radius * self.u_vec + radius * self.v_vec,
+            self.center + radius * self.u_vec - radius * self.v_vec,
+            self.center - radius * self.u_vec + radius * self.v_vec,
+            self.center - radius * self.u_vec - radius * self.v_vec,
+        ];
+        let first_corner = BoundingBox::new(corners[0], corners[0]);
+        let bounding_box = corners[1..].iter().fold(first_corner, |acc, &corner| {
+            acc.union(&BoundingBox::new(corner, corner))
+        });
+        Some(bounding_box)
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -208,6 +317,81 @@ impl Default for Sphere {
     }
 }
 
+/// Number of rays processed together by `Sphere::check_collision_batch`
+const SPHERE_BATCH_LANES: usize = 4;
+
+impl Sphere {
+    /// Same as `check_collision`, for a slice of rays.
+    ///
+    /// Rays are processed in small fixed-size chunks laid out as separate arrays per
+    /// component, so that the compiler can vectorize the arithmetic.
+    /// `out` must have the same length as `rays`.
+    #[allow(non_snake_case)]
+    pub fn check_collision_batch(&self, rays: &[Ray], out: &mut [Option<Vec3>]) {
+        assert_eq!(rays.len(), out.len());
+        let r_square = self.radius.powi(2);
+        let C = self.center;
+        for (ray_chunk, out_chunk) in rays
+            .chunks(SPHERE_BATCH_LANES)
+            .zip(out.chunks_mut(SPHERE_BATCH_LANES))
+        {
+            let mut u = [Vec3::default(); SPHERE_BATCH_LANES];
+            let mut d = [0.0; SPHERE_BATCH_LANES];
+            let mut l_square = [0.0; SPHERE_BATCH_LANES];
+            let mut m_square = [0.0; SPHERE_BATCH_LANES];
+            for (lane, ray) in ray_chunk.iter().enumerate() {
+                u[lane] = ray.direction.normalize();
+                let L = C - ray.source;
+                d[lane] = L.dot_product(u[lane]);
+                l_square[lane] = L.dot_product(L);
+                m_square[lane] = sphere_axis_distance_square(L, u[lane], d[lane]);
+            }
+            let mut t = [0.0; SPHERE_BATCH_LANES];
+            for lane in 0..SPHERE_BATCH_LANES {
+                let q = (r_square - m_square[lane]).max(0.0).sqrt();
+                t[lane] = sphere_hit_distance(d[lane], q, l_square[lane] - r_square);
+            }
+            for (lane, (ray, hit)) in ray_chunk.iter().zip(out_chunk.iter_mut()).enumerate() {
+                let is_behind = d[lane] < 0.0 && l_square[lane] > r_square;
+                *hit = if is_behind || m_square[lane] > r_square {
+                    None
+                } else {
+                    Some(ray.source + t[lane] * u[lane])
+                };
+            }
+        }
+    }
+}
+
+/// Square distance between the sphere center and the ray line.
+///
+/// Computed from the perpendicular vector rather than `l² - d²`, which loses
+/// all its precision when the sphere is far away compared to its radius.
+fn sphere_axis_distance_square(center_offset: Vec3, direction: Vec3, d: f64) -> f64 {
+    let perpendicular = center_offset - d * direction;
+    perpendicular.dot_product(perpendicular)
+}
+
+/// Distance along the ray of the visible hit, nearest from outside the sphere
+/// and farthest from inside, given the roots `d ± q` and their product `c`.
+///
+/// The root of larger magnitude is computed first, and the other one is
+/// derived from the product so that no subtraction cancels.
+fn sphere_hit_distance(d: f64, q: f64, c: f64) -> f64 {
+    if c > 0.0 {
+        let far = d + q;
+        if far > 0.0 {
+            c / far
+        } else {
+            d - q
+        }
+    } else if d >= 0.0 {
+        d + q
+    } else {
+        c / (d - q)
+    }
+}
+
 impl Shape for Sphere {
     #[allow(non_snake_case)]
     fn check_collision(&self, ray: &Ray) -> Option<Vec3> {
@@ -222,15 +406,41 @@ impl Shape for Sphere {
         if d < 0.0 && l_square > r_square {
             return None;
         }
-        let m_square = l_square - d.powi(2);
+        let m_square = sphere_axis_distance_square(L, u, d);
         if m_square > r_square {
             return None;
         }
         let q = (r_square - m_square).sqrt();
-        let t: f64 = if l_square > r_square { d - q } else { d + q };
+        let t = sphere_hit_distance(d, q, l_square - r_square);
         Some(A + t * u)
     }
 
+    #[allow(non_snake_case)]
+    fn occludes(&self, ray: &Ray, max_distance: f64) -> bool {
+        let r_square = self.radius.powi(2);
+        let u = ray.direction.normalize();
+        let L = self.center - ray.source;
+        let d = L.dot_product(u);
+        let l_square = L.dot_product(L);
+        if d < 0.0 && l_square > r_square {
+            return false;
+        }
+        let m_square = sphere_axis_distance_square(L, u, d);
+        if m_square > r_square {
+            return false;
+        }
+        let q_square = r_square - m_square;
+        let is_outside = l_square > r_square;
+        // From outside, the hit at `d - q` is farther than `max_distance` when
+        // `d - max_distance > q`, which can be checked without the square root
+        let gap = d - max_distance;
+        if is_outside && gap > 0.0 && gap.powi(2) > q_square {
+            return false;
+        }
+        let t = sphere_hit_distance(d, q_square.sqrt(), l_square - r_square);
+        t > OCCLUSION_MIN_DISTANCE && t <= max_distance
+    }
+
     fn normal_at(&self, point: Vec3) -> Option<Vec3> {
         Some(Vec3::between_points(self.center, point).normalize())
     }
@@ -241,6 +451,666 @@ impl Shape for Sphere {
         let v = 0.5 - unit_point.y.asin() / PI;
         Some((u, v))
     }
+
+    fn centroid(&self) -> Vec3 {
+        self.center
+    }
+
+    fn bounding_box(&self) -> Option<BoundingBox> {
+        let extent = Vec3::new(self.radius, self.radius, self.radius);
+        Some(BoundingBox::new(self.center - extent, self.center + extent))
+    }
+
+    fn contains(&self, point: Vec3) -> Option<bool> {
+        Some(self.center.distance(point) <= self.radius)
+    }
+}
+
+/// Distance from a face plane under which a point is considered on that face of an
+/// `AxisAlignedBox`, to accept collision points computed with float errors
+const AXIS_ALIGNED_BOX_TOLERANCE: f64 = 1e-6;
+
+/// Box whose faces are parallel to the axes, such as a room or a crate
+#[derive(Debug)]
+pub struct AxisAlignedBox {
+    min: Vec3,
+    max: Vec3,
+}
+
+impl AxisAlignedBox {
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        AxisAlignedBox {
+            min: Vec3::new(min.x.min(max.x), min.y.min(max.y), min.z.min(max.z)),
+            max: Vec3::new(min.x.max(max.x), min.y.max(max.y), min.z.max(max.z)),
+        }
+    }
+
+    /// Axis index and side (-1 for the min face, 1 for the max face) of the face
+    /// nearest to a point, if the point is on the box surface
+    fn face_at(&self, point: Vec3) -> Option<(usize, f64)> {
+        let coords = |v: Vec3| [v.x, v.y, v.z];
+        let (point, min, max) = (coords(point), coords(self.min), coords(self.max));
+        let is_inside_face = |axis: usize| {
+            (0..3).filter(|&other| other != axis).all(|other| {
+                point[other] >= min[other] - AXIS_ALIGNED_BOX_TOLERANCE
+                    && point[other] <= max[other] + AXIS_ALIGNED_BOX_TOLERANCE
+            })
+        };
+        (0..3)
+            .flat_map(|axis| {
+                vec![
+                    (axis, -1.0, (point[axis] - min[axis]).abs()),
+                    (axis, 1.0, (point[axis] - max[axis]).abs()),
+                ]
+            })
+            .filter(|&(axis, _, distance)| {
+                distance <= AXIS_ALIGNED_BOX_TOLERANCE && is_inside_face(axis)
+            })
+            .min_by(|left, right| left.2.partial_cmp(&right.2).unwrap())
+            .map(|(axis, side, _)| (axis, side))
+    }
+}
+
+impl Shape for AxisAlignedBox {
+    fn check_collision(&self, ray: &Ray) -> Option<Vec3> {
+        // https://en.wikipedia.org/wiki/Slab_method
+        let source = [ray.source.x, ray.source.y, ray.source.z];
+        let direction = [ray.direction.x, ray.direction.y, ray.direction.z];
+        let min = [self.min.x, self.min.y, self.min.z];
+        let max = [self.max.x, self.max.y, self.max.z];
+        let mut t_near = f64::NEG_INFINITY;
+        let mut t_far = f64::INFINITY;
+        for axis in 0..3 {
+            if direction[axis].abs() < 1e-12 {
+                if source[axis] < min[axis] || source[axis] > max[axis] {
+                    return None;
+                }
+                continue;
+            }
+            let t_min = (min[axis] - source[axis]) / direction[axis];
+            let t_max = (max[axis] - source[axis]) / direction[axis];
+            t_near = t_near.max(t_min.min(t_max));
+            t_far = t_far.min(t_min.max(t_max));
+        }
+        if t_near > t_far || t_far <= 1e-12 {
+            return None;
+        }
+        // From inside, or from the surface the ray starts on, the exit point is hit
+        let t = if t_near > 1e-12 { t_near } else { t_far };
+        Some(ray.source + t * ray.direction)
+    }
+
+    fn normal_at(&self, point: Vec3) -> Option<Vec3> {
+        let (axis, side) = self.face_at(point)?;
+        let mut normal = [0.0; 3];
+        normal[axis] = side;
+        Some(Vec3::new(normal[0], normal[1], normal[2]))
+    }
+
+    /// Each face is mapped to the whole texture
+    fn surface_mapping_at(&self, point: Vec3) -> Option<(UnitInterval, UnitInterval)> {
+        let (axis, _) = self.face_at(point)?;
+        let ratio = |value: f64, min: f64, max: f64| ((value - min) / (max - min)).clamp(0.0, 1.0);
+        let x = ratio(point.x, self.min.x, self.max.x);
+        let y = ratio(point.y, self.min.y, self.max.y);
+        let z = ratio(point.z, self.min.z, self.max.z);
+        Some(match axis {
+            0 => (z, y),
+            1 => (x, z),
+            _ => (x, y),
+        })
+    }
+
+    fn centroid(&self) -> Vec3 {
+        0.5 * (self.min + self.max)
+    }
+
+    fn bounding_box(&self) -> Option<BoundingBox> {
+        Some(BoundingBox::new(self.min, self.max))
+    }
+
+    fn contains(&self, point: Vec3) -> Option<bool> {
+        Some(BoundingBox::new(self.min, self.max).contains(point))
+    }
+}
+
+/// Distance from a cap plane under which a point is considered on that cap of a `Frustum`,
+/// to accept collision points computed with float errors
+const FRUSTUM_TOLERANCE: f64 = 1e-6;
+
+/// Truncated cone, whose radius varies linearly from `bottom_radius` at `base`
+/// to `top_radius` at `height` along `axis`, closed by two circular caps
+#[derive(Debug)]
+pub struct Frustum {
+    base: Vec3,
+    axis_normalized: Vec3,
+    u_vec: Vec3,
+    v_vec: Vec3,
+    bottom_radius: f64,
+    top_radius: f64,
+    height: f64,
+}
+
+impl Frustum {
+    pub fn new(base: Vec3, axis: Vec3, bottom_radius: f64, top_radius: f64, height: f64) -> Self {
+        let transform = Mat3::transformation_between(Vec3::new(0.0, 1.0, 0.0), axis);
+        Frustum {
+            base,
+            axis_normalized: axis.normalize(),
+            u_vec: transform * Vec3::new(1.0, 0.0, 0.0),
+            v_vec: transform * Vec3::new(0.0, 0.0, 1.0),
+            bottom_radius,
+            top_radius,
+            height,
+        }
+    }
+
+    /// Radius variation per unit of height
+    fn slope(&self) -> f64 {
+        (self.top_radius - self.bottom_radius) / self.height
+    }
+
+    fn radius_at(&self, height: f64) -> f64 {
+        self.bottom_radius + self.slope() * height
+    }
+
+    /// Height along the axis and radial vector of a point, relative to the base
+    fn to_local_coords(&self, point: Vec3) -> (f64, Vec3) {
+        let local_coords = Vec3::between_points(self.base, point);
+        let height = local_coords.dot_product(self.axis_normalized);
+        (height, local_coords - height * self.axis_normalized)
+    }
+
+    fn body_collision_distance(&self, ray: &Ray) -> Option<f64> {
+        let slope = self.slope();
+        let source = Vec3::between_points(self.base, ray.source);
+        let source_height = source.dot_product(self.axis_normalized);
+        let direction_height = ray.direction.dot_product(self.axis_normalized);
+        let source_radius = self.bottom_radius + slope * source_height;
+        let a = ray.direction.dot_product(ray.direction)
+            - (1.0 + slope * slope) * direction_height * direction_height;
+        let b = 2.0
+            * (source.dot_product(ray.direction)
+                - source_height * direction_height
+                - slope * direction_height * source_radius);
+        let c = source.dot_product(source)
+            - source_height * source_height
+            - source_radius * source_radius;
+        if a.abs() < 1e-12 {
+            return None;
+        }
+        let delta = b * b - 4.0 * a * c;
+        if delta < 0.0 {
+            return None;
+        }
+        let sqrt_delta = delta.sqrt();
+        let (t1, t2) = ((-b - sqrt_delta) / (2.0 * a), (-b + sqrt_delta) / (2.0 * a));
+        let (t_near, t_far) = if t1 <= t2 { (t1, t2) } else { (t2, t1) };
+        [t_near, t_far].iter().copied().find(|&t| {
+            let height = source_height + t * direction_height;
+            t > 0.0 && height >= 0.0 && height <= self.height
+        })
+    }
+
+    fn cap_collision_distance(&self, ray: &Ray, cap_height: f64, cap_radius: f64) -> Option<f64> {
+        let direction_height = ray.direction.dot_product(self.axis_normalized);
+        if direction_height.abs() < 1e-6 {
+            return None;
+        }
+        let source_height =
+            Vec3::between_points(self.base, ray.source).dot_product(self.axis_normalized);
+        let t = (cap_height - source_height) / direction_height;
+        if t <= 0.0 {
+            return None;
+        }
+        let (_, radial) = self.to_local_coords(ray.source + t * ray.direction);
+        if radial.norm() <= cap_radius {
+            Some(t)
+        } else {
+            None
+        }
+    }
+}
+
+impl Shape for Frustum {
+    fn check_collision(&self, ray: &Ray) -> Option<Vec3> {
+        let candidates = [
+            self.body_collision_distance(ray),
+            self.cap_collision_distance(ray, 0.0, self.bottom_radius),
+            self.cap_collision_distance(ray, self.height, self.top_radius),
+        ];
+        candidates
+            .iter()
+            .flatten()
+            .copied()
+            .fold(None, |nearest: Option<f64>, t| match nearest {
+                Some(nearest) if nearest <= t => Some(nearest),
+                _ => Some(t),
+            })
+            .map(|t| ray.source + t * ray.direction)
+    }
+
+    fn normal_at(&self, point: Vec3) -> Option<Vec3> {
+        let (height, radial) = self.to_local_coords(point);
+        let radial_distance = radial.norm();
+        if height.abs() < FRUSTUM_TOLERANCE && radial_distance <= self.bottom_radius {
+            return Some(-self.axis_normalized);
+        }
+        if (height - self.height).abs() < FRUSTUM_TOLERANCE && radial_distance <= self.top_radius {
+            return Some(self.axis_normalized);
+        }
+        if radial_distance < FRUSTUM_TOLERANCE {
+            return None;
+        }
+        Some((radial.normalize() - self.slope() * self.axis_normalized).normalize())
+    }
+
+    fn surface_mapping_at(&self, point: Vec3) -> Option<(UnitInterval, UnitInterval)> {
+        let (height, radial) = self.to_local_coords(point);
+        if height < -FRUSTUM_TOLERANCE
+            || height > self.height + FRUSTUM_TOLERANCE
+            || radial.norm() > self.radius_at(height) + FRUSTUM_TOLERANCE
+        {
+            return None;
+        }
+        let angle = radial
+            .dot_product(self.v_vec)
+            .atan2(radial.dot_product(self.u_vec));
+        let u = 0.5 + angle / (2.0 * PI);
+        let v = (height / self.height).clamp(0.0, 1.0);
+        Some((u, v))
+    }
+
+    fn centroid(&self) -> Vec3 {
+        self.base + (self.height / 2.0) * self.axis_normalized
+    }
+
+    fn bounding_box(&self) -> Option<BoundingBox> {
+        let cap_box = |center: Vec3, radius: f64| {
+            let axis = self.axis_normalized;
+            let extent = Vec3::new(
+                radius * (1.0 - axis.x * axis.x).max(0.0).sqrt(),
+                radius * (1.0 - axis.y * axis.y).max(0.0).sqrt(),
+                radius * (1.0 - axis.z * axis.z).max(0.0).sqrt(),
+            );
+            BoundingBox::new(center - extent, center + extent)
+        };
+        let top = self.base + self.height * self.axis_normalized;
+        Some(cap_box(self.base, self.bottom_radius).union(&cap_box(top, self.top_radius)))
+    }
+
+    fn contains(&self, point: Vec3) -> Option<bool> {
+        let (height, radial) = self.to_local_coords(point);
+        Some(height >= 0.0 && height <= self.height && radial.norm() <= self.radius_at(height))
+    }
+}
+
+/// Finite cylinder of `radius`, whose base cap is centered on `center` and which
+/// extends over `height` along `axis`, closed by two circular caps
+///
+/// It is a `Frustum` whose both radii are the same, so that its side is the
+/// infinite tube clipped to the height band, with a radial normal.
+#[derive(Debug)]
+pub struct Cylinder {
+    frustum: Frustum,
+}
+
+impl Cylinder {
+    pub fn new(center: Vec3, axis: Vec3, radius: f64, height: f64) -> Self {
+        Cylinder {
+            frustum: Frustum::new(center, axis, radius, radius, height),
+        }
+    }
+}
+
+impl Shape for Cylinder {
+    fn check_collision(&self, ray: &Ray) -> Option<Vec3> {
+        self.frustum.check_collision(ray)
+    }
+
+    fn normal_at(&self, point: Vec3) -> Option<Vec3> {
+        self.frustum.normal_at(point)
+    }
+
+    fn surface_mapping_at(&self, point: Vec3) -> Option<(UnitInterval, UnitInterval)> {
+        self.frustum.surface_mapping_at(point)
+    }
+
+    fn centroid(&self) -> Vec3 {
+        self.frustum.centroid()
+    }
+
+    fn bounding_box(&self) -> Option<BoundingBox> {
+        self.frustum.bounding_box()
+    }
+
+    fn contains(&self, point: Vec3) -> Option<bool> {
+        self.frustum.contains(point)
+    }
+}
+
+/// Distance along the ray to a triangle, `None` if the triangle is missed or parallel
+/// to the ray
+fn triangle_collision_distance(ray: &Ray, [v0, v1, v2]: [Vec3; 3]) -> Option<f64> {
+    // https://en.wikipedia.org/wiki/M%C3%B6ller%E2%80%93Trumbore_intersection_algorithm
+    let edge_1 = v1 - v0;
+    let edge_2 = v2 - v0;
+    let h = ray.direction.cross_product(edge_2);
+    let a = edge_1.dot_product(h);
+    if a.abs() < 1e-6 {
+        return None;
+    }
+    let f = 1.0 / a;
+    let s = ray.source - v0;
+    let u = f * s.dot_product(h);
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = s.cross_product(edge_1);
+    let v = f * ray.direction.dot_product(q);
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let t = f * edge_2.dot_product(q);
+    // Skip the face the ray may be starting from
+    if t > 1e-9 {
+        Some(t)
+    } else {
+        None
+    }
+}
+
+/// Barycentric weights of a point relative to the vertices of a triangle, `None` if
+/// the point is outside the triangle or off its plane
+fn barycentric_weights(point: Vec3, [v0, v1, v2]: [Vec3; 3]) -> Option<[f64; 3]> {
+    let edge_1 = v1 - v0;
+    let edge_2 = v2 - v0;
+    let normal = edge_1.cross_product(edge_2);
+    let double_area = normal.norm();
+    if double_area < 1e-12 {
+        return None;
+    }
+    let to_point = point - v0;
+    if (to_point.dot_product(normal) / double_area).abs() > 1e-6 {
+        return None;
+    }
+    let w1 = to_point.cross_product(edge_2).dot_product(normal) / double_area.powi(2);
+    let w2 = edge_1.cross_product(to_point).dot_product(normal) / double_area.powi(2);
+    let w0 = 1.0 - w1 - w2;
+    if w0 < -1e-9 || w1 < -1e-9 || w2 < -1e-9 {
+        return None;
+    }
+    Some([w0, w1, w2])
+}
+
+/// Single flat triangle
+#[derive(Debug)]
+pub struct Triangle {
+    vertices: [Vec3; 3],
+    normal_normalized: Vec3,
+}
+
+impl Triangle {
+    pub fn new(v0: Vec3, v1: Vec3, v2: Vec3) -> Self {
+        Triangle {
+            vertices: [v0, v1, v2],
+            normal_normalized: (v1 - v0).cross_product(v2 - v0).normalize(),
+        }
+    }
+}
+
+impl Shape for Triangle {
+    fn check_collision(&self, ray: &Ray) -> Option<Vec3> {
+        triangle_collision_distance(ray, self.vertices).map(|t| ray.source + t * ray.direction)
+    }
+
+    fn normal_at(&self, _point: Vec3) -> Option<Vec3> {
+        Some(self.normal_normalized)
+    }
+
+    /// Barycentric coordinates: the second vertex is mapped to u = 1, the third to v = 1
+    fn surface_mapping_at(&self, point: Vec3) -> Option<(UnitInterval, UnitInterval)> {
+        let [_, w1, w2] = barycentric_weights(point, self.vertices)?;
+        Some((w1, w2))
+    }
+
+    fn centroid(&self) -> Vec3 {
+        let [v0, v1, v2] = self.vertices;
+        (1.0 / 3.0) * (v0 + v1 + v2)
+    }
+
+    fn bounding_box(&self) -> Option<BoundingBox> {
+        let [v0, v1, v2] = self.vertices;
+        Some(
+            BoundingBox::new(v0, v0)
+                .union(&BoundingBox::new(v1, v1))
+                .union(&BoundingBox::new(v2, v2)),
+        )
+    }
+}
+
+/// Mesh of triangles, with a normal and a surface mapping for each vertex
+#[derive(Debug)]
+pub struct TriangleMesh {
+    vertices: Vec<Vec3>,
+    normals: Vec<Vec3>,
+    uvs: Vec<(UnitInterval, UnitInterval)>,
+    faces: Vec<[usize; 3]>,
+}
+
+impl TriangleMesh {
+    pub fn new(
+        vertices: Vec<Vec3>,
+        normals: Vec<Vec3>,
+        uvs: Vec<(UnitInterval, UnitInterval)>,
+        faces: Vec<[usize; 3]>,
+    ) -> Self {
+        assert_eq!(vertices.len(), normals.len(), "one normal per vertex");
+        assert_eq!(vertices.len(), uvs.len(), "one uv mapping per vertex");
+        assert!(
+            faces.iter().flatten().all(|&index| index < vertices.len()),
+            "face references an unknown vertex"
+        );
+        TriangleMesh {
+            vertices,
+            normals: normals.iter().map(Vec3::normalize).collect(),
+            uvs,
+            faces,
+        }
+    }
+
+    pub fn face_count(&self) -> usize {
+        self.faces.len()
+    }
+
+    fn face_vertices(&self, face: &[usize; 3]) -> [Vec3; 3] {
+        [
+            self.vertices[face[0]],
+            self.vertices[face[1]],
+            self.vertices[face[2]],
+        ]
+    }
+
+    /// Barycentric weights of the point for the first face containing it
+    fn locate(&self, point: Vec3) -> Option<(&[usize; 3], [f64; 3])> {
+        self.faces.iter().find_map(|face| {
+            barycentric_weights(point, self.face_vertices(face)).map(|weights| (face, weights))
+        })
+    }
+}
+
+impl Shape for TriangleMesh {
+    fn check_collision(&self, ray: &Ray) -> Option<Vec3> {
+        let mut nearest_t = f64::INFINITY;
+        for face in &self.faces {
+            if let Some(t) = triangle_collision_distance(ray, self.face_vertices(face)) {
+                nearest_t = nearest_t.min(t);
+            }
+        }
+        if nearest_t.is_finite() {
+            Some(ray.source + nearest_t * ray.direction)
+        } else {
+            None
+        }
+    }
+
+    fn normal_at(&self, point: Vec3) -> Option<Vec3> {
+        let (face, weights) = self.locate(point)?;
+        let normal = weights[0] * self.normals[face[0]]
+            + weights[1] * self.normals[face[1]]
+            + weights[2] * self.normals[face[2]];
+        Some(normal.normalize())
+    }
+
+    fn surface_mapping_at(&self, point: Vec3) -> Option<(UnitInterval, UnitInterval)> {
+        let (face, weights) = self.locate(point)?;
+        let (mut u, mut v) = (0.0, 0.0);
+        for (&index, weight) in face.iter().zip(weights.iter()) {
+            u += weight * self.uvs[index].0;
+            v += weight * self.uvs[index].1;
+        }
+        Some((u, v))
+    }
+
+    fn centroid(&self) -> Vec3 {
+        let sum = self
+            .vertices
+            .iter()
+            .fold(Vec3::ZERO, |acc, &vertex| acc + vertex);
+        (1.0 / self.vertices.len() as f64) * sum
+    }
+
+    fn bounding_box(&self) -> Option<BoundingBox> {
+        let first_vertex = *self.vertices.first()?;
+        let bounding_box = self.vertices.iter().fold(
+            BoundingBox::new(first_vertex, first_vertex),
+            |acc, &vertex| acc.union(&BoundingBox::new(vertex, vertex)),
+        );
+        Some(bounding_box)
+    }
+}
+
+/// Build a UV sphere mesh, with `segments` stacks and twice more slices.
+///
+/// Surface mapping is the same as the one of `Sphere`.
+pub fn tessellate_sphere(center: Vec3, radius: f64, segments: u32) -> TriangleMesh {
+    let stacks = segments.max(2);
+    let slices = 2 * stacks;
+    let mut vertices = Vec::new();
+    let mut normals = Vec::new();
+    let mut uvs = Vec::new();
+    for stack in 0..=stacks {
+        let v = stack as f64 / stacks as f64;
+        let theta = PI * v;
+        for slice in 0..=slices {
+            let u = slice as f64 / slices as f64;
+            let phi = 2.0 * PI * (u - 0.5);
+            let normal = Vec3::new(
+                theta.sin() * phi.cos(),
+                theta.cos(),
+                theta.sin() * phi.sin(),
+            );
+            vertices.push(center + radius * normal);
+            normals.push(normal);
+            uvs.push((u, v));
+        }
+    }
+    let index = |stack: u32, slice: u32| (stack * (slices + 1) + slice) as usize;
+    let mut faces = Vec::new();
+    for stack in 0..stacks {
+        for slice in 0..slices {
+            let top_left = index(stack, slice);
+            let bottom_left = index(stack + 1, slice);
+            let bottom_right = index(stack + 1, slice + 1);
+            let top_right = index(stack, slice + 1);
+            // Triangles touching the poles would be degenerated
+            if stack != stacks - 1 {
+                faces.push([top_left, bottom_left, bottom_right]);
+            }
+            if stack != 0 {
+                faces.push([top_left, bottom_right, top_right]);
+            }
+        }
+    }
+    TriangleMesh::new(vertices, normals, uvs, faces)
+}
+
+/// Shape placed in the scene by a transformation, so that a single shape defined
+/// around the origin can be translated, rotated and scaled
+///
+/// Rays are moved into the local space of the shape, and normals back to the world
+/// with the inverse-transpose of the transformation, which keeps them orthogonal to
+/// the surface under non-uniform scale.
+#[derive(Debug)]
+pub struct Transformed<S: Shape> {
+    shape: S,
+    transform: Mat4,
+    inverse: Mat4,
+}
+
+impl<S: Shape> Transformed<S> {
+    /// `None` if the transformation cannot be inverted, e.g. with a null scale
+    pub fn new(shape: S, transform: Mat4) -> Option<Self> {
+        Some(Transformed {
+            shape,
+            transform,
+            inverse: transform.inverse()?,
+        })
+    }
+}
+
+impl<S: Shape> Shape for Transformed<S> {
+    fn check_collision(&self, ray: &Ray) -> Option<Vec3> {
+        let local_ray = Ray::new(
+            self.inverse.transform_point(ray.source),
+            self.inverse.transform_vector(ray.direction),
+        );
+        self.shape
+            .check_collision(&local_ray)
+            .map(|point| self.transform.transform_point(point))
+    }
+
+    fn normal_at(&self, point: Vec3) -> Option<Vec3> {
+        let local_normal = self.shape.normal_at(self.inverse.transform_point(point))?;
+        Some(
+            self.inverse
+                .transpose()
+                .transform_vector(local_normal)
+                .normalize(),
+        )
+    }
+
+    fn surface_mapping_at(&self, point: Vec3) -> Option<(UnitInterval, UnitInterval)> {
+        self.shape
+            .surface_mapping_at(self.inverse.transform_point(point))
+    }
+
+    fn centroid(&self) -> Vec3 {
+        self.transform.transform_point(self.shape.centroid())
+    }
+
+    fn bounding_box(&self) -> Option<BoundingBox> {
+        let local_box = self.shape.bounding_box()?;
+        let corner = |index: usize| {
+            let pick = |bit: usize, min: f64, max: f64| if index & bit == 0 { min } else { max };
+            let local_corner = Vec3::new(
+                pick(1, local_box.min.x, local_box.max.x),
+                pick(2, local_box.min.y, local_box.max.y),
+                pick(4, local_box.min.z, local_box.max.z),
+            );
+            let world_corner = self.transform.transform_point(local_corner);
+            BoundingBox::new(world_corner, world_corner)
+        };
+        Some(
+            (1..8)
+                .map(corner)
+                .fold(corner(0), |result, corner_box| result.union(&corner_box)),
+        )
+    }
+
+    fn contains(&self, point: Vec3) -> Option<bool> {
+        self.shape.contains(self.inverse.transform_point(point))
+    }
 }
 
 #[cfg(test)]
@@ -309,4 +1179,352 @@ mod tests {
         );
         assert!(sphere.check_collision(&ray).is_none()); // There is no collision
     }
+
+    #[test]
+    fn ray_hits_a_small_distant_sphere_accurately() {
+        let sphere = Sphere {
+            center: Vec3::new(8.0e5, 5.0e5, 3.0e5),
+            radius: 0.5,
+        };
+        // Aim close to the silhouette, where the root difference is small
+        let offset = 0.49 * Vec3::new(0.0, 3.0, -5.0).normalize();
+        let ray = Ray::ray_from_to(Vec3::new(0.0, 0.0, 0.0), sphere.center + offset);
+        let hit = sphere.check_collision(&ray).unwrap();
+        assert!(((hit - sphere.center).norm() - sphere.radius).abs() < 1e-6);
+        assert!((hit - sphere.center).dot_product(ray.direction) < 0.0);
+        let mut batch_hit = [None];
+        sphere.check_collision_batch(std::slice::from_ref(&ray), &mut batch_hit);
+        assert!((batch_hit[0].unwrap() - hit).norm() < 1e-9);
+        let distance = (hit - ray.source).norm();
+        assert!(sphere.occludes(&ray, distance + 1e-6));
+        assert!(!sphere.occludes(&ray, distance - 1e-3));
+    }
+
+    #[test]
+    fn tessellated_sphere_silhouette_matches_sphere() {
+        let sphere = Sphere {
+            center: Vec3::new(1.0, 2.0, 3.0),
+            radius: 2.0,
+        };
+        let mesh = tessellate_sphere(sphere.center, sphere.radius, 64);
+        for step_x in -12..=12 {
+            for step_y in -12..=12 {
+                let offset = Vec3::new(step_x as f64 * 0.2, step_y as f64 * 0.2, 0.0);
+                let distance_to_axis = offset.norm();
+                if (distance_to_axis - sphere.radius).abs() < 0.02 {
+                    // Too near from the silhouette to be compared
+                    continue;
+                }
+                let ray = Ray::new(
+                    sphere.center + offset + Vec3::new(0.0, 0.0, -10.0),
+                    Vec3::new(0.0, 0.0, 1.0),
+                );
+                match (sphere.check_collision(&ray), mesh.check_collision(&ray)) {
+                    (None, None) => {}
+                    (Some(expected), Some(result)) => {
+                        assert!(expected.distance(result) < 0.01 * sphere.radius)
+                    }
+                    _ => panic!("silhouette mismatch for offset {:?}", offset),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn tessellated_sphere_normal_is_radial() {
+        let mesh = tessellate_sphere(Vec3::ZERO, 1.0, 32);
+        let ray = Ray::new(Vec3::new(0.3, 0.2, -5.0), Vec3::new(0.0, 0.0, 1.0));
+        let point = mesh.check_collision(&ray).unwrap();
+        let normal = mesh.normal_at(point).unwrap();
+        assert!(normal.distance(point.normalize()) < 0.01);
+    }
+
+    #[test]
+    fn square_plan_mapping_rejects_off_plane_point() {
+        let square = SquarePlan::new(Vec3::ZERO, Vec3::new(0.0, 1.0, 0.0), 2.0);
+        let mapping_at_height = |y| square.surface_mapping_at(Vec3::new(0.5, y, 0.5));
+        assert!(mapping_at_height(0.0).is_some());
+        assert!(mapping_at_height(1e-9).is_some());
+        assert!(mapping_at_height(0.1).is_none());
+    }
+
+    #[test]
+    fn translated_unit_sphere_collides_at_new_position() {
+        let unit_sphere = Sphere {
+            center: Vec3::ZERO,
+            radius: 1.0,
+        };
+        let translation = Mat4::translation(Vec3::new(5.0, 0.0, 0.0));
+        let sphere = Transformed::new(unit_sphere, translation).unwrap();
+        let ray = Ray::new(Vec3::new(5.0, 0.0, -10.0), Vec3::new(0.0, 0.0, 1.0));
+        let point = sphere.check_collision(&ray).unwrap();
+        assert!(point.distance(Vec3::new(5.0, 0.0, -1.0)) < 1e-9);
+        let normal = sphere.normal_at(point).unwrap();
+        assert!(normal.distance(Vec3::new(0.0, 0.0, -1.0)) < 1e-9);
+        assert_eq!(sphere.centroid(), Vec3::new(5.0, 0.0, 0.0));
+        assert_eq!(sphere.contains(Vec3::new(5.5, 0.0, 0.0)), Some(true));
+        assert_eq!(sphere.contains(Vec3::ZERO), Some(false));
+        let ray = Ray::new(Vec3::new(0.0, 0.0, -10.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!(sphere.check_collision(&ray).is_none());
+    }
+
+    #[test]
+    fn scaled_sphere_normal_uses_inverse_transpose() {
+        let unit_sphere = Sphere {
+            center: Vec3::ZERO,
+            radius: 1.0,
+        };
+        // Ellipsoid twice as wide along x
+        let ellipsoid =
+            Transformed::new(unit_sphere, Mat4::scale(Vec3::new(2.0, 1.0, 1.0))).unwrap();
+        let ray = Ray::new(Vec3::new(-10.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let point = ellipsoid.check_collision(&ray).unwrap();
+        assert!(point.distance(Vec3::new(-2.0, 0.0, 0.0)) < 1e-9);
+        // Gradient of x²/4 + y² on the surface is along (x/2, 2y)
+        let point = Vec3::new(2.0_f64.sqrt(), 2.0_f64.sqrt() / 2.0, 0.0);
+        let normal = ellipsoid.normal_at(point).unwrap();
+        assert!(normal.distance(Vec3::new(1.0, 2.0, 0.0).normalize()) < 1e-9);
+        let bounding_box = ellipsoid.bounding_box().unwrap();
+        assert!(bounding_box.min.distance(Vec3::new(-2.0, -1.0, -1.0)) < 1e-9);
+        assert!(bounding_box.max.distance(Vec3::new(2.0, 1.0, 1.0)) < 1e-9);
+    }
+
+    #[test]
+    fn sphere_centroid_is_center() {
+        let sphere = Sphere {
+            center: Vec3::new(3.0, 0.0, 0.0),
+            radius: 1.0,
+        };
+        assert_eq!(sphere.centroid(), Vec3::new(3.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn sphere_batch_collision_matches_single_ray() {
+        let sphere = Sphere {
+            center: Vec3::new(0.0, 0.0, 5.0),
+            radius: 1.5,
+        };
+        let rays: Vec<Ray> = (0..11)
+            .map(|i| {
+                let x = -2.0 + 0.4 * i as f64;
+                Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(x, 0.3, 5.0))
+            })
+            .chain(std::iter::once(Ray::new(
+                Vec3::new(0.0, 0.0, 5.0),
+                Vec3::new(0.0, 1.0, 0.0),
+            )))
+            .chain(std::iter::once(Ray::new(
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(0.0, 0.0, -1.0),
+            )))
+            .collect();
+        let mut batch = vec![None; rays.len()];
+        sphere.check_collision_batch(&rays, &mut batch);
+        for (ray, batch_hit) in rays.iter().zip(batch) {
+            match (sphere.check_collision(ray), batch_hit) {
+                (None, None) => {}
+                (Some(single), Some(batch)) => {
+                    assert!(f64_eq(single.x, batch.x));
+                    assert!(f64_eq(single.y, batch.y));
+                    assert!(f64_eq(single.z, batch.z));
+                }
+                (single, batch) => panic!("{:?} != {:?}", single, batch),
+            }
+        }
+    }
+
+    #[test]
+    fn sphere_contains_center_but_not_far_point() {
+        let sphere = Sphere {
+            center: Vec3::new(1.0, 2.0, 3.0),
+            radius: 2.0,
+        };
+        assert_eq!(sphere.contains(sphere.center), Some(true));
+        assert_eq!(sphere.contains(Vec3::new(10.0, 2.0, 3.0)), Some(false));
+    }
+
+    #[test]
+    fn bounding_box_contains_its_bounds_only() {
+        let bounding_box = BoundingBox::new(Vec3::ZERO, Vec3::new(1.0, 2.0, 3.0));
+        assert!(bounding_box.contains(bounding_box.center()));
+        assert!(bounding_box.contains(bounding_box.max));
+        assert!(!bounding_box.contains(Vec3::new(0.5, 2.5, 1.0)));
+    }
+
+    #[test]
+    fn plan_has_no_inside() {
+        let plan = InfinitePlan::new(Vec3::ZERO, Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(plan.contains(Vec3::ZERO), None);
+    }
+
+    #[test]
+    fn occludes_agrees_with_check_collision() {
+        use rand::rngs::StdRng;
+        use rand::{Rng, SeedableRng};
+        let shapes: Vec<Box<dyn Shape>> = vec![
+            Box::new(Sphere {
+                center: Vec3::new(1.0, 0.5, 2.0),
+                radius: 1.5,
+            }),
+            Box::new(InfinitePlan::new(
+                Vec3::new(0.0, -1.0, 0.0),
+                Vec3::new(0.2, 1.0, 0.1),
+            )),
+            Box::new(SquarePlan::new(
+                Vec3::new(0.0, 0.0, 3.0),
+                Vec3::new(0.0, 0.0, 1.0),
+                4.0,
+            )),
+        ];
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut random_point = || {
+            Vec3::new(
+                rng.gen_range(-4.0, 4.0),
+                rng.gen_range(-4.0, 4.0),
+                rng.gen_range(-4.0, 4.0),
+            )
+        };
+        let mut hit_count = 0;
+        for _ in 0..500 {
+            let ray = Ray::ray_from_to(random_point(), random_point());
+            let max_distance = random_point().norm();
+            for shape in &shapes {
+                let expected = shape.check_collision(&ray).is_some_and(|point| {
+                    let distance = point.distance(ray.source);
+                    distance > OCCLUSION_MIN_DISTANCE && distance <= max_distance
+                });
+                assert_eq!(shape.occludes(&ray, max_distance), expected, "{:?}", shape);
+                hit_count += expected as u32;
+            }
+        }
+        // Both outcomes are tested
+        assert!(hit_count > 100 && hit_count < 1400);
+    }
+
+    #[test]
+    fn frustum_with_equal_radii_behaves_like_cylinder() {
+        let frustum = Frustum::new(
+            Vec3::new(0.0, -1.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            1.0,
+            1.0,
+            2.0,
+        );
+        // Side hit: at the cylinder radius, with a radial normal
+        let ray = Ray::new(Vec3::new(-5.0, 0.5, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let point = frustum.check_collision(&ray).unwrap();
+        assert!(f64_eq(point.x, -1.0) && f64_eq(point.y, 0.5) && f64_eq(point.z, 0.0));
+        let normal = frustum.normal_at(point).unwrap();
+        assert!(f64_eq(normal.x, -1.0) && f64_eq(normal.y, 0.0) && f64_eq(normal.z, 0.0));
+        // Oblique side hit: same distance to the axis wherever it is hit
+        let ray = Ray::ray_from_to(Vec3::new(0.3, 0.9, -5.0), Vec3::new(0.3, -0.2, 0.0));
+        let point = frustum.check_collision(&ray).unwrap();
+        assert!(f64_eq((point.x * point.x + point.z * point.z).sqrt(), 1.0));
+        // Cap hit: flat, with a normal along the axis
+        let ray = Ray::new(Vec3::new(0.5, -5.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let point = frustum.check_collision(&ray).unwrap();
+        assert!(f64_eq(point.y, -1.0));
+        let normal = frustum.normal_at(point).unwrap();
+        assert!(f64_eq(normal.y, -1.0));
+        // Misses beyond the radius or the height
+        let ray = Ray::new(Vec3::new(-5.0, 0.0, 1.1), Vec3::new(1.0, 0.0, 0.0));
+        assert!(frustum.check_collision(&ray).is_none());
+        let ray = Ray::new(Vec3::new(-5.0, 1.1, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(frustum.check_collision(&ray).is_none());
+        assert_eq!(frustum.contains(Vec3::new(0.9, 0.9, 0.0)), Some(true));
+        assert_eq!(frustum.contains(Vec3::new(0.9, 0.0, 0.9)), Some(false));
+    }
+
+    #[test]
+    fn frustum_normal_follows_slant() {
+        // Radius shrinks from 2 to 1 over a height of 1: 45 degree slant
+        let frustum = Frustum::new(Vec3::ZERO, Vec3::new(0.0, 1.0, 0.0), 2.0, 1.0, 1.0);
+        let ray = Ray::new(Vec3::new(-5.0, 0.5, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let point = frustum.check_collision(&ray).unwrap();
+        assert!(f64_eq(point.x, -1.5));
+        let normal = frustum.normal_at(point).unwrap();
+        let expected = Vec3::new(-1.0, 1.0, 0.0).normalize();
+        assert!(f64_eq(normal.x, expected.x) && f64_eq(normal.y, expected.y));
+        let (_, v) = frustum.surface_mapping_at(point).unwrap();
+        assert!(f64_eq(v, 0.5));
+    }
+
+    #[test]
+    fn cylinder_along_x_axis_has_radial_and_cap_normals() {
+        let cylinder = Cylinder::new(Vec3::ZERO, Vec3::new(1.0, 0.0, 0.0), 1.0, 4.0);
+        // Side hit from above: radial normal, height mapped to v
+        let ray = Ray::new(Vec3::new(1.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let point = cylinder.check_collision(&ray).unwrap();
+        assert!(point.distance(Vec3::new(1.0, 1.0, 0.0)) < 1e-9);
+        let normal = cylinder.normal_at(point).unwrap();
+        assert!(normal.distance(Vec3::new(0.0, 1.0, 0.0)) < 1e-9);
+        let (_, v) = cylinder.surface_mapping_at(point).unwrap();
+        assert!(f64_eq(v, 0.25));
+        // Far cap hit from beyond the top: normal along the axis
+        let ray = Ray::new(Vec3::new(10.0, 0.5, 0.0), Vec3::new(-1.0, 0.0, 0.0));
+        let point = cylinder.check_collision(&ray).unwrap();
+        assert!(f64_eq(point.x, 4.0));
+        let normal = cylinder.normal_at(point).unwrap();
+        assert!(normal.distance(Vec3::new(1.0, 0.0, 0.0)) < 1e-9);
+        // Side ray passing beyond the height band
+        let ray = Ray::new(Vec3::new(5.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        assert!(cylinder.check_collision(&ray).is_none());
+    }
+
+    #[test]
+    fn ray_hits_triangle_inside_only() {
+        let triangle = Triangle::new(
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(0.0, 2.0, 0.0),
+        );
+        let ray = Ray::new(Vec3::new(0.5, 0.5, -5.0), Vec3::new(0.0, 0.0, 1.0));
+        let point = triangle.check_collision(&ray).unwrap();
+        assert!(point.distance(Vec3::new(0.5, 0.5, 0.0)) < 1e-9);
+        let normal = triangle.normal_at(point).unwrap();
+        assert!(normal.distance(Vec3::new(0.0, 0.0, 1.0)) < 1e-9);
+        let (u, v) = triangle.surface_mapping_at(point).unwrap();
+        assert!(f64_eq(u, 0.25) && f64_eq(v, 0.25));
+        // Beyond the hypotenuse
+        let ray = Ray::new(Vec3::new(1.5, 1.5, -5.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!(triangle.check_collision(&ray).is_none());
+        // Parallel to the triangle plane
+        let ray = Ray::new(Vec3::new(-1.0, 0.5, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(triangle.check_collision(&ray).is_none());
+        // Triangle behind the ray
+        let ray = Ray::new(Vec3::new(0.5, 0.5, 5.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!(triangle.check_collision(&ray).is_none());
+    }
+
+    #[test]
+    fn ray_hits_axis_aligned_box_faces() {
+        let unit_box = AxisAlignedBox::new(Vec3::new(-1.0, -1.0, -1.0), Vec3::new(1.0, 1.0, 1.0));
+        let ray = Ray::new(Vec3::new(0.5, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0));
+        let point = unit_box.check_collision(&ray).unwrap();
+        assert!(point.distance(Vec3::new(0.5, 0.0, -1.0)) < 1e-9);
+        let normal = unit_box.normal_at(point).unwrap();
+        assert!(normal.distance(Vec3::new(0.0, 0.0, -1.0)) < 1e-9);
+        let (u, v) = unit_box.surface_mapping_at(point).unwrap();
+        assert!(f64_eq(u, 0.75) && f64_eq(v, 0.5));
+        // Missing the box, or pointing away from it
+        let ray = Ray::new(Vec3::new(1.5, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!(unit_box.check_collision(&ray).is_none());
+        let ray = Ray::new(Vec3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(unit_box.check_collision(&ray).is_none());
+        assert!(unit_box.normal_at(Vec3::ZERO).is_none());
+    }
+
+    #[test]
+    fn ray_inside_axis_aligned_box_hits_exit_face() {
+        let unit_box = AxisAlignedBox::new(Vec3::new(-1.0, -1.0, -1.0), Vec3::new(1.0, 1.0, 1.0));
+        let ray = Ray::new(Vec3::ZERO, Vec3::new(0.0, 1.0, 0.0));
+        let point = unit_box.check_collision(&ray).unwrap();
+        assert!(point.distance(Vec3::new(0.0, 1.0, 0.0)) < 1e-9);
+        // A ray leaving the surface it starts on does not hit it again
+        let entry = Ray::new(Vec3::new(0.0, -1.0, 0.0), Vec3::new(0.0, 1.0, 0.0)).shift_source();
+        let exit = unit_box.check_collision(&entry).unwrap();
+        assert!(exit.distance(Vec3::new(0.0, 1.0, 0.0)) < 1e-9);
+        let outward = Ray::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 1.0, 0.0)).shift_source();
+        assert!(unit_box.check_collision(&outward).is_none());
+    }
 }
@@ -22,30 +22,161 @@ OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 SOFTWARE.
 */
 
-use crate::colors::Color;
+use crate::colors::{Color, Radiance};
 use crate::lights::AnyLightObject;
 use crate::primitives::Ray;
 use crate::result::RaytracerError;
 use crate::result::Result;
-use crate::scene::{Scene, SceneObject};
+use crate::scene::{RayEmitter, Scene, SceneObject};
+use crate::textures::Transparency;
 use crate::vector::Vec3;
 use crate::UnitInterval;
-use rand::Rng;
+use rand::{Rng, RngCore};
 use std::f64;
+use std::f64::consts::PI;
+use std::sync::atomic::Ordering;
 
+/// Way of computing the color of a pixel. Rays are launched from `camera`, which is
+/// the camera of `scene` except for frames rendered from another point of view.
 pub trait AnyPixelRenderStrategy: Send + Sync {
     fn render_pixel(
         &self,
         scene: &Scene,
+        camera: &dyn RayEmitter,
         canvas_x: UnitInterval,
         canvas_y: UnitInterval,
         pixel_width: f64,
         pixel_height: f64,
-    ) -> Result<Color>;
+    ) -> Result<Radiance>;
+
+    fn render_pixel_passes(
+        &self,
+        scene: &Scene,
+        camera: &dyn RayEmitter,
+        canvas_x: UnitInterval,
+        canvas_y: UnitInterval,
+        pixel_width: f64,
+        pixel_height: f64,
+    ) -> Result<RenderPasses>;
+
+    /// Information about what is seen through the pixel, using its center ray
+    fn render_pixel_metadata(
+        &self,
+        scene: &Scene,
+        camera: &dyn RayEmitter,
+        canvas_x: UnitInterval,
+        canvas_y: UnitInterval,
+        pixel_width: f64,
+        pixel_height: f64,
+    ) -> PixelMetadata {
+        let x_unit = pixel_width / 2.0 + canvas_x;
+        let y_unit = pixel_height / 2.0 + canvas_y;
+        let camera_ray = camera.generate_ray(x_unit, y_unit);
+        let collision = search_camera_collision(&camera_ray, scene);
+        PixelMetadata {
+            depth: collision
+                .as_ref()
+                .map(|context| context.collision_point.distance(camera_ray.source)),
+            object_index: collision.map(|context| context.array_index),
+            sample_count: 1,
+        }
+    }
+
+    /// Seed of the random choices of the strategy, `None` if they are not reproducible
+    fn seed(&self) -> Option<u64> {
+        None
+    }
+}
+
+/// Data about a pixel, other than its color
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PixelMetadata {
+    /// Distance to the first surface hit, `None` if the background is seen
+    pub depth: Option<f64>,
+    /// Index of the first object hit in the scene
+    pub object_index: Option<usize>,
+    /// Number of rays used to compute the color
+    pub sample_count: u32,
+}
+
+/// Light of a ray split by lighting component, for compositing
+#[derive(Debug, Default, Clone)]
+pub struct RenderPasses {
+    pub diffuse: Radiance,
+    pub specular: Radiance,
+    pub reflection: Radiance,
+    pub refraction: Radiance,
+    pub ambient: Radiance,
+    /// Light emitted by the surface itself
+    pub emission: Radiance,
+    /// World color, when no object is hit
+    pub background: Radiance,
+}
+
+impl RenderPasses {
+    pub const DIFFUSE: &'static str = "diffuse";
+    pub const SPECULAR: &'static str = "specular";
+    pub const REFLECTION: &'static str = "reflection";
+    pub const REFRACTION: &'static str = "refraction";
+    pub const AMBIENT: &'static str = "ambient";
+    pub const EMISSION: &'static str = "emission";
+    pub const BACKGROUND: &'static str = "background";
+
+    fn with_background(color: Color) -> Self {
+        RenderPasses {
+            background: color.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Sum of all the passes, equal to the color of the normal render
+    pub fn combined(&self) -> Radiance {
+        self.named_passes()
+            .iter()
+            .fold(Radiance::default(), |acc, (_, radiance)| acc + **radiance)
+    }
+
+    pub fn named_passes(&self) -> [(&'static str, &Radiance); 7] {
+        [
+            (Self::DIFFUSE, &self.diffuse),
+            (Self::SPECULAR, &self.specular),
+            (Self::REFLECTION, &self.reflection),
+            (Self::REFRACTION, &self.refraction),
+            (Self::AMBIENT, &self.ambient),
+            (Self::EMISSION, &self.emission),
+            (Self::BACKGROUND, &self.background),
+        ]
+    }
+
+    fn scale(&self, coeff: f64) -> RenderPasses {
+        RenderPasses {
+            diffuse: coeff * self.diffuse,
+            specular: coeff * self.specular,
+            reflection: coeff * self.reflection,
+            refraction: coeff * self.refraction,
+            ambient: coeff * self.ambient,
+            emission: coeff * self.emission,
+            background: coeff * self.background,
+        }
+    }
+}
+
+impl std::ops::AddAssign for RenderPasses {
+    fn add_assign(&mut self, rhs: Self) {
+        self.diffuse += rhs.diffuse;
+        self.specular += rhs.specular;
+        self.reflection += rhs.reflection;
+        self.refraction += rhs.refraction;
+        self.ambient += rhs.ambient;
+        self.emission += rhs.emission;
+        self.background += rhs.background;
+    }
 }
 
 pub mod strategy {
     use super::*;
+    use std::cell::Cell;
+    use std::collections::{HashMap, VecDeque};
 
     pub struct StandardRenderStrategy;
 
@@ -53,55 +184,877 @@ pub mod strategy {
         fn render_pixel(
             &self,
             scene: &Scene,
+            camera: &dyn RayEmitter,
             canvas_x: UnitInterval,
             canvas_y: UnitInterval,
             pixel_width: f64,
             pixel_height: f64,
-        ) -> Result<Color> {
+        ) -> Result<Radiance> {
             let x_unit = pixel_width / 2.0 + canvas_x;
             let y_unit = pixel_height / 2.0 + canvas_y;
-            let camera_ray = scene.camera.generate_ray(x_unit, y_unit);
+            let camera_ray = camera.generate_ray(x_unit, y_unit);
             launch_ray(&camera_ray, scene, 0)
         }
+
+        fn render_pixel_passes(
+            &self,
+            scene: &Scene,
+            camera: &dyn RayEmitter,
+            canvas_x: UnitInterval,
+            canvas_y: UnitInterval,
+            pixel_width: f64,
+            pixel_height: f64,
+        ) -> Result<RenderPasses> {
+            let x_unit = pixel_width / 2.0 + canvas_x;
+            let y_unit = pixel_height / 2.0 + canvas_y;
+            let camera_ray = camera.generate_ray(x_unit, y_unit);
+            launch_ray_passes(&camera_ray, scene, 0)
+        }
+    }
+
+    /// Grey levels showing the distance to the nearest object, white being the
+    /// closest and black at `max_depth` or beyond
+    pub struct DepthRenderStrategy {
+        pub max_depth: f64,
+    }
+
+    impl DepthRenderStrategy {
+        fn depth_color(
+            &self,
+            scene: &Scene,
+            camera: &dyn RayEmitter,
+            canvas_x: UnitInterval,
+            canvas_y: UnitInterval,
+        ) -> Color {
+            let camera_ray = camera.generate_ray(canvas_x, canvas_y);
+            match search_camera_collision(&camera_ray, scene) {
+                Some(context) => {
+                    let depth = context.collision_point.distance(camera_ray.source);
+                    let level = 1.0 - depth / self.max_depth;
+                    Color::new(level, level, level)
+                }
+                None => Color::BLACK,
+            }
+        }
+    }
+
+    impl AnyPixelRenderStrategy for DepthRenderStrategy {
+        fn render_pixel(
+            &self,
+            scene: &Scene,
+            camera: &dyn RayEmitter,
+            canvas_x: UnitInterval,
+            canvas_y: UnitInterval,
+            pixel_width: f64,
+            pixel_height: f64,
+        ) -> Result<Radiance> {
+            Ok(self
+                .depth_color(
+                    scene,
+                    camera,
+                    pixel_width / 2.0 + canvas_x,
+                    pixel_height / 2.0 + canvas_y,
+                )
+                .into())
+        }
+
+        fn render_pixel_passes(
+            &self,
+            scene: &Scene,
+            camera: &dyn RayEmitter,
+            canvas_x: UnitInterval,
+            canvas_y: UnitInterval,
+            pixel_width: f64,
+            pixel_height: f64,
+        ) -> Result<RenderPasses> {
+            Ok(RenderPasses::with_background(self.depth_color(
+                scene,
+                camera,
+                pixel_width / 2.0 + canvas_x,
+                pixel_height / 2.0 + canvas_y,
+            )))
+        }
+    }
+
+    /// Space in which `NormalRenderStrategy` expresses normals
+    #[derive(Debug, Copy, Clone, PartialEq)]
+    pub enum NormalSpace {
+        World,
+        /// Basis of the camera, see `CameraBasis::to_view_space`. World space is used
+        /// for cameras without basis.
+        View,
+    }
+
+    /// Normal of the nearest object as a color, each component between -1 and 1
+    /// being mapped between 0 and 1, black where no object is seen
+    pub struct NormalRenderStrategy {
+        pub space: NormalSpace,
+    }
+
+    impl NormalRenderStrategy {
+        fn normal_color(
+            &self,
+            scene: &Scene,
+            camera: &dyn RayEmitter,
+            canvas_x: UnitInterval,
+            canvas_y: UnitInterval,
+        ) -> Result<Color> {
+            let camera_ray = camera.generate_ray(canvas_x, canvas_y);
+            let context = match search_camera_collision(&camera_ray, scene) {
+                Some(context) => context,
+                None => return Ok(Color::BLACK),
+            };
+            let normal = context
+                .object
+                .normal_at(context.collision_point)
+                .ok_or(RaytracerError::NormalNotFound(context.array_index))?
+                .normalize();
+            let normal = match (self.space, camera.basis()) {
+                (NormalSpace::View, Some(basis)) => basis.to_view_space(normal),
+                _ => normal,
+            };
+            Ok(Color::new(
+                0.5 * (normal.x + 1.0),
+                0.5 * (normal.y + 1.0),
+                0.5 * (normal.z + 1.0),
+            ))
+        }
+    }
+
+    impl AnyPixelRenderStrategy for NormalRenderStrategy {
+        fn render_pixel(
+            &self,
+            scene: &Scene,
+            camera: &dyn RayEmitter,
+            canvas_x: UnitInterval,
+            canvas_y: UnitInterval,
+            pixel_width: f64,
+            pixel_height: f64,
+        ) -> Result<Radiance> {
+            self.normal_color(
+                scene,
+                camera,
+                pixel_width / 2.0 + canvas_x,
+                pixel_height / 2.0 + canvas_y,
+            )
+            .map(Radiance::from)
+        }
+
+        fn render_pixel_passes(
+            &self,
+            scene: &Scene,
+            camera: &dyn RayEmitter,
+            canvas_x: UnitInterval,
+            canvas_y: UnitInterval,
+            pixel_width: f64,
+            pixel_height: f64,
+        ) -> Result<RenderPasses> {
+            Ok(RenderPasses::with_background(self.normal_color(
+                scene,
+                camera,
+                pixel_width / 2.0 + canvas_x,
+                pixel_height / 2.0 + canvas_y,
+            )?))
+        }
+    }
+
+    pub struct RandomAntiAliasingRenderStrategy {
+        pub rays_per_pixel: u32,
+        /// If set, the rays of a pixel only depend on this seed and on the pixel
+        /// position, so that the render is the same whatever the pixel order
+        pub seed: Option<u64>,
+    }
+
+    const SPLIT_MIX_GAMMA: u64 = 0x9e37_79b9_7f4a_7c15;
+
+    /// SplitMix64 finalizer, a stable hash of a 64 bit value
+    fn split_mix(value: u64) -> u64 {
+        let mut z = value.wrapping_add(SPLIT_MIX_GAMMA);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Small and fast random generator (SplitMix64) used for the rays of a pixel.
+    ///
+    /// Its sequence only depends on its seed, whatever the platform or the Rust release,
+    /// and it is cheap enough to be created for each pixel.
+    #[derive(Debug, Clone)]
+    pub struct PixelRng {
+        state: u64,
+    }
+
+    impl PixelRng {
+        pub fn new(seed: u64) -> Self {
+            PixelRng { state: seed }
+        }
+
+        /// Generator of the pixel at (`canvas_x`, `canvas_y`) for a render seed
+        pub fn for_pixel(seed: u64, canvas_x: UnitInterval, canvas_y: UnitInterval) -> Self {
+            let row_hash = split_mix(seed ^ canvas_y.to_bits());
+            PixelRng::new(split_mix(row_hash ^ canvas_x.to_bits()))
+        }
+    }
+
+    impl RngCore for PixelRng {
+        fn next_u32(&mut self) -> u32 {
+            (self.next_u64() >> 32) as u32
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            let value = split_mix(self.state);
+            self.state = self.state.wrapping_add(SPLIT_MIX_GAMMA);
+            value
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            for chunk in dest.chunks_mut(8) {
+                let bytes = self.next_u64().to_le_bytes();
+                chunk.copy_from_slice(&bytes[..chunk.len()]);
+            }
+        }
+
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> std::result::Result<(), rand::Error> {
+            self.fill_bytes(dest);
+            Ok(())
+        }
+    }
+
+    thread_local! {
+        /// Seeds the pixels of unseeded renders, so that the thread generator is only
+        /// reached once per thread
+        static UNSEEDED_PIXEL_STATE: Cell<u64> = Cell::new(rand::thread_rng().gen());
+    }
+
+    /// Call `f` with the random generator of a pixel, obtained once for all its rays.
+    ///
+    /// With a seed, the generator only depends on the seed and on the pixel position,
+    /// so that the render is the same whatever the pixel order.
+    fn with_pixel_rng<T>(
+        seed: Option<u64>,
+        canvas_x: UnitInterval,
+        canvas_y: UnitInterval,
+        f: impl FnOnce(&mut dyn RngCore) -> T,
+    ) -> T {
+        let mut rng = match seed {
+            Some(seed) => PixelRng::for_pixel(seed, canvas_x, canvas_y),
+            None => UNSEEDED_PIXEL_STATE.with(|state| {
+                let mut thread_rng = PixelRng::new(state.get());
+                let pixel_seed = thread_rng.next_u64();
+                state.set(thread_rng.state);
+                PixelRng::new(pixel_seed)
+            }),
+        };
+        f(&mut rng)
+    }
+
+    impl AnyPixelRenderStrategy for RandomAntiAliasingRenderStrategy {
+        fn render_pixel(
+            &self,
+            scene: &Scene,
+            camera: &dyn RayEmitter,
+            canvas_x: UnitInterval,
+            canvas_y: UnitInterval,
+            pixel_width: f64,
+            pixel_height: f64,
+        ) -> Result<Radiance> {
+            with_pixel_rng(self.seed, canvas_x, canvas_y, |rng| {
+                let mut budget = SecondaryRayBudget::new(scene);
+                let mut radiance = Radiance::default();
+                for _ in 0..self.rays_per_pixel {
+                    let x_unit: f64 = rng.gen::<f64>() * pixel_width + canvas_x;
+                    let y_unit: f64 = rng.gen::<f64>() * pixel_height + canvas_y;
+                    let camera_ray = camera.generate_ray(x_unit, y_unit);
+                    radiance += launch_budgeted_ray(&camera_ray, scene, 0, &mut budget)?.combined();
+                }
+                Ok(radiance.mean(self.rays_per_pixel))
+            })
+        }
+
+        fn render_pixel_passes(
+            &self,
+            scene: &Scene,
+            camera: &dyn RayEmitter,
+            canvas_x: UnitInterval,
+            canvas_y: UnitInterval,
+            pixel_width: f64,
+            pixel_height: f64,
+        ) -> Result<RenderPasses> {
+            with_pixel_rng(self.seed, canvas_x, canvas_y, |rng| {
+                let mut budget = SecondaryRayBudget::new(scene);
+                let mut result_passes = RenderPasses::default();
+                for _ in 0..self.rays_per_pixel {
+                    let x_unit: f64 = rng.gen::<f64>() * pixel_width + canvas_x;
+                    let y_unit: f64 = rng.gen::<f64>() * pixel_height + canvas_y;
+                    let camera_ray = camera.generate_ray(x_unit, y_unit);
+                    result_passes += launch_budgeted_ray(&camera_ray, scene, 0, &mut budget)?
+                        .scale(1.0 / (self.rays_per_pixel as f64));
+                }
+                Ok(result_passes)
+            })
+        }
+
+        fn render_pixel_metadata(
+            &self,
+            scene: &Scene,
+            camera: &dyn RayEmitter,
+            canvas_x: UnitInterval,
+            canvas_y: UnitInterval,
+            pixel_width: f64,
+            pixel_height: f64,
+        ) -> PixelMetadata {
+            PixelMetadata {
+                sample_count: self.rays_per_pixel,
+                ..StandardRenderStrategy.render_pixel_metadata(
+                    scene,
+                    camera,
+                    canvas_x,
+                    canvas_y,
+                    pixel_width,
+                    pixel_height,
+                )
+            }
+        }
+
+        fn seed(&self) -> Option<u64> {
+            self.seed
+        }
+    }
+
+    /// Arrangement of the samples of `GridAntiAliasingRenderStrategy` in a pixel
+    #[derive(Debug, Copy, Clone, PartialEq)]
+    pub enum GridPattern {
+        /// Samples on rows and columns parallel to the pixel edges
+        Aligned,
+        /// Rotated grid supersampling (RGSS), the grid is rotated by `atan(1/2)` so
+        /// that no two samples share a row or a column, which gives better
+        /// anti-aliasing of almost horizontal or vertical edges
+        Rotated,
+    }
+
+    /// Average of `samples_per_side * samples_per_side` rays, regularly spread in
+    /// each pixel
+    pub struct GridAntiAliasingRenderStrategy {
+        pub samples_per_side: u32,
+        pub pattern: GridPattern,
+    }
+
+    impl GridAntiAliasingRenderStrategy {
+        /// Position of each sample in a pixel, as fractions of the pixel size
+        pub fn sample_offsets(&self) -> Vec<(f64, f64)> {
+            let side = self.samples_per_side.max(1);
+            let (sin, cos, scale) = match self.pattern {
+                GridPattern::Aligned => (0.0, 1.0, 1.0),
+                GridPattern::Rotated => {
+                    let (sin, cos) = 0.5_f64.atan().sin_cos();
+                    // Shrink the rotated grid so that it still fits in the pixel
+                    (sin, cos, 1.0 / (sin + cos))
+                }
+            };
+            (0..side * side)
+                .map(|index| {
+                    let x = ((index % side) as f64 + 0.5) / side as f64 - 0.5;
+                    let y = ((index / side) as f64 + 0.5) / side as f64 - 0.5;
+                    (
+                        0.5 + scale * (x * cos - y * sin),
+                        0.5 + scale * (x * sin + y * cos),
+                    )
+                })
+                .collect()
+        }
+    }
+
+    impl AnyPixelRenderStrategy for GridAntiAliasingRenderStrategy {
+        fn render_pixel(
+            &self,
+            scene: &Scene,
+            camera: &dyn RayEmitter,
+            canvas_x: UnitInterval,
+            canvas_y: UnitInterval,
+            pixel_width: f64,
+            pixel_height: f64,
+        ) -> Result<Radiance> {
+            let offsets = self.sample_offsets();
+            let mut budget = SecondaryRayBudget::new(scene);
+            let mut radiance = Radiance::default();
+            for (offset_x, offset_y) in offsets.iter() {
+                let x_unit = offset_x * pixel_width + canvas_x;
+                let y_unit = offset_y * pixel_height + canvas_y;
+                let camera_ray = camera.generate_ray(x_unit, y_unit);
+                radiance += launch_budgeted_ray(&camera_ray, scene, 0, &mut budget)?.combined();
+            }
+            Ok(radiance.mean(offsets.len() as u32))
+        }
+
+        fn render_pixel_passes(
+            &self,
+            scene: &Scene,
+            camera: &dyn RayEmitter,
+            canvas_x: UnitInterval,
+            canvas_y: UnitInterval,
+            pixel_width: f64,
+            pixel_height: f64,
+        ) -> Result<RenderPasses> {
+            let offsets = self.sample_offsets();
+            let mut budget = SecondaryRayBudget::new(scene);
+            let mut result_passes = RenderPasses::default();
+            for (offset_x, offset_y) in offsets.iter() {
+                let x_unit = offset_x * pixel_width + canvas_x;
+                let y_unit = offset_y * pixel_height + canvas_y;
+                let camera_ray = camera.generate_ray(x_unit, y_unit);
+                result_passes += launch_budgeted_ray(&camera_ray, scene, 0, &mut budget)?
+                    .scale(1.0 / (offsets.len() as f64));
+            }
+            Ok(result_passes)
+        }
+
+        fn render_pixel_metadata(
+            &self,
+            scene: &Scene,
+            camera: &dyn RayEmitter,
+            canvas_x: UnitInterval,
+            canvas_y: UnitInterval,
+            pixel_width: f64,
+            pixel_height: f64,
+        ) -> PixelMetadata {
+            PixelMetadata {
+                sample_count: self.samples_per_side.max(1).pow(2),
+                ..StandardRenderStrategy.render_pixel_metadata(
+                    scene,
+                    camera,
+                    canvas_x,
+                    canvas_y,
+                    pixel_width,
+                    pixel_height,
+                )
+            }
+        }
+    }
+
+    /// Adaptive supersampling: the corners and the center of the pixel are sampled,
+    /// and an area whose samples vary more than `variance_threshold` is split into
+    /// four quarters sampled the same way, as long as `max_rays` is not exceeded.
+    /// Flat areas only cost 5 rays, while edges get more.
+    pub struct AdaptiveAntiAliasingRenderStrategy {
+        /// Maximum number of rays for a pixel, the 5 first rays being always launched
+        pub max_rays: u32,
+        /// Mean of the variances of the red, green and blue components of the samples
+        /// of an area, above which the area is split
+        pub variance_threshold: f64,
+    }
+
+    /// Number of times the area of a pixel can be split into quarters
+    const ADAPTIVE_MAX_SUBDIVISIONS: u32 = 4;
+
+    impl AdaptiveAntiAliasingRenderStrategy {
+        /// Passes of the pixel, with the number of rays which were launched
+        fn sample_pixel(
+            &self,
+            scene: &Scene,
+            camera: &dyn RayEmitter,
+            canvas_x: UnitInterval,
+            canvas_y: UnitInterval,
+            pixel_width: f64,
+            pixel_height: f64,
+        ) -> Result<(RenderPasses, u32)> {
+            // Samples are on a grid, so that those shared by areas are launched once
+            let grid_size = 2_u32.pow(ADAPTIVE_MAX_SUBDIVISIONS + 1);
+            let mut budget = SecondaryRayBudget::new(scene);
+            let mut samples: HashMap<(u32, u32), RenderPasses> = HashMap::new();
+            let mut result_passes = RenderPasses::default();
+            // Rays launched once all the queued areas are sampled, at most
+            let mut planned_rays = 5;
+            // Breadth first, so that the ray budget is spread over the whole pixel
+            let mut areas = VecDeque::new();
+            areas.push_back((0, 0, grid_size));
+            while let Some((x, y, size)) = areas.pop_front() {
+                let half = size / 2;
+                let points = [
+                    (x, y),
+                    (x + size, y),
+                    (x, y + size),
+                    (x + size, y + size),
+                    (x + half, y + half),
+                ];
+                let mut area_passes = RenderPasses::default();
+                let mut colors = Vec::with_capacity(points.len());
+                for point in points.iter() {
+                    if !samples.contains_key(point) {
+                        let x_unit = point.0 as f64 / grid_size as f64 * pixel_width + canvas_x;
+                        let y_unit = point.1 as f64 / grid_size as f64 * pixel_height + canvas_y;
+                        let camera_ray = camera.generate_ray(x_unit, y_unit);
+                        let passes = launch_budgeted_ray(&camera_ray, scene, 0, &mut budget)?;
+                        samples.insert(*point, passes);
+                    }
+                    let passes = &samples[point];
+                    colors.push(passes.combined());
+                    area_passes += passes.scale(1.0 / points.len() as f64);
+                }
+                // Quarters of an area add at most 8 samples
+                let can_split = half >= 2 && planned_rays + 8 <= self.max_rays;
+                if can_split && color_variance(&colors) > self.variance_threshold {
+                    planned_rays += 8;
+                    areas.push_back((x, y, half));
+                    areas.push_back((x + half, y, half));
+                    areas.push_back((x, y + half, half));
+                    areas.push_back((x + half, y + half, half));
+                } else {
+                    result_passes += area_passes.scale((size as f64 / grid_size as f64).powi(2));
+                }
+            }
+            Ok((result_passes, samples.len() as u32))
+        }
+    }
+
+    /// Mean of the variances of the color components
+    fn color_variance(colors: &[Radiance]) -> f64 {
+        let count = colors.len() as f64;
+        let component_variance = |component: fn(&Radiance) -> f64| {
+            let mean = colors.iter().map(component).sum::<f64>() / count;
+            colors
+                .iter()
+                .map(|color| (component(color) - mean).powi(2))
+                .sum::<f64>()
+                / count
+        };
+        (component_variance(Radiance::red)
+            + component_variance(Radiance::green)
+            + component_variance(Radiance::blue))
+            / 3.0
+    }
+
+    impl AnyPixelRenderStrategy for AdaptiveAntiAliasingRenderStrategy {
+        fn render_pixel(
+            &self,
+            scene: &Scene,
+            camera: &dyn RayEmitter,
+            canvas_x: UnitInterval,
+            canvas_y: UnitInterval,
+            pixel_width: f64,
+            pixel_height: f64,
+        ) -> Result<Radiance> {
+            self.render_pixel_passes(scene, camera, canvas_x, canvas_y, pixel_width, pixel_height)
+                .map(|passes| passes.combined())
+        }
+
+        fn render_pixel_passes(
+            &self,
+            scene: &Scene,
+            camera: &dyn RayEmitter,
+            canvas_x: UnitInterval,
+            canvas_y: UnitInterval,
+            pixel_width: f64,
+            pixel_height: f64,
+        ) -> Result<RenderPasses> {
+            self.sample_pixel(scene, camera, canvas_x, canvas_y, pixel_width, pixel_height)
+                .map(|(passes, _)| passes)
+        }
+
+        /// The pixel is sampled again to know how many rays were used
+        fn render_pixel_metadata(
+            &self,
+            scene: &Scene,
+            camera: &dyn RayEmitter,
+            canvas_x: UnitInterval,
+            canvas_y: UnitInterval,
+            pixel_width: f64,
+            pixel_height: f64,
+        ) -> PixelMetadata {
+            let sample_count = self
+                .sample_pixel(scene, camera, canvas_x, canvas_y, pixel_width, pixel_height)
+                .map_or(1, |(_, sample_count)| sample_count);
+            PixelMetadata {
+                sample_count,
+                ..StandardRenderStrategy.render_pixel_metadata(
+                    scene,
+                    camera,
+                    canvas_x,
+                    canvas_y,
+                    pixel_width,
+                    pixel_height,
+                )
+            }
+        }
+    }
+
+    /// Monte Carlo path tracing, light bouncing on surfaces in random directions.
+    ///
+    /// Surfaces are purely diffuse: only the texture color and the emission of
+    /// objects are used. Lights of the scene, being points, are only reached
+    /// through light sampling. The whole color is given in the diffuse pass.
+    pub struct PathTracingRenderStrategy {
+        pub samples_per_pixel: u32,
+        /// Number of bounces after the first surface hit
+        pub max_bounces: u32,
+        /// Next event estimation: at each bounce, lights and emissive objects are
+        /// directly sampled, which reduces the noise of small light sources
+        pub light_sampling: bool,
+        /// If set, the paths of a pixel only depend on this seed and on the pixel
+        /// position, so that the render is the same whatever the pixel order
+        pub seed: Option<u64>,
+    }
+
+    impl AnyPixelRenderStrategy for PathTracingRenderStrategy {
+        fn render_pixel(
+            &self,
+            scene: &Scene,
+            camera: &dyn RayEmitter,
+            canvas_x: UnitInterval,
+            canvas_y: UnitInterval,
+            pixel_width: f64,
+            pixel_height: f64,
+        ) -> Result<Radiance> {
+            with_pixel_rng(self.seed, canvas_x, canvas_y, |rng| {
+                let mut radiance = Radiance::default();
+                for _ in 0..self.samples_per_pixel {
+                    let x_unit: f64 = rng.gen::<f64>() * pixel_width + canvas_x;
+                    let y_unit: f64 = rng.gen::<f64>() * pixel_height + canvas_y;
+                    let camera_ray = camera.generate_ray(x_unit, y_unit);
+                    trace_path(
+                        camera_ray,
+                        scene,
+                        self.max_bounces,
+                        self.light_sampling,
+                        rng,
+                        &mut radiance,
+                    )?;
+                }
+                Ok(radiance.mean(self.samples_per_pixel))
+            })
+        }
+
+        fn render_pixel_passes(
+            &self,
+            scene: &Scene,
+            camera: &dyn RayEmitter,
+            canvas_x: UnitInterval,
+            canvas_y: UnitInterval,
+            pixel_width: f64,
+            pixel_height: f64,
+        ) -> Result<RenderPasses> {
+            Ok(RenderPasses {
+                diffuse: self.render_pixel(
+                    scene,
+                    camera,
+                    canvas_x,
+                    canvas_y,
+                    pixel_width,
+                    pixel_height,
+                )?,
+                ..Default::default()
+            })
+        }
+
+        fn render_pixel_metadata(
+            &self,
+            scene: &Scene,
+            camera: &dyn RayEmitter,
+            canvas_x: UnitInterval,
+            canvas_y: UnitInterval,
+            pixel_width: f64,
+            pixel_height: f64,
+        ) -> PixelMetadata {
+            PixelMetadata {
+                sample_count: self.samples_per_pixel,
+                ..StandardRenderStrategy.render_pixel_metadata(
+                    scene,
+                    camera,
+                    canvas_x,
+                    canvas_y,
+                    pixel_width,
+                    pixel_height,
+                )
+            }
+        }
+
+        fn seed(&self) -> Option<u64> {
+            self.seed
+        }
+    }
+}
+
+pub(crate) fn launch_ray(camera_ray: &Ray, scene: &Scene, depth: u8) -> Result<Radiance> {
+    launch_ray_passes(camera_ray, scene, depth).map(|passes| passes.combined())
+}
+
+pub(crate) fn launch_ray_passes(
+    camera_ray: &Ray,
+    scene: &Scene,
+    depth: u8,
+) -> Result<RenderPasses> {
+    launch_budgeted_ray(
+        camera_ray,
+        scene,
+        depth,
+        &mut SecondaryRayBudget::new(scene),
+    )
+}
+
+/// Number of reflection and refraction rays which can still be launched for a pixel,
+/// following `SceneConfiguration::secondary_ray_budget`
+pub(crate) struct SecondaryRayBudget {
+    remaining: Option<u32>,
+    launched: u32,
+}
+
+impl SecondaryRayBudget {
+    pub(crate) fn new(scene: &Scene) -> Self {
+        SecondaryRayBudget {
+            remaining: scene.config.secondary_ray_budget,
+            launched: 0,
+        }
+    }
+
+    /// Spend one ray of the budget, `false` if it is already spent
+    fn spend(&mut self) -> bool {
+        match &mut self.remaining {
+            Some(0) => return false,
+            Some(remaining) => *remaining -= 1,
+            None => {}
+        }
+        self.launched += 1;
+        true
     }
+}
 
-    pub struct RandomAntiAliasingRenderStrategy {
-        pub rays_per_pixel: u32,
+/// Passes of a ray whose secondary rays are taken from `budget`, shared by the
+/// rays of a pixel
+pub(crate) fn launch_budgeted_ray(
+    camera_ray: &Ray,
+    scene: &Scene,
+    depth: u8,
+    budget: &mut SecondaryRayBudget,
+) -> Result<RenderPasses> {
+    trace_budgeted_ray(camera_ray, scene, depth, budget, RayKind::Camera)
+}
+
+fn trace_budgeted_ray(
+    ray: &Ray,
+    scene: &Scene,
+    depth: u8,
+    budget: &mut SecondaryRayBudget,
+    kind: RayKind,
+) -> Result<RenderPasses> {
+    if depth > scene.config.maximum_light_recursion {
+        return Ok(RenderPasses::default());
     }
+    let collision_context = match kind {
+        RayKind::Camera => search_camera_collision(ray, scene),
+        _ => search_visible_collision(ray, &scene.objects, kind),
+    };
+    shade_collision(ray, scene, depth, collision_context, budget)
+}
 
-    impl AnyPixelRenderStrategy for RandomAntiAliasingRenderStrategy {
-        fn render_pixel(
-            &self,
-            scene: &Scene,
-            canvas_x: UnitInterval,
-            canvas_y: UnitInterval,
-            pixel_width: f64,
-            pixel_height: f64,
-        ) -> Result<Color> {
-            let mut rng = rand::thread_rng();
-            let mut result_color = Color::BLACK;
-            for _ in 0..self.rays_per_pixel {
-                let x_unit: f64 = rng.gen::<f64>() * pixel_width + canvas_x;
-                let y_unit: f64 = rng.gen::<f64>() * pixel_height + canvas_y;
-                let camera_ray = scene.camera.generate_ray(x_unit, y_unit);
-                result_color +=
-                    (1.0 / (self.rays_per_pixel as f64)) * launch_ray(&camera_ray, scene, 0)?;
-            }
-            Ok(result_color)
+/// Color, depth, normal and object seen by a primary ray, computed from a single
+/// search of the nearest object
+#[derive(Debug, Clone)]
+pub(crate) struct PrimaryRayOutputs {
+    pub(crate) color: Radiance,
+    pub(crate) depth: Option<f64>,
+    pub(crate) normal: Option<Vec3>,
+    pub(crate) object_index: Option<usize>,
+    /// Shadow darkness on a shadow catcher, 0 when no shadow catcher is seen
+    pub(crate) shadow_alpha: f64,
+}
+
+pub(crate) fn launch_primary_ray(camera_ray: &Ray, scene: &Scene) -> Result<PrimaryRayOutputs> {
+    let collision_context = search_camera_collision(camera_ray, scene);
+    let (depth, normal, object_index) = match &collision_context {
+        Some(context) => (
+            Some(context.collision_point.distance(camera_ray.source)),
+            context
+                .object
+                .normal_at(context.collision_point)
+                .map(|normal| normal.normalize()),
+            Some(context.array_index),
+        ),
+        None => (None, None, None),
+    };
+    let shadow_alpha = match (&collision_context, normal) {
+        (Some(context), Some(normal)) if context.object.shadow_catcher => {
+            scene.shadow_amount(context.collision_point, facing_normal(normal, camera_ray))
+        }
+        _ => 0.0,
+    };
+    let color = shade_collision(
+        camera_ray,
+        scene,
+        0,
+        collision_context,
+        &mut SecondaryRayBudget::new(scene),
+    )?
+    .combined();
+    Ok(PrimaryRayOutputs {
+        color,
+        depth,
+        normal,
+        object_index,
+        shadow_alpha,
+    })
+}
+
+/// Light received along a ray, from the result of the nearest object search
+fn shade_collision(
+    camera_ray: &Ray,
+    scene: &Scene,
+    depth: u8,
+    collision_context: Option<CollisionContext>,
+    budget: &mut SecondaryRayBudget,
+) -> Result<RenderPasses> {
+    let shading = shade_surface(camera_ray, scene, collision_context)?;
+    let mut passes = shading.passes;
+    if let Some((ray, filter)) = &shading.refraction {
+        if budget.spend() {
+            passes.refraction =
+                trace_budgeted_ray(ray, scene, depth + 1, budget, RayKind::Refraction)?
+                    .combined()
+                    .filtered(filter);
+        }
+    }
+    if let Some((ray, filter)) = &shading.reflection {
+        if budget.spend() {
+            passes.reflection =
+                trace_budgeted_ray(ray, scene, depth + 1, budget, RayKind::Reflection)?
+                    .combined()
+                    .filtered(filter);
         }
     }
+    Ok(passes)
 }
 
-pub(crate) fn launch_ray(camera_ray: &Ray, scene: &Scene, depth: u8) -> Result<Color> {
-    if depth > scene.config.maximum_light_recursion {
-        return Ok(Color::BLACK);
+/// Shading of the surface hit by a ray, and the secondary rays whose light the
+/// surface also sends back along the ray
+struct SurfaceShading {
+    /// Passes without reflection and refraction
+    passes: RenderPasses,
+    /// Ray going through the object, and the filter applied to its light
+    refraction: Option<(Ray, Color)>,
+    /// Ray reflected by the surface, and the filter applied to its light
+    reflection: Option<(Ray, Color)>,
+}
+
+impl SurfaceShading {
+    fn with_background(color: Color) -> Self {
+        SurfaceShading {
+            passes: RenderPasses::with_background(color),
+            refraction: None,
+            reflection: None,
+        }
     }
+}
 
+fn shade_surface(
+    camera_ray: &Ray,
+    scene: &Scene,
+    collision_context: Option<CollisionContext>,
+) -> Result<SurfaceShading> {
     // Check if there is an object to process for this pixel
-    let collision_context = match search_object_collision(&camera_ray, &scene.objects) {
+    let collision_context = match collision_context {
         Some(collision_context) => collision_context,
         None => {
-            return Ok(scene.config.world_color.clone());
+            return Ok(SurfaceShading::with_background(
+                scene.config.background_color(camera_ray.direction),
+            ));
         }
     };
     let CollisionContext {
@@ -109,58 +1062,269 @@ pub(crate) fn launch_ray(camera_ray: &Ray, scene: &Scene, depth: u8) -> Result<C
         collision_point,
         array_index,
     } = collision_context;
+    if nearest_object.holdout || nearest_object.shadow_catcher {
+        return Ok(SurfaceShading::with_background(
+            scene.config.background_color(camera_ray.direction),
+        ));
+    }
 
     // After having found the nearest object, we launch a ray to the light
-    let mut total_color = Color::BLACK;
-    total_color += illumination_from_lights(
-        &collision_context,
-        &scene.lights,
-        &scene.objects,
-        &camera_ray,
-    )?;
+    let mut shading = SurfaceShading {
+        passes: RenderPasses::default(),
+        refraction: None,
+        reflection: None,
+    };
+    let (diffuse, specular) = illumination_from_lights(&collision_context, scene, &camera_ray)?;
+    shading.passes.diffuse = diffuse;
+    shading.passes.specular = specular;
 
-    // Refraction light
-    if let Some(transparency) = &nearest_object.effects().transparency {
+    // Ambient light
+    if let Some(ambient_light) = &scene.config.ambient_light {
+        shading.passes.ambient = (ambient_light * &nearest_object.color_at(collision_point)).into();
+    }
+
+    // Light emitted by the surface, seen even when it does not light other objects
+    if let Some(emission) = &nearest_object.effects().emission {
         let surface_normal = nearest_object
             .normal_at(collision_point)
-            .ok_or(RaytracerError::NormalNotFound(array_index))?
-            .normalize();
-        let n_ratio = scene.config.world_refractive_index / transparency.refractive_index;
-        let cos_refraction = camera_ray.direction.dot_product(surface_normal);
-        let sin_square_refraction = n_ratio.powi(2) * (1.0 - cos_refraction.powi(2));
-        let refraction_direction = n_ratio * camera_ray.direction
-            - (n_ratio * cos_refraction + (1.0 - sin_square_refraction).sqrt()) * surface_normal;
-        // Go up to object exterior
-        let refraction_ray = Ray::new(collision_point, refraction_direction).shift_source();
-        if let Some(collision_context) = search_object_collision(&refraction_ray, &scene.objects) {
-            // TODO only the nearest_object is necessary
-            // launch new ray
-            let exit_point = collision_context.collision_point;
-            let new_ray = Ray::new(exit_point, camera_ray.direction).shift_source();
-            total_color += transparency.alpha * launch_ray(&new_ray, scene, depth + 1)?;
+            .ok_or(RaytracerError::NormalNotFound(array_index))?;
+        if emission.is_emitted_toward(surface_normal, camera_ray.direction) {
+            shading.passes.emission = emission.strength * Radiance::from(&emission.color);
+        }
+    }
+
+    // Caustics, from light focused by mirrors and transparent objects
+    if let Some(photon_map) = &scene.config.photon_map {
+        let effects = nearest_object.effects();
+        if effects.transparency.is_none() && effects.mirror.is_none() {
+            let surface_normal = facing_normal(
+                nearest_object
+                    .normal_at(collision_point)
+                    .ok_or(RaytracerError::NormalNotFound(array_index))?
+                    .normalize(),
+                camera_ray,
+            );
+            let caustic = photon_map
+                .irradiance(collision_point, surface_normal)
+                .filtered(&nearest_object.color_at(collision_point));
+            shading.passes.diffuse += caustic;
+        }
+    }
+
+    // Secondary rays are not launched when only direct lighting is rendered
+    if scene.config.direct_only {
+        return Ok(shading);
+    }
+
+    // Refraction light, the part reflected by the surface of a Fresnel transparency
+    // being given to the reflection
+    let mut fresnel_reflection = None;
+    if let Some(transparency) = &nearest_object.effects().transparency {
+        let reflectance = if transparency.fresnel {
+            let reflectance =
+                fresnel_reflectance(camera_ray, scene, &collision_context, transparency)?;
+            fresnel_reflection = Some(transparency.alpha * reflectance);
+            reflectance
+        } else {
+            0.0
+        };
+        if let Some(new_ray) = transmitted_ray(camera_ray, scene, &collision_context, transparency)?
+        {
+            let filter = (transparency.alpha * (1.0 - reflectance))
+                * transparency
+                    .transmission_color
+                    .clone()
+                    .unwrap_or(Color::WHITE);
+            shading.refraction = Some((new_ray, filter));
         }
     }
 
-    // Reflexion
-    if let Some(mirror) = &nearest_object.effects().mirror {
+    // Reflexion, a thin film reflects light even without mirror effect
+    let effects = nearest_object.effects();
+    if effects.mirror.is_some() || effects.thin_film.is_some() || fresnel_reflection.is_some() {
+        let ray_reflexion = reflected_ray(camera_ray, &collision_context)?;
         let surface_normal = nearest_object
             .normal_at(collision_point)
             .ok_or(RaytracerError::NormalNotFound(array_index))?
             .normalize();
-        let ray_reflexion = Ray::new(
-            collision_point,
-            camera_ray.direction.reflect(surface_normal).normalize(),
-        )
-        .shift_source();
-        total_color += mirror.coeff * launch_ray(&ray_reflexion, scene, depth + 1)?;
+        let cos_incidence = camera_ray.direction.dot_product(surface_normal).abs();
+        let mirror_coeff = match (&effects.mirror, fresnel_reflection) {
+            (Some(mirror), _) => mirror.reflectance(cos_incidence),
+            (None, Some(reflectance)) => reflectance,
+            (None, None) => 1.0,
+        };
+        let mut filter = mirror_coeff * Color::WHITE;
+        if let Some(thin_film) = &effects.thin_film {
+            filter = filter * thin_film.interference_color(cos_incidence);
+        }
+        shading.reflection = Some((ray_reflexion, filter));
     }
 
-    // Ambient light
-    if let Some(ambient_light) = &scene.config.ambient_light {
-        total_color += ambient_light * &nearest_object.color_at(collision_point);
+    Ok(shading)
+}
+
+/// Color of a ray split by the recursion depth at which the light is sent back:
+/// index 0 is the shading of the first surface hit, index 1 the light of its
+/// reflection and refraction rays, and so on. Summing all layers gives the color of
+/// `launch_ray`.
+pub(crate) fn launch_ray_layers(camera_ray: &Ray, scene: &Scene) -> Result<Vec<Radiance>> {
+    let mut layers = vec![Radiance::default(); scene.config.maximum_light_recursion as usize + 1];
+    let mut budget = SecondaryRayBudget::new(scene);
+    accumulate_layers(
+        camera_ray,
+        scene,
+        0,
+        &Color::WHITE,
+        &mut layers,
+        &mut budget,
+        RayKind::Camera,
+    )?;
+    Ok(layers)
+}
+
+fn accumulate_layers(
+    ray: &Ray,
+    scene: &Scene,
+    depth: u8,
+    filter: &Color,
+    layers: &mut [Radiance],
+    budget: &mut SecondaryRayBudget,
+    kind: RayKind,
+) -> Result<()> {
+    if depth > scene.config.maximum_light_recursion {
+        return Ok(());
+    }
+    let collision_context = match kind {
+        RayKind::Camera => search_camera_collision(ray, scene),
+        _ => search_visible_collision(ray, &scene.objects, kind),
+    };
+    let shading = shade_surface(ray, scene, collision_context)?;
+    layers[depth as usize] += shading.passes.combined().filtered(filter);
+    let secondary_rays = (shading
+        .refraction
+        .iter()
+        .map(|ray| (ray, RayKind::Refraction)))
+    .chain(
+        shading
+            .reflection
+            .iter()
+            .map(|ray| (ray, RayKind::Reflection)),
+    );
+    for ((secondary_ray, secondary_filter), secondary_kind) in secondary_rays {
+        if !budget.spend() {
+            break;
+        }
+        accumulate_layers(
+            secondary_ray,
+            scene,
+            depth + 1,
+            &(filter * secondary_filter),
+            layers,
+            budget,
+            secondary_kind,
+        )?;
+    }
+    Ok(())
+}
+
+/// Ray leaving a transparent object on the other side, `None` if the refracted ray
+/// does not find the exit surface
+pub(crate) fn transmitted_ray(
+    camera_ray: &Ray,
+    scene: &Scene,
+    collision_context: &CollisionContext,
+    transparency: &Transparency,
+) -> Result<Option<Ray>> {
+    let CollisionContext {
+        object: nearest_object,
+        collision_point,
+        array_index,
+    } = *collision_context;
+    let surface_normal = nearest_object
+        .normal_at(collision_point)
+        .ok_or(RaytracerError::NormalNotFound(array_index))?
+        .normalize();
+    let refractive_index = entered_refractive_index(
+        array_index,
+        transparency,
+        collision_point,
+        camera_ray,
+        scene,
+    );
+    let n_ratio = scene.config.world_refractive_index / refractive_index;
+    let cos_refraction = camera_ray.direction.dot_product(surface_normal);
+    let sin_square_refraction = n_ratio.powi(2) * (1.0 - cos_refraction.powi(2));
+    // Total internal reflection, no light goes through
+    if sin_square_refraction > 1.0 {
+        return Ok(None);
+    }
+    let refraction_direction = n_ratio * camera_ray.direction
+        - (n_ratio * cos_refraction + (1.0 - sin_square_refraction).sqrt()) * surface_normal;
+    // Go up to object exterior
+    let refraction_ray = Ray::new(collision_point, refraction_direction).shift_source();
+    // TODO only the nearest_object is necessary
+    Ok(
+        search_object_collision(&refraction_ray, &scene.objects).map(|collision_context| {
+            let exit_point = collision_context.collision_point;
+            Ray::new(exit_point, camera_ray.direction).shift_source()
+        }),
+    )
+}
+
+/// Fraction of the light reflected by the surface of a transparent object, from the
+/// Schlick approximation of the Fresnel equations, 1 on total internal reflection
+pub(crate) fn fresnel_reflectance(
+    camera_ray: &Ray,
+    scene: &Scene,
+    collision_context: &CollisionContext,
+    transparency: &Transparency,
+) -> Result<f64> {
+    let CollisionContext {
+        object,
+        collision_point,
+        array_index,
+    } = *collision_context;
+    let surface_normal = object
+        .normal_at(collision_point)
+        .ok_or(RaytracerError::NormalNotFound(array_index))?
+        .normalize();
+    let refractive_index = entered_refractive_index(
+        array_index,
+        transparency,
+        collision_point,
+        camera_ray,
+        scene,
+    );
+    let n_ratio = scene.config.world_refractive_index / refractive_index;
+    let cos_incidence = camera_ray.direction.dot_product(surface_normal).abs();
+    let sin_square_refraction = n_ratio.powi(2) * (1.0 - cos_incidence.powi(2));
+    if sin_square_refraction > 1.0 {
+        return Ok(1.0);
     }
+    let r0 = ((n_ratio - 1.0) / (n_ratio + 1.0)).powi(2);
+    // Going to a less dense medium, the angle of the refracted ray is the largest
+    let cosine = if n_ratio > 1.0 {
+        (1.0 - sin_square_refraction).sqrt()
+    } else {
+        cos_incidence
+    };
+    Ok(r0 + (1.0 - r0) * (1.0 - cosine).powi(5))
+}
 
-    Ok(total_color)
+/// Ray reflected by the surface, as by a mirror
+pub(crate) fn reflected_ray(camera_ray: &Ray, collision_context: &CollisionContext) -> Result<Ray> {
+    let surface_normal = collision_context
+        .object
+        .normal_at(collision_context.collision_point)
+        .ok_or(RaytracerError::NormalNotFound(
+            collision_context.array_index,
+        ))?
+        .normalize();
+    Ok(Ray::new(
+        collision_context.collision_point,
+        camera_ray.direction.reflect(surface_normal).normalize(),
+    )
+    .shift_source())
 }
 
 pub struct CollisionContext<'a> {
@@ -169,10 +1333,51 @@ pub struct CollisionContext<'a> {
     pub array_index: usize,
 }
 
-fn search_object_collision<'a>(
+/// Nearest object hit by a ray, if any
+pub fn search_object_collision<'a>(
+    ray: &Ray,
+    objects: &'a [SceneObject],
+) -> Option<CollisionContext<'a>> {
+    nearest_collision(ray, objects, |_| true)
+}
+
+/// Kind of a ray launched through the scene, objects being visible to some kinds only
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RayKind {
+    Camera,
+    Reflection,
+    Refraction,
+}
+
+/// Nearest object visible to the `kind` of the ray, following its `visibility`
+pub fn search_visible_collision<'a>(
     ray: &Ray,
     objects: &'a [SceneObject],
+    kind: RayKind,
+) -> Option<CollisionContext<'a>> {
+    nearest_collision(ray, objects, |object| object.visibility.is_visible_to(kind))
+}
+
+/// Nearest object seen by a camera ray, counted in `SceneConfiguration::camera_hits`
+pub(crate) fn search_camera_collision<'a>(
+    ray: &Ray,
+    scene: &'a Scene,
 ) -> Option<CollisionContext<'a>> {
+    let collision = search_visible_collision(ray, &scene.objects, RayKind::Camera);
+    if let (Some(_), Some(hits)) = (&collision, &scene.config.camera_hits) {
+        hits.fetch_add(1, Ordering::Relaxed);
+    }
+    collision
+}
+
+fn nearest_collision<'a, F>(
+    ray: &Ray,
+    objects: &'a [SceneObject],
+    is_candidate: F,
+) -> Option<CollisionContext<'a>>
+where
+    F: Fn(&SceneObject) -> bool,
+{
     let mut shortest_distance: f64 = f64::MAX;
     let mut nearest_object_opt: Option<&SceneObject> = None;
     let mut collision_point: Vec3 = Default::default();
@@ -180,6 +1385,9 @@ fn search_object_collision<'a>(
     // For each pixel, we search for collision with objects
     // We also take into account the nearest object, for now
     for (index, object_candidate) in objects.iter().enumerate() {
+        if !is_candidate(object_candidate) {
+            continue;
+        }
         if let Some(collision_point_candidate) = object_candidate.check_collision(&ray) {
             let distance = collision_point_candidate.distance(ray.source);
             if distance <= 1e-12 {
@@ -199,74 +1407,1146 @@ fn search_object_collision<'a>(
     })
 }
 
+/// Refractive index of the medium a ray enters when crossing the surface of a
+/// transparent object. If the point is also inside other transparent objects, the
+/// medium with the highest priority is used.
+fn entered_refractive_index(
+    object_index: usize,
+    transparency: &Transparency,
+    collision_point: Vec3,
+    camera_ray: &Ray,
+    scene: &Scene,
+) -> f64 {
+    let probe_ray = Ray::new(collision_point, camera_ray.direction).shift_source();
+    scene
+        .objects
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| *index != object_index)
+        .filter_map(|(_, object)| {
+            let other_transparency = object.effects().transparency.as_ref()?;
+            let exit_point = object.check_collision(&probe_ray)?;
+            let normal = object.normal_at(exit_point)?;
+            // Leaving the object through its surface means the point is inside it
+            if normal.dot_product(probe_ray.direction) > 0.0 {
+                Some(other_transparency)
+            } else {
+                None
+            }
+        })
+        .fold(transparency, |best, candidate| {
+            if candidate.priority > best.priority {
+                candidate
+            } else {
+                best
+            }
+        })
+        .refractive_index
+}
+
 fn illumination_from_lights(
     collision_context: &CollisionContext,
-    lights: &[Box<dyn AnyLightObject>],
-    objects: &[SceneObject],
+    scene: &Scene,
     camera_ray: &Ray,
-) -> Result<Color> {
-    let mut total_color = Color::BLACK;
+) -> Result<(Radiance, Radiance)> {
+    // Accumulated without clamping, so that negative lights can remove energy
+    let mut diffuse_color = Radiance::default();
+    let mut specular_color = Radiance::default();
     let surface_point = collision_context.collision_point;
     let object = collision_context.object;
-    for current_light in lights {
-        let light_ray = Ray::ray_from_to(surface_point, current_light.source());
-
-        // Generate shadow, by skipping process if there is an obstacle between object and light
-        if ray_encounter_obstacle(&light_ray, &current_light.source(), objects) {
-            continue;
-        }
-
+    let surface_normal = facing_normal(
+        object
+            .normal_at(surface_point)
+            .ok_or(RaytracerError::NormalNotFound(
+                collision_context.array_index,
+            ))?,
+        camera_ray,
+    );
+    // Light scattered under the surface goes through the object itself
+    let subsurface = &object.effects().subsurface;
+    let lights = visible_lights(
+        surface_point,
+        surface_normal,
+        scene.config.shadow_bias,
+        &scene.lights,
+        &scene.objects,
+        subsurface.as_ref().map(|_| collision_context.array_index),
+        scene.config.photon_map.is_some(),
+    );
+    for (_, current_light, light_ray, transmission) in lights {
         // Build values needed for light computation
         let light_direction = light_ray.direction;
-        let light_color = current_light.color_for_ray(light_ray);
-        let surface_normal =
-            object
-                .normal_at(surface_point)
-                .ok_or(RaytracerError::NormalNotFound(
-                    collision_context.array_index,
-                ))?;
+        let light_color = transmission * current_light.color_for_ray(light_ray);
+        let light_intensity = current_light.intensity();
         let ray_reflexion = camera_ray.direction.reflect(surface_normal).normalize();
-
-        // Diffuse reflection
         let reflection_angle = light_direction.dot_product(surface_normal);
-        if reflection_angle > 0.0 {
-            total_color +=
-                reflection_angle * &(light_color.clone() * object.color_at(surface_point));
+
+        // Diffuse reflection, which translucent surfaces also receive past the
+        // terminator
+        let diffuse_factor = match subsurface {
+            Some(subsurface) => subsurface.diffuse_factor(reflection_angle),
+            None => reflection_angle.max(0.0),
+        };
+        if diffuse_factor > 0.0 {
+            diffuse_color.accumulate(
+                light_intensity * diffuse_factor,
+                &(light_color.clone() * object.color_at(surface_point)),
+            );
+        }
+
+        // A light behind the surface gives no specular light
+        if reflection_angle <= 0.0 {
+            continue;
         }
 
         // Add specular / phong light
         if let Some(phong) = &object.effects().phong {
             let specular_angle = light_direction.dot_product(ray_reflexion);
             if specular_angle > 0.0 {
-                total_color += light_color.clone()
-                    * (specular_angle).powi(phong.size as i32)
-                    * phong.lum_coeff;
+                specular_color.accumulate(
+                    light_intensity * specular_angle.powi(phong.size as i32) * phong.lum_coeff,
+                    &light_color,
+                );
+            }
+        }
+    }
+    Ok((diffuse_color, specular_color))
+}
+
+/// Add to `radiance` the light brought by one random path starting with `ray`.
+///
+/// Emissive objects hit by the path are weighted with the power heuristic of
+/// multiple importance sampling when they are also sampled as lights, so that
+/// their light is not counted twice.
+fn trace_path(
+    mut ray: Ray,
+    scene: &Scene,
+    max_bounces: u32,
+    light_sampling: bool,
+    rng: &mut dyn RngCore,
+    radiance: &mut Radiance,
+) -> Result<()> {
+    let emitters: Vec<usize> = if light_sampling {
+        scene
+            .objects
+            .iter()
+            .enumerate()
+            .filter(|(_, object)| object.effects().emission.is_some())
+            .filter(|(_, object)| object.shape.bounding_box().is_some())
+            .map(|(index, _)| index)
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let mut throughput = Color::WHITE;
+    // Origin and density of the direction sampled at the previous bounce
+    let mut previous_bounce: Option<(Vec3, f64)> = None;
+    for bounce in 0..=max_bounces {
+        let context = match search_object_collision(&ray, &scene.objects) {
+            Some(context) => context,
+            None => {
+                let background = scene.config.background_color(ray.direction);
+                radiance.accumulate(1.0, &(&throughput * &background));
+                return Ok(());
+            }
+        };
+        let object = context.object;
+        let point = context.collision_point;
+        let surface_normal = object
+            .normal_at(point)
+            .ok_or(RaytracerError::NormalNotFound(context.array_index))?
+            .normalize();
+        let normal = facing_normal(surface_normal, &ray);
+
+        // Emission seen by the path
+        let emission = object
+            .effects()
+            .emission
+            .as_ref()
+            .filter(|emission| emission.is_emitted_toward(surface_normal, ray.direction));
+        if let Some(emission) = emission {
+            let weight = match previous_bounce {
+                Some((origin, bsdf_pdf)) if emitters.contains(&context.array_index) => {
+                    let light_pdf = emitter_cone(origin, object)
+                        .map_or(0.0, |(_, cos_max)| cone_pdf(cos_max))
+                        / emitters.len() as f64;
+                    power_heuristic(bsdf_pdf, light_pdf)
+                }
+                _ => 1.0,
+            };
+            radiance.accumulate(weight * emission.strength, &(&throughput * &emission.color));
+        }
+        if bounce == max_bounces {
+            return Ok(());
+        }
+        let albedo = object.color_at(point);
+
+        if light_sampling {
+            // Point lights can only be reached by sampling them
+            let lights = visible_lights(
+                point,
+                normal,
+                scene.config.shadow_bias,
+                &scene.lights,
+                &scene.objects,
+                None,
+                scene.config.photon_map.is_some(),
+            );
+            for (_, light, light_ray, transmission) in lights {
+                let cos_light = light_ray.direction.dot_product(normal);
+                if cos_light > 0.0 {
+                    let light_color = transmission * light.color_for_ray(light_ray);
+                    radiance.accumulate(
+                        light.intensity() * cos_light,
+                        &(&throughput * &(&albedo * &light_color)),
+                    );
+                }
+            }
+            // One emissive object, sampled through the cone of its bounding sphere
+            if !emitters.is_empty() {
+                let emitter_index = emitters[rng.gen_range(0, emitters.len())];
+                let emitter = &scene.objects[emitter_index];
+                if let Some((axis, cos_max)) = emitter_cone(point, emitter) {
+                    let direction = sample_cone(axis, cos_max, rng);
+                    let cos_surface = direction.dot_product(normal);
+                    let light_ray = Ray::new(point, direction).shift_source();
+                    let hit = search_object_collision(&light_ray, &scene.objects);
+                    if let (true, Some(hit), Some(emission)) =
+                        (cos_surface > 0.0, hit, &emitter.effects().emission)
+                    {
+                        let faces_point = matches!(
+                            hit.object.normal_at(hit.collision_point),
+                            Some(emitter_normal)
+                                if emission.is_emitted_toward(emitter_normal, light_ray.direction)
+                        );
+                        if hit.array_index == emitter_index && faces_point {
+                            let light_pdf = cone_pdf(cos_max) / emitters.len() as f64;
+                            let bsdf_pdf = cos_surface / PI;
+                            let weight = power_heuristic(light_pdf, bsdf_pdf);
+                            radiance.accumulate(
+                                weight * emission.strength * cos_surface / (PI * light_pdf),
+                                &(&throughput * &(&albedo * &emission.color)),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+
+        // Diffuse bounce, with directions following the cosine of the normal angle
+        // so that the surface color is the only weight of the path
+        let direction = sample_cosine_hemisphere(normal, rng);
+        previous_bounce = Some((point, direction.dot_product(normal) / PI));
+        throughput = &throughput * &albedo;
+        ray = Ray::new(point, direction).shift_source();
+    }
+    Ok(())
+}
+
+/// Axis and cosine of the half angle of the cone of directions from `point` to the
+/// bounding sphere of an object. The cone is the whole sphere of directions when
+/// the point is inside the bounding sphere.
+fn emitter_cone(point: Vec3, object: &SceneObject) -> Option<(Vec3, f64)> {
+    let bounding_box = object.shape.bounding_box()?;
+    let center = bounding_box.center();
+    let radius = center.distance(bounding_box.max);
+    let distance = point.distance(center);
+    if distance <= radius {
+        return Some((Vec3::new(0.0, 0.0, 1.0), -1.0));
+    }
+    let axis = Vec3::between_points(point, center).normalize();
+    Some((axis, (1.0 - (radius / distance).powi(2)).sqrt()))
+}
+
+/// Density of directions uniformly sampled in a cone
+fn cone_pdf(cos_max: f64) -> f64 {
+    1.0 / (2.0 * PI * (1.0 - cos_max))
+}
+
+pub(crate) fn sample_cone(axis: Vec3, cos_max: f64, rng: &mut dyn RngCore) -> Vec3 {
+    let cos_theta = 1.0 - rng.gen::<f64>() * (1.0 - cos_max);
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let phi = 2.0 * PI * rng.gen::<f64>();
+    let (tangent, bitangent) = orthonormal_basis(axis);
+    (sin_theta * phi.cos()) * tangent + (sin_theta * phi.sin()) * bitangent + cos_theta * axis
+}
+
+fn sample_cosine_hemisphere(normal: Vec3, rng: &mut dyn RngCore) -> Vec3 {
+    let radius = rng.gen::<f64>().sqrt();
+    let phi = 2.0 * PI * rng.gen::<f64>();
+    let (tangent, bitangent) = orthonormal_basis(normal);
+    let height = (1.0 - radius * radius).max(0.0).sqrt();
+    (radius * phi.cos()) * tangent + (radius * phi.sin()) * bitangent + height * normal
+}
+
+/// Two unit vectors forming with `axis` an orthonormal basis
+fn orthonormal_basis(axis: Vec3) -> (Vec3, Vec3) {
+    let helper = if axis.x.abs() > 0.9 {
+        Vec3::new(0.0, 1.0, 0.0)
+    } else {
+        Vec3::new(1.0, 0.0, 0.0)
+    };
+    let tangent = helper.cross_product(axis).normalize();
+    (tangent, axis.cross_product(tangent))
+}
+
+// https://www.pbr-book.org/3ed-2018/Monte_Carlo_Integration/Importance_Sampling#MultipleImportanceSampling
+fn power_heuristic(pdf: f64, other_pdf: f64) -> f64 {
+    let square = pdf * pdf;
+    square / (square + other_pdf * other_pdf)
+}
+
+/// Lowest cosine used to scale the shadow bias, so that lights tangent to a
+/// surface do not push the shadow ray source infinitely far
+const SHADOW_BIAS_MIN_COSINE: f64 = 0.05;
+
+/// Ray from a surface point to a light, whose source is moved toward the light so
+/// that float errors on the point do not make the surface shadow itself.
+///
+/// Those errors grow with the magnitude of the coordinates, so the offset is scaled
+/// by it, as well as by the slope of the ray relative to the surface.
+fn shadow_ray(point: Vec3, normal: Vec3, direction: Vec3, shadow_bias: f64) -> Ray {
+    let cosine = direction
+        .dot_product(normal.normalize())
+        .abs()
+        .max(SHADOW_BIAS_MIN_COSINE);
+    let offset = shadow_bias * point.norm().max(1.0) / cosine;
+    Ray::new(point + offset * direction, direction)
+}
+
+/// Lights reaching a point of a surface, with their index, the ray from the point
+/// to the light, and the filter of the transparent objects crossed by the light.
+/// Lights hidden by an opaque object, which cast a shadow, are skipped, except by
+/// `ignored_object`, the index of an object letting light through itself.
+///
+/// With `opaque_shadows`, transparent objects block light too, as when the light
+/// they transmit is already given by the caustics photon map.
+pub(crate) fn visible_lights<'a>(
+    point: Vec3,
+    normal: Vec3,
+    shadow_bias: f64,
+    lights: &'a [Box<dyn AnyLightObject>],
+    objects: &'a [SceneObject],
+    ignored_object: Option<usize>,
+    opaque_shadows: bool,
+) -> impl Iterator<Item = (usize, &'a dyn AnyLightObject, Ray, Color)> + 'a {
+    lights.iter().enumerate().filter_map(move |(index, light)| {
+        let (direction, distance) = light.direction_from(point);
+        let light_ray = shadow_ray(point, normal, direction, shadow_bias);
+        let light_distance = distance - light_ray.source.distance(point);
+        let transmission = match ignored_object {
+            Some(ignored) => {
+                let before = &objects[..ignored];
+                let after = &objects[ignored + 1..];
+                light_transmission(&light_ray, light_distance, before, opaque_shadows)?
+                    * light_transmission(&light_ray, light_distance, after, opaque_shadows)?
             }
+            None => light_transmission(&light_ray, light_distance, objects, opaque_shadows)?,
+        };
+        Some((index, light.as_ref(), light_ray, transmission))
+    })
+}
+
+/// Filter applied to the light going along the ray up to `light_distance`: each
+/// transparent object crossed tints and dims it like the light seen through it,
+/// `None` if an opaque object, or any object with `opaque_shadows`, blocks it
+pub(crate) fn light_transmission(
+    ray: &Ray,
+    light_distance: f64,
+    objects: &[SceneObject],
+    opaque_shadows: bool,
+) -> Option<Color> {
+    let mut transmission = Color::WHITE;
+    for object in objects {
+        if !object.visibility.shadows || !object.shape.occludes(ray, light_distance) {
+            continue;
         }
+        let transparency = object
+            .effects()
+            .transparency
+            .as_ref()
+            .filter(|_| !opaque_shadows)?;
+        let filter = transparency.alpha
+            * transparency
+                .transmission_color
+                .clone()
+                .unwrap_or(Color::WHITE);
+        transmission = transmission * filter;
+    }
+    Some(transmission)
+}
+
+/// Orient a surface normal toward the side the ray comes from, so that
+/// surfaces are lit on both sides
+fn facing_normal(normal: Vec3, incoming_ray: &Ray) -> Vec3 {
+    if normal.dot_product(incoming_ray.direction) > 0.0 {
+        -normal
+    } else {
+        normal
     }
-    Ok(total_color)
 }
 
-#[allow(clippy::if_same_then_else)]
-fn ray_encounter_obstacle(ray: &Ray, destination: &Vec3, objects: &[SceneObject]) -> bool {
-    let source = ray.source;
-    let light_distance = Vec3::between_points(source, *destination).norm();
+/// Whether an object casting shadows is hit by the ray before `light_distance`, which
+/// can be infinite
+pub(crate) fn ray_encounter_obstacle(
+    ray: &Ray,
+    light_distance: f64,
+    objects: &[SceneObject],
+) -> bool {
     // Check of object obstruction between light and collision point
-    for candidate_object in objects {
-        if let Some(obstruction_point) = candidate_object.check_collision(ray) {
-            let object_distance = Vec3::between_points(source, obstruction_point).norm();
-            if object_distance > light_distance {
-                // Not between the object and the light
-                continue;
-            } else if object_distance <= 1e-12 {
-                // TODO Check why this value is so high, it was f64::EPSILON before
-                // Float comparison error, source is probably also the candidate object
-                continue;
-            } else {
-                // Object is hiding an other
-                return true;
+    objects.iter().any(|candidate_object| {
+        candidate_object.visibility.shadows && candidate_object.shape.occludes(ray, light_distance)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cameras::{OrthogonalCamera, PerspectiveCamera};
+    use crate::lights::LightPoint;
+    use crate::primitives::{InfinitePlan, Sphere};
+    use crate::scene::{RayVisibility, SceneConfiguration};
+    use crate::textures::{Emission, Mirror, Phong, PlainColorTexture, Subsurface, TextureEffects};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn plane_scene(light_source: Vec3) -> Scene {
+        let plane = SceneObject::new(
+            Box::new(InfinitePlan::new(Vec3::ZERO, Vec3::new(0.0, 1.0, 0.0))),
+            Box::new(PlainColorTexture::default()),
+        );
+        Scene {
+            camera: Box::new(OrthogonalCamera::default()),
+            lights: vec![Box::new(LightPoint::new(light_source))],
+            objects: vec![plane],
+            config: SceneConfiguration {
+                ambient_light: None,
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn plane_is_lit_from_both_sides() {
+        for &side in &[1.0, -1.0] {
+            let scene = plane_scene(Vec3::new(0.0, side * 10.0, 0.0));
+            let ray = Ray::ray_from_to(Vec3::new(0.0, side * 5.0, -5.0), Vec3::ZERO);
+            let color = launch_ray(&ray, &scene, 0).unwrap();
+            assert!(color.red() > 0.5, "plane not lit on side {}", side);
+        }
+    }
+
+    #[test]
+    fn negative_light_darkens_ambient_light() {
+        let mut scene = plane_scene(Vec3::new(0.0, 10.0, 0.0));
+        scene.config.ambient_light = Some(Color::new(0.5, 0.5, 0.5));
+        let ray = Ray::ray_from_to(Vec3::new(0.0, 5.0, -5.0), Vec3::ZERO);
+        scene.lights.clear();
+        let ambient_only = launch_ray(&ray, &scene, 0).unwrap();
+        assert!((ambient_only.red() - 0.5).abs() < 1e-9);
+        let negative_light = LightPoint::new(Vec3::new(0.0, 10.0, 0.0)).with_intensity(-0.3);
+        scene.lights.push(Box::new(negative_light));
+        let darkened = launch_ray(&ray, &scene, 0).unwrap();
+        assert!((darkened.red() - 0.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn holdout_shows_background_and_casts_shadow() {
+        let mut scene = plane_scene(Vec3::new(0.0, 10.0, 0.0));
+        scene.config.world_color = Color::BLUE;
+        scene.objects.push(
+            SceneObject::new(
+                Box::new(Sphere {
+                    center: Vec3::new(0.0, 3.0, 0.0),
+                    radius: 1.0,
+                }),
+                Box::new(PlainColorTexture::default()),
+            )
+            .with_holdout(true),
+        );
+        let ray_to_holdout = Ray::ray_from_to(Vec3::new(0.0, 6.0, -6.0), Vec3::new(0.0, 3.0, 0.0));
+        let color = launch_ray(&ray_to_holdout, &scene, 0).unwrap();
+        assert!(color.red() < 1e-9 && color.green() < 1e-9 && color.blue() > 1.0 - 1e-9);
+        let ray_to_shadow = Ray::ray_from_to(Vec3::new(0.0, 5.0, -5.0), Vec3::ZERO);
+        let color = launch_ray(&ray_to_shadow, &scene, 0).unwrap();
+        assert!(color.red() < 1e-9);
+        let ray_to_lit_plane =
+            Ray::ray_from_to(Vec3::new(5.0, 5.0, -5.0), Vec3::new(5.0, 0.0, 0.0));
+        let color = launch_ray(&ray_to_lit_plane, &scene, 0).unwrap();
+        assert!(color.red() > 0.5);
+    }
+
+    #[test]
+    fn object_hidden_from_camera_still_casts_shadow() {
+        let mut scene = plane_scene(Vec3::new(0.0, 10.0, 0.0));
+        scene.objects.push(
+            SceneObject::new(
+                Box::new(Sphere {
+                    center: Vec3::new(0.0, 3.0, 0.0),
+                    radius: 1.0,
+                }),
+                Box::new(PlainColorTexture::default()),
+            )
+            .with_visibility(RayVisibility {
+                camera: false,
+                ..Default::default()
+            }),
+        );
+        let ray_through_sphere =
+            Ray::ray_from_to(Vec3::new(0.0, 6.0, -6.0), Vec3::new(0.0, 3.0, 0.0));
+        let hit = search_visible_collision(&ray_through_sphere, &scene.objects, RayKind::Camera);
+        assert_eq!(hit.unwrap().array_index, 0);
+        let hit =
+            search_visible_collision(&ray_through_sphere, &scene.objects, RayKind::Reflection);
+        assert_eq!(hit.unwrap().array_index, 1);
+        let ray_to_shadow = Ray::ray_from_to(Vec3::new(0.0, 5.0, -5.0), Vec3::ZERO);
+        let color = launch_ray(&ray_to_shadow, &scene, 0).unwrap();
+        assert!(color.red() < 1e-9);
+        scene.objects[1].visibility.shadows = false;
+        let color = launch_ray(&ray_to_shadow, &scene, 0).unwrap();
+        assert!(color.red() > 0.5);
+    }
+
+    fn glass_sphere(center: Vec3, refractive_index: f64, priority: i32) -> SceneObject {
+        SceneObject::new(
+            Box::new(Sphere {
+                center,
+                radius: 2.0,
+            }),
+            Box::new(PlainColorTexture::default()),
+        )
+        .with_effects(TextureEffects {
+            transparency: Some(Transparency {
+                refractive_index,
+                priority,
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn overlapping_transparency_uses_highest_priority() {
+        for &(first_priority, second_priority, expected_index) in
+            &[(0, 5, 1.2), (5, 0, 1.5), (0, 0, 1.5)]
+        {
+            let mut scene = plane_scene(Vec3::new(0.0, 10.0, 0.0));
+            scene.objects = vec![
+                glass_sphere(Vec3::new(0.0, 0.0, 0.0), 1.5, first_priority),
+                glass_sphere(Vec3::new(0.0, 0.0, -1.0), 1.2, second_priority),
+            ];
+            // The ray enters the first sphere at z = -2, inside the second sphere
+            let ray = Ray::ray_from_to(Vec3::new(0.0, 0.0, -10.0), Vec3::ZERO);
+            let transparency = scene.objects[0].effects().transparency.as_ref().unwrap();
+            let index =
+                entered_refractive_index(0, transparency, Vec3::new(0.0, 0.0, -2.0), &ray, &scene);
+            assert!((index - expected_index).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn transmission_color_tints_what_is_seen_through() {
+        let seen_through_sheet = |transmission_color: Option<Color>| {
+            let mut scene = plane_scene(Vec3::new(0.0, 10.0, 10.0));
+            // Black glass on the way to the plane, so that only refraction is seen
+            scene.objects.push(
+                SceneObject::new(
+                    Box::new(Sphere {
+                        center: Vec3::new(0.0, 2.5, -2.5),
+                        radius: 1.0,
+                    }),
+                    Box::new(PlainColorTexture {
+                        color: Color::BLACK,
+                    }),
+                )
+                .with_effects(TextureEffects {
+                    transparency: Some(Transparency {
+                        transmission_color,
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }),
+            );
+            let ray = Ray::ray_from_to(Vec3::new(0.0, 5.0, -5.0), Vec3::ZERO);
+            launch_ray(&ray, &scene, 0).unwrap()
+        };
+        let clear = seen_through_sheet(None);
+        assert!(clear.green() > 0.1);
+        let tinted = seen_through_sheet(Some(Color::RED));
+        assert!((tinted.red() - clear.red()).abs() < 1e-9);
+        assert!(tinted.green() < 1e-9 && tinted.blue() < 1e-9);
+    }
+
+    #[test]
+    fn light_behind_surface_gives_no_specular() {
+        // The light is below the plane, but close to the reflected ray direction
+        let mut scene = plane_scene(Vec3::new(0.0, -0.1, 100.0));
+        scene.objects[0].effects.phong = Some(Phong {
+            size: 1,
+            lum_coeff: 1.0,
+        });
+        let ray = Ray::ray_from_to(Vec3::new(0.0, 5.0, -5.0), Vec3::ZERO);
+        let color = launch_ray(&ray, &scene, 0).unwrap();
+        assert!(color.red() < 1e-9 && color.green() < 1e-9 && color.blue() < 1e-9);
+    }
+
+    #[test]
+    fn plane_is_not_lit_from_opposite_side() {
+        let scene = plane_scene(Vec3::new(0.0, -10.0, 0.0));
+        let ray = Ray::ray_from_to(Vec3::new(0.0, 5.0, -5.0), Vec3::ZERO);
+        let color = launch_ray(&ray, &scene, 0).unwrap();
+        assert!(color.red() < 1e-9);
+    }
+
+    #[test]
+    fn rotated_grid_offsets_differ_from_aligned_and_are_centered() {
+        use strategy::{GridAntiAliasingRenderStrategy, GridPattern};
+        let grid = |pattern| GridAntiAliasingRenderStrategy {
+            samples_per_side: 2,
+            pattern,
+        };
+        let aligned = grid(GridPattern::Aligned).sample_offsets();
+        let rotated = grid(GridPattern::Rotated).sample_offsets();
+        assert_eq!(aligned.len(), 4);
+        assert_eq!(rotated.len(), 4);
+        for (&(aligned_x, aligned_y), &(rotated_x, rotated_y)) in aligned.iter().zip(&rotated) {
+            assert!((aligned_x - rotated_x).abs() + (aligned_y - rotated_y).abs() > 1e-3);
+            assert!((0.0..1.0).contains(&rotated_x) && (0.0..1.0).contains(&rotated_y));
+        }
+        // Each sample of the rotated grid has its own column and row
+        for (index, &(x, y)) in rotated.iter().enumerate() {
+            for &(other_x, other_y) in &rotated[index + 1..] {
+                assert!((x - other_x).abs() > 1e-3 && (y - other_y).abs() > 1e-3);
+            }
+        }
+        let mean_x = rotated.iter().map(|(x, _)| x).sum::<f64>() / 4.0;
+        let mean_y = rotated.iter().map(|(_, y)| y).sum::<f64>() / 4.0;
+        assert!((mean_x - 0.5).abs() < 1e-9 && (mean_y - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn adaptive_sampling_only_splits_varying_areas() {
+        use strategy::AdaptiveAntiAliasingRenderStrategy;
+        let scene = Scene {
+            camera: Box::new(OrthogonalCamera::new(
+                Vec3::new(0.0, 0.0, -10.0),
+                Vec3::ZERO,
+                1.0,
+                1.0,
+            )),
+            lights: vec![Box::new(LightPoint::new(Vec3::new(0.0, 0.0, -10.0)))],
+            // Seen at the center of the screen, but not at its corners
+            objects: vec![SceneObject::new(
+                Box::new(Sphere {
+                    center: Vec3::new(0.3, 0.0, 0.0),
+                    radius: 0.4,
+                }),
+                Box::new(PlainColorTexture::default()),
+            )],
+            config: Default::default(),
+        };
+        let adaptive = |variance_threshold| AdaptiveAntiAliasingRenderStrategy {
+            max_rays: 40,
+            variance_threshold,
+        };
+        let sample_count = |strategy: &AdaptiveAntiAliasingRenderStrategy, x, size| {
+            strategy
+                .render_pixel_metadata(&scene, &*scene.camera, x, 0.0, size, size)
+                .sample_count
+        };
+        let strategy = adaptive(0.01);
+        let flat_color = strategy
+            .render_pixel(&scene, &*scene.camera, 0.0, 0.0, 0.05, 0.05)
+            .unwrap();
+        assert_eq!(flat_color.to_color().to_rgb8(), [0, 0, 0]);
+        assert_eq!(sample_count(&strategy, 0.0, 0.05), 5);
+        assert_eq!(sample_count(&strategy, 0.95, 0.05), 5);
+        let edge_count = sample_count(&strategy, 0.0, 1.0);
+        assert!(edge_count > 5 && edge_count <= 40);
+        let edge_color = strategy
+            .render_pixel(&scene, &*scene.camera, 0.0, 0.0, 1.0, 1.0)
+            .unwrap();
+        assert!(edge_color.red() > 0.0 && edge_color.red() < 1.0);
+        assert_eq!(sample_count(&adaptive(f64::INFINITY), 0.0, 1.0), 5);
+    }
+
+    #[test]
+    fn averaged_samples_keep_a_bright_color() {
+        use strategy::{
+            GridAntiAliasingRenderStrategy, GridPattern, RandomAntiAliasingRenderStrategy,
+        };
+        // Each sample sees a surface emitting more light than a displayable white
+        let scene = Scene {
+            camera: Box::new(OrthogonalCamera::default()),
+            lights: vec![],
+            objects: vec![SceneObject::new(
+                Box::new(Sphere {
+                    center: Vec3::ZERO,
+                    radius: 3.0,
+                }),
+                Box::new(PlainColorTexture::default()),
+            )
+            .with_effects(TextureEffects {
+                emission: Some(Emission {
+                    color: Color::new(1.0, 0.5, 0.25),
+                    strength: 4.0,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })],
+            config: SceneConfiguration {
+                ambient_light: None,
+                ..Default::default()
+            },
+        };
+        let strategies: [Box<dyn AnyPixelRenderStrategy>; 2] = [
+            Box::new(RandomAntiAliasingRenderStrategy {
+                rays_per_pixel: 7,
+                seed: Some(1),
+            }),
+            Box::new(GridAntiAliasingRenderStrategy {
+                samples_per_side: 3,
+                pattern: GridPattern::Rotated,
+            }),
+        ];
+        for strategy in strategies.iter() {
+            let radiance = strategy
+                .render_pixel(&scene, &*scene.camera, 0.5, 0.5, 0.01, 0.01)
+                .unwrap();
+            assert!((radiance.red() - 4.0).abs() < 1e-9);
+            assert!((radiance.green() - 2.0).abs() < 1e-9);
+            assert!((radiance.blue() - 1.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn pixel_rng_sequence_is_stable() {
+        use strategy::PixelRng;
+        // Reference output of SplitMix64 for a zero seed
+        let mut rng = PixelRng::new(0);
+        assert_eq!(rng.next_u64(), 0xe220_a839_7b1d_cdaf);
+        assert_eq!(rng.next_u64(), 0x6e78_9e6a_a1b9_65f4);
+        assert_eq!(rng.next_u64(), 0x06c4_5d18_8009_454f);
+        let mut first = PixelRng::for_pixel(1, 0.25, 0.5);
+        let mut same = PixelRng::for_pixel(1, 0.25, 0.5);
+        let mut other = PixelRng::for_pixel(1, 0.5, 0.25);
+        let first_value = first.next_u64();
+        assert_eq!(first_value, same.next_u64());
+        assert_ne!(first_value, other.next_u64());
+    }
+
+    #[test]
+    fn emissive_surface_is_seen_without_light() {
+        let mut scene = plane_scene(Vec3::new(0.0, 10.0, 0.0));
+        scene.objects[0].texture = Box::new(PlainColorTexture {
+            color: Color::BLACK,
+        });
+        scene.objects[0].effects.emission = Some(Emission {
+            color: Color::GREEN,
+            strength: 0.5,
+            emissive_two_sided: false,
+        });
+        let from_above = Ray::ray_from_to(Vec3::new(0.0, 5.0, -5.0), Vec3::ZERO);
+        let passes = launch_ray_passes(&from_above, &scene, 0).unwrap();
+        assert!((passes.emission.green() - 0.5).abs() < 1e-9);
+        assert!((passes.combined().green() - 0.5).abs() < 1e-9);
+        assert!(passes.combined().red() < 1e-9);
+        // Only emitted on the side of the normal
+        let from_below = Ray::ray_from_to(Vec3::new(0.0, -5.0, -5.0), Vec3::ZERO);
+        let passes = launch_ray_passes(&from_below, &scene, 0).unwrap();
+        assert!(passes.emission.green() < 1e-9);
+    }
+
+    #[test]
+    fn transparent_object_casts_a_lighter_tinted_shadow() {
+        let shadow_under_sphere = |transparency: Option<Transparency>| {
+            let mut scene = plane_scene(Vec3::new(0.0, 10.0, 0.0));
+            scene.objects.push(
+                SceneObject::new(
+                    Box::new(Sphere {
+                        center: Vec3::new(0.0, 3.0, 0.0),
+                        radius: 1.0,
+                    }),
+                    Box::new(PlainColorTexture::default()),
+                )
+                .with_effects(TextureEffects {
+                    transparency,
+                    ..Default::default()
+                }),
+            );
+            let ray = Ray::ray_from_to(Vec3::new(0.0, 5.0, -5.0), Vec3::ZERO);
+            launch_ray_passes(&ray, &scene, 0).unwrap().diffuse
+        };
+        let opaque = shadow_under_sphere(None);
+        let glass = shadow_under_sphere(Some(Transparency {
+            alpha: 0.8,
+            transmission_color: Some(Color::new(1.0, 0.5, 0.5)),
+            ..Default::default()
+        }));
+        assert!(opaque.red() < 1e-9);
+        assert!((glass.red() - 0.8).abs() < 1e-9);
+        assert!((glass.green() - 0.4).abs() < 1e-9);
+    }
+
+    #[test]
+    fn fresnel_transparency_splits_light_and_handles_total_reflection() {
+        let glass_scene = |world_refractive_index: f64, refractive_index: f64| {
+            let mut scene = plane_scene(Vec3::new(0.0, 10.0, 0.0));
+            // Large sphere whose top is flat around the origin, for the rays to exit it
+            scene.objects[0].shape = Box::new(Sphere {
+                center: Vec3::new(0.0, -100.0, 0.0),
+                radius: 100.0,
+            });
+            scene.objects[0].effects.transparency = Some(Transparency {
+                refractive_index,
+                alpha: 1.0,
+                fresnel: true,
+                ..Default::default()
+            });
+            scene.config.world_refractive_index = world_refractive_index;
+            scene
+        };
+        let reflectance = |scene: &Scene, ray: &Ray| {
+            let context = search_object_collision(ray, &scene.objects).unwrap();
+            let transparency = context.object.effects().transparency.as_ref().unwrap();
+            let reflectance = fresnel_reflectance(ray, scene, &context, transparency).unwrap();
+            let transmitted = transmitted_ray(ray, scene, &context, transparency).unwrap();
+            (reflectance, transmitted)
+        };
+        let glass = glass_scene(1.0, 1.5);
+        let head_on = Ray::ray_from_to(Vec3::new(0.0, 5.0, 0.0), Vec3::ZERO);
+        let grazing = Ray::ray_from_to(Vec3::new(0.0, 0.5, -10.0), Vec3::ZERO);
+        let (head_on_reflectance, _) = reflectance(&glass, &head_on);
+        let (grazing_reflectance, _) = reflectance(&glass, &grazing);
+        assert!((head_on_reflectance - 0.04).abs() < 1e-9);
+        assert!(grazing_reflectance > 0.5);
+        let context = search_object_collision(&grazing, &glass.objects);
+        let shading = shade_surface(&grazing, &glass, context).unwrap();
+        let (_, refraction_filter) = shading.refraction.unwrap();
+        let (_, reflection_filter) = shading.reflection.unwrap();
+        assert!((refraction_filter.red() + reflection_filter.red() - 1.0).abs() < 1e-9);
+        // Leaving a dense medium at a grazing angle, all the light is reflected
+        let (total_reflectance, transmitted) = reflectance(&glass_scene(1.5, 1.0), &grazing);
+        assert_eq!(total_reflectance, 1.0);
+        assert!(transmitted.is_none());
+        let color = launch_ray(&grazing, &glass_scene(1.5, 1.0), 0).unwrap();
+        assert!(color.red().is_finite() && color.green().is_finite() && color.blue().is_finite());
+    }
+
+    #[test]
+    fn direct_only_skips_reflections() {
+        let mirror_scene = |with_sphere: bool, direct_only: bool| {
+            let mut scene = plane_scene(Vec3::new(0.0, 10.0, -10.0));
+            scene.objects[0].effects.mirror = Some(Mirror {
+                coeff: 1.0,
+                ..Default::default()
+            });
+            if with_sphere {
+                // Seen in the mirror from the tested ray, but not shadowing its hit point
+                scene.objects.push(SceneObject::new(
+                    Box::new(Sphere {
+                        center: Vec3::new(0.0, 3.0, 3.0),
+                        radius: 1.0,
+                    }),
+                    Box::new(PlainColorTexture { color: Color::RED }),
+                ));
             }
+            scene.config.direct_only = direct_only;
+            scene
+        };
+        let ray = Ray::ray_from_to(Vec3::new(0.0, 5.0, -5.0), Vec3::ZERO);
+        let color = |scene: &Scene| launch_ray(&ray, scene, 0).unwrap();
+        let reflected = color(&mirror_scene(true, false));
+        let plain = color(&mirror_scene(false, false));
+        assert!(
+            (reflected.red() - plain.red()).abs() + (reflected.blue() - plain.blue()).abs() > 0.1
+        );
+        let reflected = color(&mirror_scene(true, true));
+        let plain = color(&mirror_scene(false, true));
+        assert!((reflected.red() - plain.red()).abs() < 1e-9);
+        assert!((reflected.green() - plain.green()).abs() < 1e-9);
+        assert!((reflected.blue() - plain.blue()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn front_facing_view_space_normal_points_to_positive_z() {
+        use strategy::{NormalRenderStrategy, NormalSpace};
+        let mut scene = plane_scene(Vec3::new(0.0, 10.0, 0.0));
+        scene.objects[0].shape = Box::new(Sphere {
+            center: Vec3::ZERO,
+            radius: 2.0,
+        });
+        // Looking along +x, so that world and view spaces differ
+        scene.camera = Box::new(PerspectiveCamera::from_eye(
+            Vec3::new(-10.0, 0.0, 0.0),
+            Vec3::ZERO,
+            16.0,
+            9.0,
+            PI / 8.0,
+        ));
+        let center_color = |space: NormalSpace| {
+            NormalRenderStrategy { space }
+                .render_pixel(&scene, &*scene.camera, 0.49, 0.49, 0.02, 0.02)
+                .unwrap()
+        };
+        let view = center_color(NormalSpace::View);
+        assert!(f64::abs(view.red() - 0.5) < 1e-9 && f64::abs(view.green() - 0.5) < 1e-9);
+        assert!(f64::abs(view.blue() - 1.0) < 1e-9);
+        let world = center_color(NormalSpace::World);
+        assert!(world.red() < 1e-9 && f64::abs(world.blue() - 0.5) < 1e-9);
+    }
+
+    #[test]
+    fn secondary_ray_budget_bounds_rays_of_a_pixel() {
+        // Glass and mirror sphere between two facing mirrors: each hit splits the ray
+        let mut scene = plane_scene(Vec3::new(0.0, 5.0, -10.0));
+        scene.config.maximum_light_recursion = 16;
+        scene.objects[0].effects.mirror = Some(Mirror {
+            coeff: 0.9,
+            ..Default::default()
+        });
+        scene.objects.push(
+            SceneObject::new(
+                Box::new(InfinitePlan::new(
+                    Vec3::new(0.0, 6.0, 0.0),
+                    Vec3::new(0.0, -1.0, 0.0),
+                )),
+                Box::new(PlainColorTexture::default()),
+            )
+            .with_effects(TextureEffects {
+                mirror: Some(Mirror {
+                    coeff: 0.9,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+        );
+        let mut sphere = glass_sphere(Vec3::new(0.0, 3.0, 0.0), 1.5, 0);
+        sphere.effects.mirror = Some(Mirror {
+            coeff: 0.5,
+            ..Default::default()
+        });
+        scene.objects.push(sphere);
+        let ray = Ray::ray_from_to(Vec3::new(0.3, 5.9, -0.5), Vec3::new(0.1, 3.0, 0.2));
+        let mut unlimited = SecondaryRayBudget::new(&scene);
+        launch_budgeted_ray(&ray, &scene, 0, &mut unlimited).unwrap();
+        assert!(unlimited.launched > 100);
+
+        scene.config.secondary_ray_budget = Some(20);
+        let mut budget = SecondaryRayBudget::new(&scene);
+        for _ in 0..4 {
+            launch_budgeted_ray(&ray, &scene, 0, &mut budget).unwrap();
+        }
+        assert_eq!(budget.launched, 20);
+        let layers = launch_ray_layers(&ray, &scene).unwrap();
+        assert!(layers[0].red() > 0.0);
+    }
+
+    #[test]
+    fn mirror_reflection_is_in_first_depth_layer() {
+        let mut scene = plane_scene(Vec3::new(0.0, 10.0, -10.0));
+        scene.objects[0].effects.mirror = Some(Mirror {
+            coeff: 0.5,
+            ..Default::default()
+        });
+        scene.objects.push(SceneObject::new(
+            Box::new(Sphere {
+                center: Vec3::new(0.0, 3.0, 3.0),
+                radius: 1.0,
+            }),
+            Box::new(PlainColorTexture { color: Color::RED }),
+        ));
+        let ray = Ray::ray_from_to(Vec3::new(0.0, 5.0, -5.0), Vec3::ZERO);
+        let layers = launch_ray_layers(&ray, &scene).unwrap();
+        let passes = launch_ray_passes(&ray, &scene, 0).unwrap();
+        let direct = RenderPasses {
+            reflection: Radiance::default(),
+            ..passes.clone()
         }
+        .combined();
+        let same_color = |a: &Radiance, b: &Radiance| {
+            (a.red() - b.red()).abs() < 1e-9
+                && (a.green() - b.green()).abs() < 1e-9
+                && (a.blue() - b.blue()).abs() < 1e-9
+        };
+        assert!(same_color(&layers[0], &direct));
+        assert!(layers[1].red() > 0.1 && layers[1].green() < 1e-9);
+        assert!(same_color(&layers[1], &passes.reflection));
+        assert!(same_color(&(layers[0] + layers[1]), &passes.combined()));
+    }
+
+    #[test]
+    fn light_sampling_reduces_path_tracing_noise() {
+        // Small emissive sphere right above a white plane
+        let mut scene = plane_scene(Vec3::new(0.0, 10.0, 0.0));
+        scene.lights.clear();
+        scene.objects.push(
+            SceneObject::new(
+                Box::new(Sphere {
+                    center: Vec3::new(0.0, 5.0, 0.0),
+                    radius: 0.5,
+                }),
+                Box::new(PlainColorTexture::default()),
+            )
+            .with_effects(TextureEffects {
+                emission: Some(Emission::default()),
+                ..Default::default()
+            }),
+        );
+        // Radiance of the plane under a sphere of angular radius `asin(0.1)`
+        let expected = 0.1_f64.powi(2);
+        let sample_count = 20000;
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut statistics = |light_sampling: bool| {
+            let samples: Vec<f64> = (0..sample_count)
+                .map(|_| {
+                    let ray = Ray::ray_from_to(Vec3::new(0.0, 5.0, -5.0), Vec3::ZERO);
+                    let mut radiance = Radiance::default();
+                    trace_path(ray, &scene, 1, light_sampling, &mut rng, &mut radiance).unwrap();
+                    radiance.mean(1).red()
+                })
+                .collect();
+            let mean = samples.iter().sum::<f64>() / sample_count as f64;
+            let variance = samples
+                .iter()
+                .map(|value| (value - mean).powi(2))
+                .sum::<f64>()
+                / sample_count as f64;
+            (mean, variance)
+        };
+        let (naive_mean, naive_variance) = statistics(false);
+        let (sampled_mean, sampled_variance) = statistics(true);
+        assert!((naive_mean - expected).abs() < 0.3 * expected);
+        assert!((sampled_mean - expected).abs() < 0.05 * expected);
+        assert!(sampled_variance < naive_variance / 10.0);
+    }
+
+    #[test]
+    fn seeded_path_tracing_is_reproducible() {
+        use strategy::PathTracingRenderStrategy;
+        let mut scene = plane_scene(Vec3::new(0.0, 10.0, 0.0));
+        scene.objects[0].effects.emission = Some(Emission::default());
+        let strategy = PathTracingRenderStrategy {
+            samples_per_pixel: 4,
+            max_bounces: 2,
+            light_sampling: true,
+            seed: Some(5),
+        };
+        let first = strategy
+            .render_pixel(&scene, &*scene.camera, 0.5, 0.5, 0.01, 0.01)
+            .unwrap();
+        let second = strategy
+            .render_pixel(&scene, &*scene.camera, 0.5, 0.5, 0.01, 0.01)
+            .unwrap();
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn one_sided_emissive_plane_is_dark_from_behind() {
+        let mut scene = plane_scene(Vec3::new(0.0, 10.0, 0.0));
+        scene.lights.clear();
+        scene.objects[0].effects = TextureEffects {
+            emission: Some(Emission {
+                emissive_two_sided: false,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let mut rng = rand::thread_rng();
+        let mut radiance_from = |side: f64| {
+            let ray = Ray::ray_from_to(Vec3::new(0.0, side * 5.0, -5.0), Vec3::ZERO);
+            let mut radiance = Radiance::default();
+            trace_path(ray, &scene, 0, true, &mut rng, &mut radiance).unwrap();
+            radiance.mean(1).red()
+        };
+        assert!(radiance_from(1.0) > 0.99);
+        assert!(radiance_from(-1.0) < 1e-9);
+    }
+
+    /// Fraction of sampled points of a tilted plane lit by a light above them, away
+    /// from a sphere lying on the plane
+    fn lit_plane_fraction(scale: f64, shadow_bias: f64) -> f64 {
+        let normal = Vec3::new(0.1, 1.0, 0.2).normalize();
+        let center = scale * Vec3::new(0.3, -0.2, 0.1);
+        let mut scene = plane_scene(center + 10.0 * scale * normal);
+        scene.config.shadow_bias = shadow_bias;
+        scene.objects[0].shape = Box::new(InfinitePlan::new(center, normal));
+        scene.objects.push(SceneObject::new(
+            Box::new(Sphere {
+                center: center + scale * normal,
+                radius: scale,
+            }),
+            Box::new(PlainColorTexture::default()),
+        ));
+        let samples = 400;
+        let lit = (0..samples)
+            .filter(|index| {
+                let eye = center + scale * Vec3::new(0.7, 4.0, -3.1);
+                let ray_to_center = Ray::ray_from_to(eye, center);
+                let ray = Ray::new(
+                    eye,
+                    ray_to_center.direction
+                        + Vec3::new(
+                            0.3 + 0.0013 * f64::from(index % 20),
+                            0.0,
+                            0.3 + 0.0017 * f64::from(index / 20),
+                        ),
+                );
+                let color = launch_ray(&ray, &scene, 0).unwrap();
+                color.red() > 0.5
+            })
+            .count();
+        f64::from(lit as u32) / f64::from(samples)
+    }
+
+    #[test]
+    fn large_plane_has_no_shadow_acne() {
+        let default_bias = SceneConfiguration::default().shadow_bias;
+        assert!(lit_plane_fraction(1e5, 0.0) < 0.9);
+        assert!((lit_plane_fraction(1e5, default_bias) - 1.0).abs() < 1e-9);
+        assert!((lit_plane_fraction(1.0, default_bias) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn shadow_bias_does_not_change_small_scene_shading() {
+        let scene = plane_scene(Vec3::new(2.0, 10.0, 1.0));
+        let mut unbiased_scene = plane_scene(Vec3::new(2.0, 10.0, 1.0));
+        unbiased_scene.config.shadow_bias = 0.0;
+        let ray = Ray::ray_from_to(Vec3::new(0.0, 5.0, -5.0), Vec3::new(0.5, 0.0, 0.5));
+        let color = launch_ray(&ray, &scene, 0).unwrap();
+        let unbiased_color = launch_ray(&ray, &unbiased_scene, 0).unwrap();
+        assert!((color.red() - unbiased_color.red()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn subsurface_lights_past_the_terminator() {
+        let color_past_terminator = |subsurface: Option<Subsurface>| {
+            let scene = Scene {
+                camera: Box::new(OrthogonalCamera::default()),
+                lights: vec![Box::new(LightPoint::new(Vec3::new(10.0, 0.0, 0.0)))],
+                objects: vec![SceneObject::new(
+                    Box::new(Sphere::default()),
+                    Box::new(PlainColorTexture::default()),
+                )
+                .with_effects(TextureEffects {
+                    subsurface,
+                    ..Default::default()
+                })],
+                config: SceneConfiguration {
+                    ambient_light: None,
+                    ..Default::default()
+                },
+            };
+            // Hits the sphere slightly on the dark side of the terminator
+            let ray = Ray::new(Vec3::new(-0.1, 0.0, -10.0), Vec3::new(0.0, 0.0, 1.0));
+            launch_ray(&ray, &scene, 0).unwrap()
+        };
+        assert!(color_past_terminator(None).red() < 1e-9);
+        let subsurface = Subsurface {
+            wrap: 0.5,
+            ..Default::default()
+        };
+        let wrapped = color_past_terminator(Some(subsurface)).red();
+        assert!(wrapped > 0.1 && wrapped < 0.5);
     }
-    false
 }
@@ -22,17 +22,35 @@ OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 SOFTWARE.
 */
 
-use crate::colors::Color;
+use crate::cameras::{CameraBasis, CroppedCamera, OrthogonalCamera};
+use crate::colors::{Color, Radiance, ToneMapping, TransferFunction};
+use crate::primitives::Ray;
 use crate::ray_algorithm::strategy::StandardRenderStrategy;
-use crate::ray_algorithm::AnyPixelRenderStrategy;
+use crate::ray_algorithm::{
+    launch_primary_ray, launch_ray, launch_ray_layers, launch_ray_passes, reflected_ray,
+    search_visible_collision, transmitted_ray, AnyPixelRenderStrategy, PixelMetadata, RayKind,
+    RenderPasses,
+};
 use crate::result::{RaytracerError, Result};
-use crate::scene::Scene;
+use crate::scene::{AcceleratedScene, RayEmitter, Scene};
+use crate::vector::Vec3;
+use crate::UnitInterval;
 use instant::Instant;
 use log::{debug, info, trace, warn};
+use rand::Rng;
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::fs::File;
+use std::io::{Seek, SeekFrom, Write as IoWrite};
 use std::iter::from_fn;
-use std::sync::mpsc;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Pixel {
     pub x: u32,
     pub y: u32,
@@ -45,10 +63,187 @@ impl Pixel {
     }
 }
 
+/// Pixel with metadata, to build depth or object id buffers along the color
+#[derive(Debug)]
+pub struct PixelData {
+    pub x: u32,
+    pub y: u32,
+    pub color: Color,
+    pub metadata: PixelMetadata,
+}
+
 pub struct RenderConfiguration {
     pub canvas_width: u32,
     pub canvas_height: u32,
     pub render_strategy: Box<dyn AnyPixelRenderStrategy>,
+    /// Exposure value, final colors are multiplied by `2^exposure`
+    pub exposure: f64,
+    /// Mapping of the exposed colors to the displayable range
+    pub tone_mapping: ToneMapping,
+    /// Map out of gamut colors by desaturating them at constant luminance, instead
+    /// of clamping each component, after tone mapping
+    pub gamut_clamp: bool,
+    /// Objects whose bounding sphere spans less than this number of pixels are
+    /// removed before rendering
+    pub small_object_culling: Option<f64>,
+    /// Width divided by height of a pixel on the target display. When it is not 1,
+    /// the camera screen is cropped so that the displayed image keeps the scene
+    /// proportions, each pixel covering `pixel_aspect_ratio` times more of the scene
+    /// horizontally than vertically.
+    pub pixel_aspect_ratio: f64,
+    /// When set, the render strategy is only used for the pixels of this region, and
+    /// other pixels get a single ray, to speed up the examination of a detail
+    pub high_quality_region: Option<Rect>,
+    /// Validation of final colors, to track down invalid light computations
+    pub nan_check: NanCheck,
+    /// With the parallel renderer, dispatch a coarse grid of pixels, one every
+    /// `COARSE_TO_FINE_STEP` pixels, before filling in the others, so that a rough
+    /// full image is available early during a progressive display
+    pub coarse_to_fine: bool,
+    /// Encoding of the final colors when they are written to an 8-bit output; pixel
+    /// colors given by the renderer stay linear
+    pub transfer_function: TransferFunction,
+}
+
+/// Rectangle of pixels, from its top-left corner
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        (self.x..self.x + self.width).contains(&x) && (self.y..self.y + self.height).contains(&y)
+    }
+}
+
+/// What to do with a pixel color having NaN or infinite components
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum NanCheck {
+    /// Colors are not checked
+    Disabled,
+    /// Invalid colors are logged and replaced by `NAN_DEBUG_COLOR`
+    DebugColor,
+    /// Panic on invalid colors, reporting the pixel coordinates
+    Panic,
+}
+
+/// Color replacing invalid pixel colors when `NanCheck::DebugColor` is used
+pub const NAN_DEBUG_COLOR: Color = Color::MAGENTA;
+
+/// Spacing of the first pixels dispatched when `coarse_to_fine` is used
+pub const COARSE_TO_FINE_STEP: u32 = 8;
+
+/// Coordinates of all pixels of a canvas, each one exactly once.
+///
+/// Pixels are given row by row, or when `coarse_to_fine` is set, by successive grids
+/// of halving spacing, starting at `COARSE_TO_FINE_STEP`, and skipping the pixels
+/// already given by a coarser grid.
+pub fn pixel_order(width: u32, height: u32, coarse_to_fine: bool) -> Vec<(u32, u32)> {
+    let mut order = Vec::with_capacity((width * height) as usize);
+    if !coarse_to_fine {
+        for y in 0..height {
+            for x in 0..width {
+                order.push((x, y));
+            }
+        }
+        return order;
+    }
+    let mut step = COARSE_TO_FINE_STEP;
+    let mut previous_step = None;
+    while step > 0 {
+        for y in (0..height).step_by(step as usize) {
+            for x in (0..width).step_by(step as usize) {
+                match previous_step {
+                    Some(coarse) if x % coarse == 0 && y % coarse == 0 => {}
+                    _ => order.push((x, y)),
+                }
+            }
+        }
+        previous_step = Some(step);
+        step /= 2;
+    }
+    order
+}
+
+impl RenderConfiguration {
+    /// Render strategy for a pixel, a single ray outside of the high quality region
+    fn strategy_at(&self, x: u32, y: u32) -> &dyn AnyPixelRenderStrategy {
+        match self.high_quality_region {
+            Some(region) if !region.contains(x, y) => &StandardRenderStrategy,
+            _ => &*self.render_strategy,
+        }
+    }
+
+    /// Scene changes depending on the rendering options, before any ray is launched
+    fn prepare_scene(&self, scene: &mut Scene) {
+        if self.pixel_aspect_ratio != 1.0 {
+            let camera =
+                std::mem::replace(&mut scene.camera, Box::new(OrthogonalCamera::default()));
+            scene.camera = self.prepare_camera(camera);
+        }
+        scene.apply_clay_render();
+        if let Some(min_pixels) = self.small_object_culling {
+            let culled = scene.cull_small_objects(self.canvas_width, min_pixels);
+            debug!("render: {} objects culled as smaller than a pixel", culled);
+        }
+        if scene.config.caustics.is_some() && scene.config.photon_map.is_none() {
+            scene.build_photon_map();
+        }
+    }
+
+    /// Camera seeing the canvas, cropped according to the pixel aspect ratio
+    fn prepare_camera(&self, camera: Box<dyn RayEmitter>) -> Box<dyn RayEmitter> {
+        if self.pixel_aspect_ratio == 1.0 {
+            return camera;
+        }
+        Box::new(CroppedCamera::for_pixel_aspect_ratio(
+            camera,
+            self.canvas_width,
+            self.canvas_height,
+            self.pixel_aspect_ratio,
+        ))
+    }
+
+    /// Light received by a pixel, scaled by the exposure
+    fn expose(&self, radiance: Radiance) -> Radiance {
+        self.exposure.exp2() * radiance
+    }
+
+    /// Displayable color of the light received by a pixel
+    fn finalize_color(&self, x: u32, y: u32, radiance: Radiance) -> Color {
+        // Exposure scales the light before any clamp, so that highlights brighter
+        // than white come back into range when the exposure is lowered
+        self.display_color(x, y, self.expose(radiance))
+    }
+
+    /// Displayable color of light which is already exposed
+    fn display_color(&self, x: u32, y: u32, radiance: Radiance) -> Color {
+        let radiance = self.tone_mapping.map(&radiance);
+        let color = if self.gamut_clamp {
+            // Out of gamut components are only known before the clamp
+            Color::gamut_mapped(radiance.red(), radiance.green(), radiance.blue())
+        } else {
+            radiance.to_color()
+        };
+        let is_valid = || {
+            [color.red(), color.green(), color.blue()]
+                .iter()
+                .all(|component| component.is_finite())
+        };
+        match self.nan_check {
+            NanCheck::Disabled => color,
+            _ if is_valid() => color,
+            NanCheck::DebugColor => {
+                warn!("Invalid color {:?} for pixel ({}, {})", color, x, y);
+                NAN_DEBUG_COLOR
+            }
+            NanCheck::Panic => panic!("Invalid color {:?} for pixel ({}, {})", color, x, y),
+        }
+    }
 }
 
 impl Default for RenderConfiguration {
@@ -57,6 +252,15 @@ impl Default for RenderConfiguration {
             canvas_width: 1024,
             canvas_height: 576,
             render_strategy: Box::new(StandardRenderStrategy),
+            exposure: 0.0,
+            tone_mapping: ToneMapping::Clamp,
+            gamut_clamp: false,
+            small_object_culling: None,
+            pixel_aspect_ratio: 1.0,
+            high_quality_region: None,
+            nan_check: NanCheck::Disabled,
+            coarse_to_fine: false,
+            transfer_function: TransferFunction::Srgb,
         }
     }
 }
@@ -70,7 +274,7 @@ pub fn render_scene(
 }
 
 pub fn render_scene_with_finally<F>(
-    scene: Scene,
+    mut scene: Scene,
     config: RenderConfiguration,
     parallel: bool,
     mut finally: F,
@@ -86,6 +290,7 @@ where
     if scene.lights.is_empty() {
         return Err(RaytracerError::NoLight);
     }
+    config.prepare_scene(&mut scene);
     info!("Rendering start...");
     let instant_start = Instant::now();
     let iter_end = move || {
@@ -106,6 +311,835 @@ where
     Ok(render_iter)
 }
 
+/// Render each lighting component in its own buffer, indexed by pass name.
+///
+/// Buffers are stored row by row. Only the exposure is applied, so that summing
+/// all passes of a pixel gives the light of the normal render, which
+/// [`composite_passes`] turns into its final colors.
+pub fn render_scene_passes(
+    mut scene: Scene,
+    config: RenderConfiguration,
+) -> Result<HashMap<&'static str, Vec<Radiance>>> {
+    if scene.lights.is_empty() {
+        return Err(RaytracerError::NoLight);
+    }
+    config.prepare_scene(&mut scene);
+    let pixel_count = (config.canvas_width * config.canvas_height) as usize;
+    let pixel_width = 1.0 / config.canvas_width as f64;
+    let pixel_height = 1.0 / config.canvas_height as f64;
+    let mut buffers: HashMap<&'static str, Vec<Radiance>> = HashMap::new();
+    for y in 0..config.canvas_height {
+        for x in 0..config.canvas_width {
+            let canvas_x = x as f64 / (config.canvas_width as f64);
+            let canvas_y = y as f64 / (config.canvas_height as f64);
+            let passes = config.strategy_at(x, y).render_pixel_passes(
+                &scene,
+                &*scene.camera,
+                canvas_x,
+                canvas_y,
+                pixel_width,
+                pixel_height,
+            )?;
+            for (name, radiance) in passes.named_passes().iter() {
+                buffers
+                    .entry(name)
+                    .or_insert_with(|| Vec::with_capacity(pixel_count))
+                    .push(config.expose(**radiance));
+            }
+        }
+    }
+    Ok(buffers)
+}
+
+/// Colors of the normal render from the buffers of [`render_scene_passes`].
+///
+/// Passes are summed before tone mapping and clamping, which only apply to the
+/// combined light.
+pub fn composite_passes(
+    passes: &HashMap<&'static str, Vec<Radiance>>,
+    config: &RenderConfiguration,
+) -> Vec<Color> {
+    let pixel_count = (config.canvas_width * config.canvas_height) as usize;
+    (0..pixel_count)
+        .map(|index| {
+            let radiance = passes
+                .values()
+                .fold(Radiance::default(), |acc, buffer| acc + buffer[index]);
+            let x = index as u32 % config.canvas_width;
+            let y = index as u32 / config.canvas_width;
+            config.display_color(x, y, radiance)
+        })
+        .collect()
+}
+
+/// Render the light sent back at each recursion depth in its own buffer, from a
+/// single ray launched at the center of each pixel.
+///
+/// Buffer 0 holds the shading of the surfaces seen directly, buffer 1 the light of
+/// their reflections and refractions, and so on up to the maximum light recursion.
+/// Buffers are stored row by row. The render strategy of the configuration is not
+/// used.
+pub fn render_scene_layers(
+    mut scene: Scene,
+    config: RenderConfiguration,
+) -> Result<Vec<Vec<Color>>> {
+    if scene.lights.is_empty() {
+        return Err(RaytracerError::NoLight);
+    }
+    config.prepare_scene(&mut scene);
+    let pixel_count = (config.canvas_width * config.canvas_height) as usize;
+    let layer_count = scene.config.maximum_light_recursion as usize + 1;
+    let mut buffers = vec![Vec::with_capacity(pixel_count); layer_count];
+    let pixel_width = 1.0 / config.canvas_width as f64;
+    let pixel_height = 1.0 / config.canvas_height as f64;
+    for y in 0..config.canvas_height {
+        for x in 0..config.canvas_width {
+            let canvas_x = x as f64 / (config.canvas_width as f64);
+            let canvas_y = y as f64 / (config.canvas_height as f64);
+            let camera_ray = scene
+                .camera
+                .generate_ray(pixel_width / 2.0 + canvas_x, pixel_height / 2.0 + canvas_y);
+            let layers = launch_ray_layers(&camera_ray, &scene)?;
+            for (buffer, color) in buffers.iter_mut().zip(layers) {
+                buffer.push(config.finalize_color(x, y, color));
+            }
+        }
+    }
+    Ok(buffers)
+}
+
+/// Kind of image which can be produced by `render_scene_multi`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum OutputKind {
+    /// Final color
+    Beauty,
+    /// Distance from the camera to the nearest object
+    Depth,
+    /// Normalized surface normal of the nearest object
+    Normals,
+    /// Index of the nearest object in the scene
+    ObjectIds,
+    /// Darkness of the shadows cast onto shadow catchers
+    ShadowAlpha,
+}
+
+/// Images produced by `render_scene_multi`, row by row.
+///
+/// Outputs which were not requested are `None`. Pixels where no object is
+/// seen have no depth, normal or object index.
+#[derive(Debug, Default)]
+pub struct RenderOutputs {
+    pub beauty: Option<Vec<Color>>,
+    pub depth: Option<Vec<Option<f64>>>,
+    pub normals: Option<Vec<Option<Vec3>>>,
+    pub object_ids: Option<Vec<Option<usize>>>,
+    /// From 0, where no shadow catcher is seen or where it is lit, to 1, where it
+    /// is fully in shadow
+    pub shadow_alpha: Option<Vec<f64>>,
+}
+
+/// Sequential rendering of several outputs, from a single ray launched at the center
+/// of each pixel. The render strategy of the configuration is not used.
+pub fn render_scene_multi(
+    mut scene: Scene,
+    config: RenderConfiguration,
+    outputs: &[OutputKind],
+) -> Result<RenderOutputs> {
+    if scene.lights.is_empty() {
+        return Err(RaytracerError::NoLight);
+    }
+    config.prepare_scene(&mut scene);
+    let pixel_count = (config.canvas_width * config.canvas_height) as usize;
+    let is_requested = |kind| outputs.contains(&kind);
+    let mut render_outputs = RenderOutputs {
+        beauty: is_requested(OutputKind::Beauty).then(|| Vec::with_capacity(pixel_count)),
+        depth: is_requested(OutputKind::Depth).then(|| Vec::with_capacity(pixel_count)),
+        normals: is_requested(OutputKind::Normals).then(|| Vec::with_capacity(pixel_count)),
+        object_ids: is_requested(OutputKind::ObjectIds).then(|| Vec::with_capacity(pixel_count)),
+        shadow_alpha: is_requested(OutputKind::ShadowAlpha)
+            .then(|| Vec::with_capacity(pixel_count)),
+    };
+    let pixel_width = 1.0 / config.canvas_width as f64;
+    let pixel_height = 1.0 / config.canvas_height as f64;
+    for y in 0..config.canvas_height {
+        for x in 0..config.canvas_width {
+            let canvas_x = x as f64 / (config.canvas_width as f64);
+            let canvas_y = y as f64 / (config.canvas_height as f64);
+            let camera_ray = scene
+                .camera
+                .generate_ray(pixel_width / 2.0 + canvas_x, pixel_height / 2.0 + canvas_y);
+            let primary = launch_primary_ray(&camera_ray, &scene)?;
+            if let Some(beauty) = &mut render_outputs.beauty {
+                beauty.push(config.finalize_color(x, y, primary.color));
+            }
+            if let Some(depth) = &mut render_outputs.depth {
+                depth.push(primary.depth);
+            }
+            if let Some(normals) = &mut render_outputs.normals {
+                normals.push(primary.normal);
+            }
+            if let Some(object_ids) = &mut render_outputs.object_ids {
+                object_ids.push(primary.object_index);
+            }
+            if let Some(shadow_alpha) = &mut render_outputs.shadow_alpha {
+                shadow_alpha.push(primary.shadow_alpha);
+            }
+        }
+    }
+    Ok(render_outputs)
+}
+
+/// Sequential rendering, where each pixel also carries metadata
+pub fn render_scene_data(
+    mut scene: Scene,
+    config: RenderConfiguration,
+) -> Result<impl Iterator<Item = Result<PixelData>>> {
+    if scene.lights.is_empty() {
+        return Err(RaytracerError::NoLight);
+    }
+    config.prepare_scene(&mut scene);
+    let pixel_width = 1.0 / config.canvas_width as f64;
+    let pixel_height = 1.0 / config.canvas_height as f64;
+    let (mut x, mut y) = (0, 0);
+    let render_iter = from_fn(move || {
+        if y >= config.canvas_height {
+            return None;
+        }
+        let canvas_x = x as f64 / (config.canvas_width as f64);
+        let canvas_y = y as f64 / (config.canvas_height as f64);
+        let strategy = config.strategy_at(x, y);
+        let result = strategy
+            .render_pixel(
+                &scene,
+                &*scene.camera,
+                canvas_x,
+                canvas_y,
+                pixel_width,
+                pixel_height,
+            )
+            .map(|color| PixelData {
+                x,
+                y,
+                color: config.finalize_color(x, y, color),
+                metadata: strategy.render_pixel_metadata(
+                    &scene,
+                    &*scene.camera,
+                    canvas_x,
+                    canvas_y,
+                    pixel_width,
+                    pixel_height,
+                ),
+            });
+        x += 1;
+        if x >= config.canvas_width {
+            x = 0;
+            y += 1;
+        }
+        Some(result)
+    });
+    Ok(render_iter)
+}
+
+/// Counters of a render
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct RenderStats {
+    pub pixels: u64,
+    /// Rays launched from the camera, secondary and shadow rays are not counted
+    pub camera_rays: u64,
+    /// Camera rays hitting an object
+    pub hits: u64,
+}
+
+/// Description of a finished render, to be written along its output so that renders
+/// can be compared
+#[derive(Debug, Clone, Serialize)]
+pub struct RenderReport {
+    pub width: u32,
+    pub height: u32,
+    pub strategy: String,
+    /// Rays per pixel requested from the strategy
+    pub samples: u32,
+    pub seed: Option<u64>,
+    pub duration_seconds: f64,
+    pub stats: RenderStats,
+}
+
+impl RenderReport {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Render report has only plain fields")
+    }
+}
+
+/// Camera counting the rays it generates
+#[derive(Debug)]
+struct CountingCamera {
+    camera: Box<dyn RayEmitter>,
+    rays: Arc<AtomicU64>,
+}
+
+impl RayEmitter for CountingCamera {
+    fn width(&self) -> f64 {
+        self.camera.width()
+    }
+
+    fn height(&self) -> f64 {
+        self.camera.height()
+    }
+
+    fn generate_ray(&self, canvas_x: UnitInterval, canvas_y: UnitInterval) -> Ray {
+        self.rays.fetch_add(1, Ordering::Relaxed);
+        self.camera.generate_ray(canvas_x, canvas_y)
+    }
+
+    fn pixel_footprint(&self, point: Vec3, canvas_width: u32) -> f64 {
+        self.camera.pixel_footprint(point, canvas_width)
+    }
+
+    fn reframed(&self, eye: Vec3, look_at: Vec3) -> Option<Box<dyn RayEmitter>> {
+        self.camera.reframed(eye, look_at)
+    }
+
+    fn basis(&self) -> Option<CameraBasis> {
+        self.camera.basis()
+    }
+}
+
+/// Render the image like `render_scene_with_finally`, and call `on_report` with the
+/// report of the render once the last pixel is given. An error of `on_report` is
+/// given as the last item.
+///
+/// The camera rays, and those of them hitting an object, are counted as they are
+/// launched by the render strategy.
+pub fn render_scene_with_report<F>(
+    mut scene: Scene,
+    config: RenderConfiguration,
+    parallel: bool,
+    strategy: &str,
+    samples: u32,
+    mut on_report: F,
+) -> Result<impl Iterator<Item = Result<Pixel>>>
+where
+    F: FnMut(RenderReport) -> Result<()>,
+{
+    if scene.lights.is_empty() {
+        return Err(RaytracerError::NoLight);
+    }
+    config.prepare_scene(&mut scene);
+    let pixel_count = config.canvas_width as u64 * config.canvas_height as u64;
+    let mut report = RenderReport {
+        width: config.canvas_width,
+        height: config.canvas_height,
+        strategy: strategy.to_string(),
+        samples,
+        seed: config.render_strategy.seed(),
+        duration_seconds: 0.0,
+        stats: RenderStats {
+            pixels: pixel_count,
+            camera_rays: 0,
+            hits: 0,
+        },
+    };
+    let camera_rays = Arc::new(AtomicU64::new(0));
+    let hits = Arc::new(AtomicU64::new(0));
+    scene.config.camera_hits = Some(Arc::clone(&hits));
+    let camera = std::mem::replace(&mut scene.camera, Box::new(OrthogonalCamera::default()));
+    scene.camera = Box::new(CountingCamera {
+        camera,
+        rays: Arc::clone(&camera_rays),
+    });
+    let instant_start = Instant::now();
+    let render_iter: Box<dyn Iterator<Item = Result<Pixel>>> = if parallel {
+        Box::new(renderer_parallel(scene, config))
+    } else {
+        Box::new(renderer_sequential(scene, config))
+    };
+    let mut reported = false;
+    let report_end = from_fn(move || {
+        if reported {
+            return None;
+        }
+        reported = true;
+        report.duration_seconds = instant_start.elapsed().as_secs_f64();
+        report.stats.camera_rays = camera_rays.load(Ordering::Relaxed);
+        report.stats.hits = hits.load(Ordering::Relaxed);
+        on_report(report.clone()).err().map(Err)
+    });
+    Ok(render_iter.chain(report_end).fuse())
+}
+
+/// Render the image row by row, from top to bottom, calling `on_row` with the
+/// index and the colors of each completed row.
+///
+/// Pixels of a row are computed in parallel, but only one row is kept in memory,
+/// so that large images can be written as they are rendered.
+pub fn render_by_rows<F>(mut scene: Scene, config: RenderConfiguration, on_row: F) -> Result<()>
+where
+    F: FnMut(u32, &[Color]),
+{
+    if scene.lights.is_empty() {
+        return Err(RaytracerError::NoLight);
+    }
+    config.prepare_scene(&mut scene);
+    render_rows(&scene, &*scene.camera, &config, on_row)
+}
+
+/// Order in which `render_tiles` gives the tiles of the canvas
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TileOrder {
+    /// Row by row, from the top left tile
+    Scanline,
+    /// From the center tile, turning around it
+    Spiral,
+    /// Along a Hilbert curve, so that a tile is usually next to the previous one
+    Hilbert,
+}
+
+/// Rendered rectangle of the canvas, from its top-left corner
+#[derive(Debug)]
+pub struct Tile {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Colors of the tile row by row
+    pub pixels: Vec<Color>,
+}
+
+/// Coordinates, as column and row, of the tiles of a grid in the given order
+pub fn tile_order(columns: u32, rows: u32, order: TileOrder) -> Vec<(u32, u32)> {
+    let mut tiles: Vec<(u32, u32)> = (0..rows)
+        .flat_map(|row| (0..columns).map(move |column| (column, row)))
+        .collect();
+    match order {
+        TileOrder::Scanline => {}
+        TileOrder::Spiral => {
+            let (center_x, center_y) = ((columns / 2) as i64, (rows / 2) as i64);
+            let ring_and_angle = |&(column, row): &(u32, u32)| {
+                let (dx, dy) = (column as i64 - center_x, row as i64 - center_y);
+                (dx.abs().max(dy.abs()), (dy as f64).atan2(dx as f64))
+            };
+            tiles.sort_by(|a, b| ring_and_angle(a).partial_cmp(&ring_and_angle(b)).unwrap());
+        }
+        TileOrder::Hilbert => {
+            let side = columns.max(rows).next_power_of_two();
+            tiles.sort_by_key(|&(column, row)| hilbert_index(side, column, row));
+        }
+    }
+    tiles
+}
+
+/// Distance along the Hilbert curve filling a square of `side` cells, a power of 2
+// https://en.wikipedia.org/wiki/Hilbert_curve#Applications_and_mapping_algorithms
+fn hilbert_index(side: u32, mut x: u32, mut y: u32) -> u64 {
+    let mut index = 0;
+    let mut s = side / 2;
+    while s > 0 {
+        let rx = (x & s > 0) as u32;
+        let ry = (y & s > 0) as u32;
+        index += s as u64 * s as u64 * ((3 * rx) ^ ry) as u64;
+        if ry == 0 {
+            if rx == 1 {
+                x = side - 1 - x;
+                y = side - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        s /= 2;
+    }
+    index
+}
+
+/// Render the canvas by square tiles of `tile_size` pixels, given in `order`.
+///
+/// Tiles on the right and bottom edges are smaller when the canvas size is not a
+/// multiple of `tile_size`. Pixels of a tile are computed in parallel.
+pub fn render_tiles(
+    mut scene: Scene,
+    config: RenderConfiguration,
+    tile_size: u32,
+    order: TileOrder,
+) -> Result<impl Iterator<Item = Result<Tile>>> {
+    if scene.lights.is_empty() {
+        return Err(RaytracerError::NoLight);
+    }
+    config.prepare_scene(&mut scene);
+    let tile_size = tile_size.max(1);
+    let columns = config.canvas_width.div_ceil(tile_size);
+    let rows = config.canvas_height.div_ceil(tile_size);
+    let pixel_width = 1.0 / config.canvas_width as f64;
+    let pixel_height = 1.0 / config.canvas_height as f64;
+    let render_iter = tile_order(columns, rows, order)
+        .into_iter()
+        .map(move |(column, row)| {
+            let (x, y) = (column * tile_size, row * tile_size);
+            let width = tile_size.min(config.canvas_width - x);
+            let height = tile_size.min(config.canvas_height - y);
+            let pixels = (0..width * height)
+                .into_par_iter()
+                .map(|index| {
+                    let (pixel_x, pixel_y) = (x + index % width, y + index / width);
+                    let canvas_x = pixel_x as f64 / (config.canvas_width as f64);
+                    let canvas_y = pixel_y as f64 / (config.canvas_height as f64);
+                    config
+                        .strategy_at(pixel_x, pixel_y)
+                        .render_pixel(
+                            &scene,
+                            &*scene.camera,
+                            canvas_x,
+                            canvas_y,
+                            pixel_width,
+                            pixel_height,
+                        )
+                        .map(|color| config.finalize_color(pixel_x, pixel_y, color))
+                })
+                .collect::<Result<Vec<Color>>>()?;
+            Ok(Tile {
+                x,
+                y,
+                width,
+                height,
+                pixels,
+            })
+        });
+    Ok(render_iter)
+}
+
+/// Render the canvas by tiles, as `render_tiles`, writing each tile in a binary PPM
+/// image at `path` once it is computed.
+///
+/// The file is filled in place, so that only one tile is kept in memory, even for
+/// images too large to be held whole. Colors are encoded with the transfer function
+/// of the configuration.
+pub fn render_tiles_to_file<P: AsRef<Path>>(
+    scene: Scene,
+    config: RenderConfiguration,
+    tile_size: u32,
+    order: TileOrder,
+    path: P,
+) -> Result<()> {
+    let path = path.as_ref();
+    let io_error =
+        |e: std::io::Error| RaytracerError::WriteError(format!("{}: {}", path.display(), e));
+    let (width, height) = (config.canvas_width, config.canvas_height);
+    let transfer_function = config.transfer_function;
+    let header = format!("P6\n{} {}\n255\n", width, height);
+    let mut file = File::create(path).map_err(io_error)?;
+    file.write_all(header.as_bytes()).map_err(io_error)?;
+    file.set_len(header.len() as u64 + 3 * width as u64 * height as u64)
+        .map_err(io_error)?;
+    for tile in render_tiles(scene, config, tile_size, order)? {
+        let tile = tile?;
+        let mut row_bytes = Vec::with_capacity(3 * tile.width as usize);
+        for (row, colors) in tile.pixels.chunks(tile.width as usize).enumerate() {
+            row_bytes.clear();
+            for color in colors {
+                row_bytes.extend_from_slice(&transfer_function.to_rgb8(color));
+            }
+            let offset = 3 * ((tile.y as u64 + row as u64) * width as u64 + tile.x as u64);
+            file.seek(SeekFrom::Start(header.len() as u64 + offset))
+                .map_err(io_error)?;
+            file.write_all(&row_bytes).map_err(io_error)?;
+        }
+    }
+    Ok(())
+}
+
+/// Render a frame of an accelerated scene, seen from `camera`, or from the camera of
+/// the scene if `None`. Colors are returned row by row.
+///
+/// The objects of the scene are not prepared again, so a configuration with small
+/// object culling, which depends on the camera, is an error.
+pub fn render_accelerated(
+    accel: &AcceleratedScene,
+    camera: Option<Box<dyn RayEmitter>>,
+    config: &RenderConfiguration,
+) -> Result<Vec<Color>> {
+    if config.small_object_culling.is_some() {
+        return Err(RaytracerError::UnsupportedConfiguration(
+            "small object culling cannot be applied to an accelerated scene".to_string(),
+        ));
+    }
+    let scene = accel.scene();
+    if scene.lights.is_empty() {
+        return Err(RaytracerError::NoLight);
+    }
+    let camera = config.prepare_camera(camera.unwrap_or_else(|| accel.camera()));
+    let mut colors = Vec::with_capacity((config.canvas_width * config.canvas_height) as usize);
+    render_rows(scene, &*camera, config, |_, row| {
+        colors.extend_from_slice(row)
+    })?;
+    Ok(colors)
+}
+
+/// Render the rows of `scene` seen from `camera`, which may differ from the camera
+/// of the scene
+fn render_rows<F>(
+    scene: &Scene,
+    camera: &dyn RayEmitter,
+    config: &RenderConfiguration,
+    mut on_row: F,
+) -> Result<()>
+where
+    F: FnMut(u32, &[Color]),
+{
+    let pixel_width = 1.0 / config.canvas_width as f64;
+    let pixel_height = 1.0 / config.canvas_height as f64;
+    for y in 0..config.canvas_height {
+        let canvas_y = y as f64 / (config.canvas_height as f64);
+        let row = (0..config.canvas_width)
+            .into_par_iter()
+            .map(|x| {
+                let canvas_x = x as f64 / (config.canvas_width as f64);
+                config
+                    .strategy_at(x, y)
+                    .render_pixel(scene, camera, canvas_x, canvas_y, pixel_width, pixel_height)
+                    .map(|color| config.finalize_color(x, y, color))
+            })
+            .collect::<Result<Vec<Color>>>()?;
+        on_row(y, &row);
+    }
+    Ok(())
+}
+
+/// Shading details of a surface hit by the primary ray of a traced pixel
+#[derive(Debug)]
+pub struct PixelTraceHit {
+    /// Index of the object in the scene
+    pub object_index: usize,
+    pub point: Vec3,
+    pub normal: Vec3,
+    /// Color received from each light, black when the light is hidden
+    pub light_contributions: Vec<(usize, Color)>,
+    pub reflection_ray: Option<Ray>,
+    pub refraction_ray: Option<Ray>,
+}
+
+/// Shading decisions for a single pixel, as produced by `trace_pixel`
+#[derive(Debug)]
+pub struct PixelTrace {
+    pub primary_ray: Ray,
+    /// `None` when no object is seen
+    pub hit: Option<PixelTraceHit>,
+    pub passes: RenderPasses,
+    pub color: Color,
+}
+
+impl PixelTrace {
+    /// Human readable report of the trace, for troubleshooting
+    pub fn describe(&self) -> String {
+        let mut report = String::new();
+        // Writing into a String cannot fail
+        writeln!(report, "Primary ray: {:?}", self.primary_ray).unwrap();
+        match &self.hit {
+            Some(hit) => {
+                writeln!(report, "Hit object: {}", hit.object_index).unwrap();
+                writeln!(report, "  Point: {:?}", hit.point).unwrap();
+                writeln!(report, "  Normal: {:?}", hit.normal).unwrap();
+                for (light_index, color) in &hit.light_contributions {
+                    writeln!(report, "  Light {}: {:?}", light_index, color).unwrap();
+                }
+                if let Some(ray) = &hit.reflection_ray {
+                    writeln!(report, "  Reflection ray: {:?}", ray).unwrap();
+                }
+                if let Some(ray) = &hit.refraction_ray {
+                    writeln!(report, "  Refraction ray: {:?}", ray).unwrap();
+                }
+            }
+            None => writeln!(report, "Hit object: none").unwrap(),
+        }
+        for (name, color) in self.passes.named_passes().iter() {
+            writeln!(report, "Pass {}: {:?}", name, color).unwrap();
+        }
+        writeln!(report, "Final color: {:?}", self.color).unwrap();
+        report
+    }
+}
+
+/// Estimate the duration of a sequential render, from the time taken by a random
+/// `sample_fraction` of the pixels, at least one, rendered with the strategies of the
+/// configuration. Scene preparation, like building a photon map, is not measured.
+pub fn estimate_render_time(
+    mut scene: Scene,
+    config: RenderConfiguration,
+    sample_fraction: f64,
+) -> Result<Duration> {
+    if scene.lights.is_empty() {
+        return Err(RaytracerError::NoLight);
+    }
+    config.prepare_scene(&mut scene);
+    let pixel_count = config.canvas_width as u64 * config.canvas_height as u64;
+    if pixel_count == 0 {
+        return Ok(Duration::from_secs(0));
+    }
+    let sample_count = ((pixel_count as f64 * sample_fraction).ceil() as u64).clamp(1, pixel_count);
+    let pixel_width = 1.0 / config.canvas_width as f64;
+    let pixel_height = 1.0 / config.canvas_height as f64;
+    let mut rng = rand::thread_rng();
+    let instant_start = Instant::now();
+    for _ in 0..sample_count {
+        let x = rng.gen_range(0, config.canvas_width);
+        let y = rng.gen_range(0, config.canvas_height);
+        config.strategy_at(x, y).render_pixel(
+            &scene,
+            &*scene.camera,
+            x as f64 * pixel_width,
+            y as f64 * pixel_height,
+            pixel_width,
+            pixel_height,
+        )?;
+    }
+    let elapsed = instant_start.elapsed();
+    Ok(elapsed.mul_f64(pixel_count as f64 / sample_count as f64))
+}
+
+/// Shade a single pixel from a ray launched at its center, recording the decisions
+/// made along the way. The render strategy of the configuration is not used.
+pub fn trace_pixel(
+    mut scene: Scene,
+    config: RenderConfiguration,
+    x: u32,
+    y: u32,
+) -> Result<PixelTrace> {
+    if scene.lights.is_empty() {
+        return Err(RaytracerError::NoLight);
+    }
+    if x >= config.canvas_width || y >= config.canvas_height {
+        return Err(RaytracerError::PixelOutOfCanvas(x, y));
+    }
+    config.prepare_scene(&mut scene);
+    let pixel_width = 1.0 / config.canvas_width as f64;
+    let pixel_height = 1.0 / config.canvas_height as f64;
+    let primary_ray = scene.camera.generate_ray(
+        (x as f64 + 0.5) * pixel_width,
+        (y as f64 + 0.5) * pixel_height,
+    );
+    let hit = match search_visible_collision(&primary_ray, &scene.objects, RayKind::Camera) {
+        Some(context) => {
+            let normal = context
+                .object
+                .normal_at(context.collision_point)
+                .ok_or(RaytracerError::NormalNotFound(context.array_index))?
+                .normalize();
+            let effects = context.object.effects();
+            let is_fresnel =
+                matches!(&effects.transparency, Some(transparency) if transparency.fresnel);
+            let reflection_ray =
+                if effects.mirror.is_some() || effects.thin_film.is_some() || is_fresnel {
+                    Some(reflected_ray(&primary_ray, &context)?)
+                } else {
+                    None
+                };
+            let refraction_ray = match &effects.transparency {
+                Some(transparency) => {
+                    transmitted_ray(&primary_ray, &scene, &context, transparency)?
+                }
+                None => None,
+            };
+            Some(PixelTraceHit {
+                object_index: context.array_index,
+                point: context.collision_point,
+                normal,
+                light_contributions: scene.light_contributions(context.collision_point, normal),
+                reflection_ray,
+                refraction_ray,
+            })
+        }
+        None => None,
+    };
+    let passes = launch_ray_passes(&primary_ray, &scene, 0)?;
+    let color = config.finalize_color(x, y, passes.combined());
+    Ok(PixelTrace {
+        primary_ray,
+        hit,
+        passes,
+        color,
+    })
+}
+
+/// Complete image, as produced by the progressive renderer
+#[derive(Debug)]
+pub struct Frame {
+    pub width: u32,
+    pub height: u32,
+    /// Colors stored row by row
+    pub pixels: Vec<Color>,
+    /// Number of rays accumulated for each pixel
+    pub samples_per_pixel: u32,
+}
+
+/// Endless iterator of whole frames with increasing quality.
+///
+/// The first frame uses one ray per pixel, then each frame uses four times more rays
+/// than the previous one, reusing the rays already computed. The render strategy of
+/// the configuration is not used.
+pub fn render_progressive(
+    mut scene: Scene,
+    config: RenderConfiguration,
+) -> Result<impl Iterator<Item = Result<Frame>>> {
+    if scene.lights.is_empty() {
+        return Err(RaytracerError::NoLight);
+    }
+    config.prepare_scene(&mut scene);
+    let width = config.canvas_width;
+    let height = config.canvas_height;
+    let mut accumulators = vec![Radiance::default(); (width * height) as usize];
+    let mut samples_per_pixel = 0;
+    let mut failed = false;
+    let frame_iter = from_fn(move || {
+        if failed {
+            return None;
+        }
+        let target_samples = if samples_per_pixel == 0 {
+            1
+        } else {
+            4 * samples_per_pixel
+        };
+        let first_pass = samples_per_pixel == 0;
+        let new_samples = target_samples - samples_per_pixel;
+        let scene = &scene;
+        let pass_result = accumulators.par_iter_mut().enumerate().try_for_each(
+            |(index, accumulator)| -> Result<()> {
+                let mut rng = rand::thread_rng();
+                let x = (index as u32 % width) as f64;
+                let y = (index as u32 / width) as f64;
+                for _ in 0..new_samples {
+                    let (offset_x, offset_y) = if first_pass {
+                        (0.5, 0.5)
+                    } else {
+                        (rng.gen::<f64>(), rng.gen::<f64>())
+                    };
+                    let camera_ray = scene.camera.generate_ray(
+                        (x + offset_x) / width as f64,
+                        (y + offset_y) / height as f64,
+                    );
+                    *accumulator += launch_ray(&camera_ray, scene, 0)?;
+                }
+                Ok(())
+            },
+        );
+        if let Err(err) = pass_result {
+            failed = true;
+            return Some(Err(err));
+        }
+        samples_per_pixel = target_samples;
+        let pixels = accumulators
+            .iter()
+            .enumerate()
+            .map(|(index, accumulator)| {
+                let (x, y) = (index as u32 % width, index as u32 / width);
+                config.finalize_color(x, y, accumulator.mean(samples_per_pixel))
+            })
+            .collect();
+        Some(Ok(Frame {
+            width,
+            height,
+            pixels,
+            samples_per_pixel,
+        }))
+    });
+    Ok(frame_iter)
+}
+
 pub fn renderer_parallel(
     scene: Scene,
     config: RenderConfiguration,
@@ -118,29 +1152,34 @@ pub fn renderer_parallel(
         let pixel_width = 1.0 / config.canvas_width as f64;
         let pixel_height = 1.0 / config.canvas_height as f64;
 
-        rayon::scope(move |s| {
-            for y in 0..config.canvas_height {
-                for x in 0..config.canvas_width {
-                    let tx = tx.clone();
-                    s.spawn(move |_| {
-                        let canvas_x = x as f64 / (config.canvas_width as f64);
-                        let canvas_y = y as f64 / (config.canvas_height as f64);
-                        let res_color = config.render_strategy.render_pixel(
-                            &scene,
-                            canvas_x,
-                            canvas_y,
-                            pixel_width,
-                            pixel_height,
-                        );
-                        let pixel = match res_color {
-                            Ok(color) => Ok(Pixel::new(x, y, color)),
-                            Err(err) => Err(err),
-                        };
-                        tx.send(pixel).unwrap_or_else(|err| {
-                            trace!("Error: {}", err);
-                        });
+        let order = pixel_order(
+            config.canvas_width,
+            config.canvas_height,
+            config.coarse_to_fine,
+        );
+
+        rayon::scope_fifo(move |s| {
+            for (x, y) in order {
+                let tx = tx.clone();
+                s.spawn_fifo(move |_| {
+                    let canvas_x = x as f64 / (config.canvas_width as f64);
+                    let canvas_y = y as f64 / (config.canvas_height as f64);
+                    let res_color = config.strategy_at(x, y).render_pixel(
+                        &scene,
+                        &*scene.camera,
+                        canvas_x,
+                        canvas_y,
+                        pixel_width,
+                        pixel_height,
+                    );
+                    let pixel = match res_color {
+                        Ok(color) => Ok(Pixel::new(x, y, config.finalize_color(x, y, color))),
+                        Err(err) => Err(err),
+                    };
+                    tx.send(pixel).unwrap_or_else(|err| {
+                        trace!("Error: {}", err);
                     });
-                }
+                });
             }
         });
     });
@@ -214,16 +1253,17 @@ impl Iterator for AreaRenderIterator {
         let y = self.area_y_current;
         let canvas_x = (self.area_x_current as f64) / (self.config.canvas_width as f64);
         let canvas_y = (self.area_y_current as f64) / (self.config.canvas_height as f64);
-        let render_strategy = &*self.config.render_strategy;
+        let render_strategy = self.config.strategy_at(x, y);
         let result_color = render_strategy.render_pixel(
             &self.scene,
+            &*self.scene.camera,
             canvas_x,
             canvas_y,
             self.pixel_width,
             self.pixel_height,
         );
         let color = match result_color {
-            Ok(val) => val,
+            Ok(val) => self.config.finalize_color(x, y, val),
             Err(val) => return Some(Err(val)),
         };
         self.area_x_current += 1;
@@ -32,6 +32,14 @@ pub enum RaytracerError {
     NormalNotFound(usize),
     ParsingError(String),
     NoLight,
+    ImageError(String),
+    IoError(String),
+    /// File which cannot be created or written
+    WriteError(String),
+    /// Pixel coordinates outside of the canvas
+    PixelOutOfCanvas(u32, u32),
+    /// Render configuration which cannot be used with the given scene or function
+    UnsupportedConfiguration(String),
 }
 
 impl Display for RaytracerError {
@@ -44,6 +52,19 @@ impl Display for RaytracerError {
             RaytracerError::ParsingError(val) => {
                 write!(formatter, "Error while parsing scene: {}", val)
             }
+            RaytracerError::ImageError(val) => {
+                write!(formatter, "Error while loading image: {}", val)
+            }
+            RaytracerError::IoError(val) => write!(formatter, "Error while reading file: {}", val),
+            RaytracerError::WriteError(val) => {
+                write!(formatter, "Error while writing file: {}", val)
+            }
+            RaytracerError::PixelOutOfCanvas(x, y) => {
+                write!(formatter, "Pixel ({}, {}) is outside of the canvas", x, y)
+            }
+            RaytracerError::UnsupportedConfiguration(val) => {
+                write!(formatter, "Unsupported render configuration: {}", val)
+            }
         }
     }
 }
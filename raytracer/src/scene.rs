@@ -22,16 +22,30 @@ OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 SOFTWARE.
 */
 
+use crate::animation::Animation;
+use crate::cameras::{CameraBasis, PerspectiveCamera};
 use crate::colors::Color;
+use crate::environments::Environment;
 use crate::lights::AnyLightObject;
 use crate::parser;
-use crate::primitives::{Ray, Shape};
+use crate::photon::{CausticsSettings, PhotonMap};
+use crate::primitives::{BoundingBox, Ray, Shape};
+use crate::ray_algorithm::{
+    ray_encounter_obstacle, search_object_collision, visible_lights, RayKind,
+};
 use crate::result::{RaytracerError, Result};
-use crate::textures::{Texture, TextureEffects};
+use crate::textures::{PlainColorTexture, Texture, TextureEffects};
 use crate::vector::Vec3;
 use crate::UnitInterval;
+use log::warn;
 use serde::Deserialize;
+use std::fmt::Debug;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
 use std::str::FromStr;
+use std::sync::atomic::AtomicU64;
+use std::sync::Arc;
 
 #[derive(Debug, Deserialize)]
 #[serde(default)]
@@ -40,6 +54,70 @@ pub struct SceneConfiguration {
     pub world_refractive_index: f64,
     pub ambient_light: Option<Color>,
     pub maximum_light_recursion: u8,
+    /// If set, maximum number of reflection and refraction rays launched while
+    /// shading a pixel, all its samples included. Once it is spent, surfaces are
+    /// shaded without their reflection and refraction, which bounds the cost of a
+    /// pixel when rays split at each hit, like between glass and facing mirrors.
+    pub secondary_ray_budget: Option<u32>,
+    /// If set, no reflection or refraction ray is launched, only the direct and
+    /// ambient lighting of the first object hit are rendered
+    pub direct_only: bool,
+    /// If set, objects are shaded as a matte diffuse of this color, without their
+    /// texture, reflection, refraction or other effects, to examine shapes and lighting
+    pub clay_render: Option<Color>,
+    /// If set, light intensities are scaled down so that the sum of their peak
+    /// contributions does not exceed this value. Dimmer scenes are left unchanged
+    pub normalize_lights: Option<f64>,
+    /// If set, objects are sorted by centroid then by description instead of being kept
+    /// in declaration order. Only pixels where objects are at the same distance, or
+    /// which depend on the order of accumulated computations, are affected.
+    pub sort_objects: bool,
+    /// Distance, relative to the magnitude of the shaded point coordinates, by which
+    /// shadow rays start away from their surface. It is divided by the cosine between
+    /// the surface normal and the light direction, so that grazing lights, whose rays
+    /// stay close to the surface, are offset further.
+    pub shadow_bias: f64,
+    /// If set, a photon map is built before rendering to add caustics
+    pub caustics: Option<CausticsSettings>,
+    /// Caustic photons, built by `Scene::build_photon_map`
+    #[serde(skip)]
+    pub photon_map: Option<PhotonMap>,
+    /// Background replacing the world color, when set
+    #[serde(skip)]
+    pub environment: Option<Box<dyn Environment>>,
+    /// Keyframes applied by `Scene::set_time`
+    #[serde(skip)]
+    pub animation: Animation,
+    /// If set, counts the camera rays hitting an object, for render statistics
+    #[serde(skip)]
+    pub camera_hits: Option<Arc<AtomicU64>>,
+}
+
+impl SceneConfiguration {
+    /// Use a uniform background color, replacing any environment
+    pub fn with_background_color(self, color: Color) -> Self {
+        SceneConfiguration {
+            world_color: color,
+            environment: None,
+            ..self
+        }
+    }
+
+    /// Use an environment as background, instead of the world color
+    pub fn with_environment(self, environment: Box<dyn Environment>) -> Self {
+        SceneConfiguration {
+            environment: Some(environment),
+            ..self
+        }
+    }
+
+    /// Color seen by a ray which does not hit any object
+    pub fn background_color(&self, direction: Vec3) -> Color {
+        match &self.environment {
+            Some(environment) => environment.color_in_direction(direction),
+            None => self.world_color.clone(),
+        }
+    }
 }
 
 impl Default for SceneConfiguration {
@@ -49,20 +127,113 @@ impl Default for SceneConfiguration {
             world_refractive_index: 1.0,
             ambient_light: Some(Color::new(0.2, 0.2, 0.2)),
             maximum_light_recursion: 2,
+            secondary_ray_budget: None,
+            direct_only: false,
+            clay_render: None,
+            normalize_lights: None,
+            sort_objects: false,
+            shadow_bias: 1e-9,
+            caustics: None,
+            photon_map: None,
+            environment: None,
+            animation: Animation::default(),
+            camera_hits: None,
         }
     }
 }
 
+#[derive(Debug)]
 pub struct SceneObject {
     pub texture: Box<dyn Texture>,
     pub shape: Box<dyn Shape>,
     pub effects: TextureEffects,
+    /// A holdout object is rendered as the background, but still hides other
+    /// objects and casts shadows
+    pub holdout: bool,
+    /// A shadow catcher is rendered as the background, like a holdout object, and
+    /// only shows in the shadow alpha output, with the shadows cast onto it
+    pub shadow_catcher: bool,
+    /// Kinds of rays which can hit the object
+    pub visibility: RayVisibility,
+}
+
+/// Rays an object is visible to, so that it can for instance be hidden from the
+/// camera while still casting shadows and showing in reflections
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RayVisibility {
+    pub camera: bool,
+    pub reflections: bool,
+    pub refractions: bool,
+    /// Whether the object blocks the light of shadow rays
+    pub shadows: bool,
+}
+
+impl RayVisibility {
+    pub fn is_visible_to(&self, kind: RayKind) -> bool {
+        match kind {
+            RayKind::Camera => self.camera,
+            RayKind::Reflection => self.reflections,
+            RayKind::Refraction => self.refractions,
+        }
+    }
+}
+
+impl Default for RayVisibility {
+    fn default() -> Self {
+        RayVisibility {
+            camera: true,
+            reflections: true,
+            refractions: true,
+            shadows: true,
+        }
+    }
 }
 
+/// Texture coordinates used when a shape cannot map a point of its surface
+const FALLBACK_SURFACE_MAPPING: (UnitInterval, UnitInterval) = (0.5, 0.5);
+
 impl SceneObject {
+    /// Object without effects, visible to all rays, and neither a holdout nor a
+    /// shadow catcher
+    pub fn new(shape: Box<dyn Shape>, texture: Box<dyn Texture>) -> Self {
+        SceneObject {
+            texture,
+            shape,
+            effects: Default::default(),
+            holdout: false,
+            shadow_catcher: false,
+            visibility: Default::default(),
+        }
+    }
+
+    pub fn with_effects(self, effects: TextureEffects) -> Self {
+        SceneObject { effects, ..self }
+    }
+
+    pub fn with_holdout(self, holdout: bool) -> Self {
+        SceneObject { holdout, ..self }
+    }
+
+    pub fn with_shadow_catcher(self, shadow_catcher: bool) -> Self {
+        SceneObject {
+            shadow_catcher,
+            ..self
+        }
+    }
+
+    pub fn with_visibility(self, visibility: RayVisibility) -> Self {
+        SceneObject { visibility, ..self }
+    }
+
     pub fn color_at(&self, point: Vec3) -> Color {
-        let (u, v) = self.shape.surface_mapping_at(point).unwrap();
-        self.texture.color_at(u, v)
+        let (u, v) = self.shape.surface_mapping_at(point).unwrap_or_else(|| {
+            warn!(
+                "No surface mapping for point {:?} of shape {:?}, using fallback",
+                point, self.shape
+            );
+            FALLBACK_SURFACE_MAPPING
+        });
+        self.texture.color_at_point(point, u, v)
     }
 
     pub fn check_collision(&self, ray: &Ray) -> Option<Vec3> {
@@ -78,15 +249,29 @@ impl SceneObject {
     }
 }
 
-pub trait RayEmitter: Send + Sync {
+pub trait RayEmitter: Send + Sync + Debug {
     fn width(&self) -> f64;
     fn height(&self) -> f64;
     fn size_ratio(&self) -> f64 {
         self.width() / self.height()
     }
     fn generate_ray(&self, canvas_x: UnitInterval, canvas_y: UnitInterval) -> Ray;
+    /// Approximate size of a pixel at `point`, for a canvas of `canvas_width` pixels
+    fn pixel_footprint(&self, _point: Vec3, canvas_width: u32) -> f64 {
+        self.width() / canvas_width as f64
+    }
+    /// Same camera moved to `eye` and looking at `look_at`, `None` if the camera
+    /// cannot be moved this way
+    fn reframed(&self, _eye: Vec3, _look_at: Vec3) -> Option<Box<dyn RayEmitter>> {
+        None
+    }
+    /// Axes of the screen and viewing direction, `None` if the camera has no fixed basis
+    fn basis(&self) -> Option<CameraBasis> {
+        None
+    }
 }
 
+#[derive(Debug)]
 pub struct Scene {
     pub camera: Box<dyn RayEmitter>,
     pub lights: Vec<Box<dyn AnyLightObject>>,
@@ -94,6 +279,335 @@ pub struct Scene {
     pub config: SceneConfiguration,
 }
 
+/// Scene whose preprocessing is done once, to render several frames of a scene
+/// whose objects do not move, as with `renderer::render_accelerated`.
+///
+/// This is a prepared scene rather than an acceleration structure: the clay render
+/// and the photon map of caustics are applied when it is built, and the geometry is
+/// searched as in a normal render. The photon map is not rebuilt, so lights must not
+/// move between frames when caustics are enabled.
+///
+/// The scene is never modified afterwards, and the camera of each frame is given
+/// separately, so that frames can be rendered concurrently.
+#[derive(Debug)]
+pub struct AcceleratedScene {
+    scene: Arc<Scene>,
+    camera: Arc<dyn RayEmitter>,
+}
+
+impl AcceleratedScene {
+    /// Camera the scene was built with
+    pub fn camera(&self) -> Box<dyn RayEmitter> {
+        Box::new(SharedCamera(Arc::clone(&self.camera)))
+    }
+
+    /// Prepared scene, whose camera is the one it was built with
+    pub(crate) fn scene(&self) -> &Scene {
+        &self.scene
+    }
+}
+
+/// Camera shared by several scenes
+#[derive(Debug)]
+struct SharedCamera(Arc<dyn RayEmitter>);
+
+impl RayEmitter for SharedCamera {
+    fn width(&self) -> f64 {
+        self.0.width()
+    }
+
+    fn height(&self) -> f64 {
+        self.0.height()
+    }
+
+    fn generate_ray(&self, canvas_x: UnitInterval, canvas_y: UnitInterval) -> Ray {
+        self.0.generate_ray(canvas_x, canvas_y)
+    }
+
+    fn pixel_footprint(&self, point: Vec3, canvas_width: u32) -> f64 {
+        self.0.pixel_footprint(point, canvas_width)
+    }
+
+    fn reframed(&self, eye: Vec3, look_at: Vec3) -> Option<Box<dyn RayEmitter>> {
+        self.0.reframed(eye, look_at)
+    }
+
+    fn basis(&self) -> Option<CameraBasis> {
+        self.0.basis()
+    }
+}
+
+impl Scene {
+    /// Load a scene description file, relative paths it contains being resolved from
+    /// the directory of the file
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Scene> {
+        let path = path.as_ref();
+        let scene_str = fs::read_to_string(path)
+            .map_err(|e| RaytracerError::IoError(format!("{}: {}", path.display(), e)))?;
+        let base_path = path.parent().unwrap_or_else(|| Path::new(""));
+        parser::parse_scene_description_from(&scene_str, base_path)
+    }
+
+    /// Use a uniform background color, replacing any environment
+    pub fn set_background(&mut self, color: Color) {
+        self.config.world_color = color;
+        self.config.environment = None;
+    }
+
+    /// Scale light intensities according to the `normalize_lights` configuration
+    pub fn apply_light_normalization(&mut self) {
+        let target = match self.config.normalize_lights {
+            Some(target) => target,
+            None => return,
+        };
+        let total: f64 = self
+            .lights
+            .iter()
+            .map(|light| {
+                let color = light.peak_color();
+                let peak = color.red().max(color.green()).max(color.blue());
+                peak * light.intensity().abs()
+            })
+            .sum();
+        if total <= 0.0 {
+            return;
+        }
+        let factor = (target / total).min(1.0);
+        for light in self.lights.iter_mut() {
+            light.set_intensity(factor * light.intensity());
+        }
+    }
+
+    /// Replace the texture and effects of all objects according to the `clay_render`
+    /// configuration
+    pub fn apply_clay_render(&mut self) {
+        let color = match &self.config.clay_render {
+            Some(color) => color.clone(),
+            None => return,
+        };
+        for object in self.objects.iter_mut() {
+            object.texture = Box::new(PlainColorTexture {
+                color: color.clone(),
+            });
+            object.effects = TextureEffects::default();
+        }
+    }
+
+    /// Set the animated properties of the scene to their values at `time`
+    pub fn set_time(&mut self, time: f64) {
+        let animation = std::mem::take(&mut self.config.animation);
+        animation.apply(self, time);
+        self.config.animation = animation;
+    }
+
+    /// Sphere enclosing all the bounded objects, as a center and a radius
+    pub fn bounding_sphere(&self) -> Option<(Vec3, f64)> {
+        let bounding_box = self
+            .objects
+            .iter()
+            .filter_map(|object| object.shape.bounding_box())
+            .fold(None, |acc: Option<BoundingBox>, object_box| {
+                Some(acc.map_or(object_box, |acc| acc.union(&object_box)))
+            })?;
+        let center = bounding_box.center();
+        Some((center, center.distance(bounding_box.max)))
+    }
+
+    /// Camera placed at a light source and aimed at the scene, to preview what the
+    /// light reaches. The screen has the same size as the scene camera.
+    pub fn light_camera(&self, light_index: usize) -> Option<PerspectiveCamera> {
+        let light_source = self.lights.get(light_index)?.source();
+        let (target, radius) = self.bounding_sphere().unwrap_or((Vec3::ZERO, 0.0));
+        let distance = light_source.distance(target);
+        let angle = if radius > 0.0 && radius < distance {
+            (radius / distance).asin()
+        } else {
+            std::f64::consts::FRAC_PI_4
+        };
+        Some(PerspectiveCamera::from_eye(
+            light_source,
+            target,
+            self.camera.width(),
+            self.camera.height(),
+            angle,
+        ))
+    }
+
+    /// Remove bounded objects appearing smaller than `min_pixels` pixels on a canvas of
+    /// `canvas_width` pixels, returning the number of removed objects
+    pub fn cull_small_objects(&mut self, canvas_width: u32, min_pixels: f64) -> usize {
+        let camera = &self.camera;
+        let is_large_enough = |object: &SceneObject| match object.shape.bounding_box() {
+            Some(bounding_box) => {
+                let center = bounding_box.center();
+                let diameter = 2.0 * center.distance(bounding_box.max);
+                diameter >= min_pixels * camera.pixel_footprint(center, canvas_width)
+            }
+            None => true,
+        };
+        let object_count = self.objects.len();
+        self.objects.retain(is_large_enough);
+        object_count - self.objects.len()
+    }
+
+    /// Diffuse light received by each light at a surface point, indexed like
+    /// `lights`. The contribution of a light is black when an opaque object hides it
+    /// or when it is behind the surface, and filtered by the transparent objects it
+    /// goes through.
+    pub fn light_contributions(&self, point: Vec3, normal: Vec3) -> Vec<(usize, Color)> {
+        let normal = normal.normalize();
+        let mut contributions: Vec<(usize, Color)> = (0..self.lights.len())
+            .map(|index| (index, Color::BLACK))
+            .collect();
+        let lights = visible_lights(
+            point,
+            normal,
+            self.config.shadow_bias,
+            &self.lights,
+            &self.objects,
+            None,
+            self.config.photon_map.is_some(),
+        );
+        for (index, light, light_ray, transmission) in lights {
+            let angle = light_ray.direction.dot_product(normal);
+            if angle > 0.0 {
+                let intensity = light.intensity() * angle;
+                contributions[index].1 =
+                    intensity * (transmission * light.color_for_ray(light_ray));
+            }
+        }
+        contributions
+    }
+
+    /// Fraction of the diffuse light which would reach a surface point without
+    /// obstacles, but is hidden by objects: 0 for a lit point, 1 for a point fully
+    /// in shadow. Lights behind the surface are not taken into account.
+    pub fn shadow_amount(&self, point: Vec3, normal: Vec3) -> f64 {
+        let normal = normal.normalize();
+        let received_intensity = |light: &dyn AnyLightObject| {
+            let angle = light.direction_from(point).0.dot_product(normal);
+            light.intensity() * angle.max(0.0)
+        };
+        let total_intensity: f64 = self
+            .lights
+            .iter()
+            .map(|light| received_intensity(light.as_ref()))
+            .sum();
+        if total_intensity <= 0.0 {
+            return 0.0;
+        }
+        let visible_intensity: f64 = visible_lights(
+            point,
+            normal,
+            self.config.shadow_bias,
+            &self.lights,
+            &self.objects,
+            None,
+            self.config.photon_map.is_some(),
+        )
+        .map(|(_, light, _, transmission)| received_intensity(light) * transmission.luminance())
+        .sum();
+        (1.0 - visible_intensity / total_intensity).clamp(0.0, 1.0)
+    }
+
+    /// Indexes of the lights which seem trapped inside opaque objects: rays launched
+    /// in a few directions around the light all stop on opaque surfaces, and the
+    /// camera cannot be seen from the light. This is a heuristic, a light can still
+    /// escape through a gap missed by the sampled directions.
+    pub fn enclosed_lights(&self) -> Vec<usize> {
+        let mut directions = Vec::new();
+        for &x in &[-1.0, 0.0, 1.0] {
+            for &y in &[-1.0, 0.0, 1.0] {
+                for &z in &[-1.0, 0.0, 1.0] {
+                    if (x, y, z) != (0.0, 0.0, 0.0) {
+                        directions.push(Vec3::new(x, y, z));
+                    }
+                }
+            }
+        }
+        let camera_point = self.camera.generate_ray(0.5, 0.5).source;
+        let is_enclosed = |light: &dyn AnyLightObject| {
+            let source = light.source();
+            let is_stopped = |direction: &Vec3| match search_object_collision(
+                &Ray::new(source, *direction),
+                &self.objects,
+            ) {
+                Some(context) => context.object.effects().transparency.is_none(),
+                None => false,
+            };
+            let camera_ray = Ray::ray_from_to(source, camera_point);
+            directions.iter().all(is_stopped)
+                && ray_encounter_obstacle(&camera_ray, source.distance(camera_point), &self.objects)
+        };
+        self.lights
+            .iter()
+            .enumerate()
+            .filter(|(_, light)| is_enclosed(light.as_ref()))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Trace the photons used to render caustics, if enabled by `config.caustics`.
+    /// Must be called again when objects or lights change.
+    pub fn build_photon_map(&mut self) {
+        self.config.photon_map = self
+            .config
+            .caustics
+            .as_ref()
+            .map(|settings| PhotonMap::build(self, settings));
+    }
+
+    /// Do the preprocessing of the scene once, so that it can be shared by several
+    /// frames which do not change its geometry
+    pub fn build_accel(mut self) -> AcceleratedScene {
+        self.apply_clay_render();
+        self.build_photon_map();
+        let camera: Arc<dyn RayEmitter> = Arc::from(self.camera);
+        self.camera = Box::new(SharedCamera(Arc::clone(&camera)));
+        AcceleratedScene {
+            scene: Arc::new(self),
+            camera,
+        }
+    }
+
+    /// Check the scene for likely mistakes, logging and returning a warning for each
+    pub fn validate(&self) -> Vec<String> {
+        let warnings: Vec<String> = self
+            .enclosed_lights()
+            .into_iter()
+            .map(|index| {
+                format!(
+                    "Light {} seems enclosed by opaque objects, it may not light anything visible",
+                    index
+                )
+            })
+            .collect();
+        for warning in &warnings {
+            warn!("{}", warning);
+        }
+        warnings
+    }
+
+    /// Human readable summary of the scene content, for troubleshooting
+    pub fn describe(&self) -> String {
+        let mut summary = String::new();
+        // Writing into a String cannot fail
+        writeln!(summary, "Camera: {:?}", self.camera).unwrap();
+        writeln!(summary, "Configuration: {:?}", self.config).unwrap();
+        writeln!(summary, "Lights ({}):", self.lights.len()).unwrap();
+        for light in &self.lights {
+            writeln!(summary, "  - {:?}", light).unwrap();
+        }
+        writeln!(summary, "Objects ({}):", self.objects.len()).unwrap();
+        for object in &self.objects {
+            writeln!(summary, "  - Shape: {:?}", object.shape).unwrap();
+            writeln!(summary, "    Texture: {:?}", object.texture).unwrap();
+            writeln!(summary, "    Effects: {:?}", object.effects).unwrap();
+        }
+        summary
+    }
+}
+
 impl FromStr for Scene {
     type Err = RaytracerError;
 
@@ -101,3 +615,172 @@ impl FromStr for Scene {
         parser::parse_scene_description(scene_str)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cameras::OrthogonalCamera;
+    use crate::lights::{DirectionalLight, LightPoint};
+    use crate::primitives::{InfinitePlan, Sphere, SquarePlan};
+    use crate::textures::GradientColorTexture;
+    use crate::utils::f64_eq;
+
+    fn plain_object(shape: Box<dyn Shape>) -> SceneObject {
+        SceneObject::new(shape, Box::new(PlainColorTexture::default()))
+    }
+
+    #[test]
+    fn bounding_sphere_contains_all_spheres() {
+        let centers = [Vec3::new(-5.0, 0.0, 0.0), Vec3::new(5.0, 0.0, 0.0)];
+        let mut objects: Vec<SceneObject> = centers
+            .iter()
+            .map(|&center| {
+                plain_object(Box::new(Sphere {
+                    center,
+                    radius: 1.0,
+                }))
+            })
+            .collect();
+        // Unbounded objects are ignored
+        objects.push(plain_object(Box::new(InfinitePlan::new(
+            Vec3::new(0.0, -1.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+        ))));
+        let scene = Scene {
+            camera: Box::new(OrthogonalCamera::default()),
+            lights: vec![Box::new(LightPoint::new(Vec3::new(0.0, 10.0, 0.0)))],
+            objects,
+            config: Default::default(),
+        };
+        let (center, radius) = scene.bounding_sphere().unwrap();
+        for &sphere_center in &centers {
+            assert!(center.distance(sphere_center) + 1.0 <= radius + 1e-9);
+        }
+    }
+
+    #[test]
+    fn point_outside_square_plan_uses_fallback_mapping() {
+        let texture = GradientColorTexture {
+            start_color: Color::WHITE,
+            end_color: Color::BLACK,
+        };
+        let object = SceneObject::new(
+            Box::new(SquarePlan::new(
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+                2.0,
+            )),
+            Box::new(texture.clone()),
+        );
+        let color = object.color_at(Vec3::new(1.0 + 1e-9, 0.0, 0.0));
+        let (u, v) = FALLBACK_SURFACE_MAPPING;
+        let expected = texture.color_at(u, v);
+        assert!(f64_eq(color.red(), expected.red()));
+        assert!(f64_eq(color.green(), expected.green()));
+        assert!(f64_eq(color.blue(), expected.blue()));
+    }
+
+    #[test]
+    fn light_camera_rays_start_at_light() {
+        let light_source = Vec3::new(20.0, 30.0, -10.0);
+        let scene = Scene {
+            camera: Box::new(OrthogonalCamera::default()),
+            lights: vec![Box::new(LightPoint::new(light_source))],
+            objects: vec![plain_object(Box::new(Sphere {
+                center: Vec3::new(0.0, 0.0, 10.0),
+                radius: 2.0,
+            }))],
+            config: Default::default(),
+        };
+        assert!(scene.light_camera(1).is_none());
+        let camera = scene.light_camera(0).unwrap();
+        for &(x, y) in &[(0.1, 0.1), (0.5, 0.5), (0.9, 0.3)] {
+            assert!(camera.generate_ray(x, y).source.distance(light_source) < 1e-9);
+        }
+        let center_ray = camera.generate_ray(0.5, 0.5);
+        let expected = Vec3::between_points(light_source, Vec3::new(0.0, 0.0, 10.0)).normalize();
+        assert!(center_ray.direction.distance(expected) < 1e-9);
+    }
+
+    #[test]
+    fn shadowed_light_has_black_contribution() {
+        let scene = Scene {
+            camera: Box::new(OrthogonalCamera::default()),
+            lights: vec![
+                Box::new(LightPoint::new(Vec3::new(0.0, 10.0, 0.0))),
+                Box::new(LightPoint::new(Vec3::new(10.0, 10.0, 0.0))),
+            ],
+            // Sphere between the origin and the first light only
+            objects: vec![plain_object(Box::new(Sphere {
+                center: Vec3::new(0.0, 5.0, 0.0),
+                radius: 1.0,
+            }))],
+            config: Default::default(),
+        };
+        let contributions = scene.light_contributions(Vec3::ZERO, Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(contributions.len(), 2);
+        let (index, shadowed) = &contributions[0];
+        assert_eq!(*index, 0);
+        assert!(shadowed.red() + shadowed.green() + shadowed.blue() < 1e-9);
+        let (index, lit) = &contributions[1];
+        assert_eq!(*index, 1);
+        assert!(lit.red() > 0.1 && lit.green() > 0.1 && lit.blue() > 0.1);
+    }
+
+    #[test]
+    fn directional_light_is_hidden_by_objects_at_any_distance() {
+        let scene = Scene {
+            camera: Box::new(OrthogonalCamera::default()),
+            lights: vec![Box::new(DirectionalLight::new(Vec3::new(0.0, -1.0, 0.0)))],
+            // Farther than any finite position given to the light
+            objects: vec![plain_object(Box::new(Sphere {
+                center: Vec3::new(0.0, 1e7, 0.0),
+                radius: 1e5,
+            }))],
+            config: Default::default(),
+        };
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        let (_, shadowed) = &scene.light_contributions(Vec3::ZERO, up)[0];
+        assert!(shadowed.red() < 1e-9);
+        // Rays are parallel: a point far from the sphere axis is lit, vertically
+        let (_, lit) = &scene.light_contributions(Vec3::new(2e5, 0.0, 0.0), up)[0];
+        assert!(f64_eq(lit.red(), 1.0));
+        assert!(f64_eq(scene.shadow_amount(Vec3::ZERO, up), 1.0));
+    }
+
+    #[test]
+    fn light_trapped_in_closed_box_is_reported() {
+        let box_faces = |center: Vec3, half_size: f64| {
+            let axes = [
+                Vec3::new(1.0, 0.0, 0.0),
+                Vec3::new(0.0, 1.0, 0.0),
+                Vec3::new(0.0, 0.0, 1.0),
+            ];
+            let mut faces = Vec::new();
+            for axis in axes.iter() {
+                for &side in &[-1.0, 1.0] {
+                    let face =
+                        SquarePlan::new(center + side * half_size * *axis, *axis, 2.0 * half_size);
+                    faces.push(plain_object(Box::new(face)));
+                }
+            }
+            faces
+        };
+        let scene = Scene {
+            camera: Box::new(OrthogonalCamera::new(
+                Vec3::new(0.0, 0.0, -10.0),
+                Vec3::ZERO,
+                16.0,
+                9.0,
+            )),
+            lights: vec![
+                Box::new(LightPoint::new(Vec3::new(5.0, 0.0, 0.0))),
+                Box::new(LightPoint::new(Vec3::new(0.0, 5.0, -5.0))),
+            ],
+            objects: box_faces(Vec3::new(5.0, 0.0, 0.0), 1.0),
+            config: Default::default(),
+        };
+        assert_eq!(scene.enclosed_lights(), vec![0]);
+        assert_eq!(scene.validate().len(), 1);
+    }
+}
@@ -23,15 +23,27 @@ SOFTWARE.
 */
 
 use crate::colors::Color;
+use crate::result::{RaytracerError, Result};
 use crate::utils::{f64_gt, f64_lt};
+use crate::vector::Vec3;
 use crate::UnitInterval;
 use serde::Deserialize;
+use std::f64::consts::PI;
+use std::fmt;
+use std::fmt::Debug;
+use std::path::Path;
 
-pub trait Texture: Sync + Send {
+pub trait Texture: Sync + Send + Debug {
     fn color_at(&self, u: f64, v: f64) -> Color;
+
+    /// Color at `point`, in world coordinates, whose texture coordinates are `u` and
+    /// `v`. Only textures depending on the position in space need to override it.
+    fn color_at_point(&self, _point: Vec3, u: f64, v: f64) -> Color {
+        self.color_at(u, v)
+    }
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 #[serde(default)]
 pub struct PlainColorTexture {
     pub color: Color,
@@ -51,7 +63,7 @@ impl Texture for PlainColorTexture {
     }
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 pub struct GradientColorTexture {
     pub start_color: Color,
     pub end_color: Color,
@@ -63,12 +75,15 @@ impl Texture for GradientColorTexture {
     }
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 #[serde(default)]
 pub struct CheckedPattern {
     pub primary_color: Color,
     pub secondary_color: Color,
     pub count: f64,
+    /// Width of the band around tile edges where both colors are blended,
+    /// as a fraction of a tile; 0 gives hard edges
+    pub smoothing: f64,
 }
 
 impl Default for CheckedPattern {
@@ -77,24 +92,268 @@ impl Default for CheckedPattern {
             primary_color: Color::new(0.95, 0.95, 0.95),
             secondary_color: Color::new(0.05, 0.05, 0.05),
             count: 10.0,
+            smoothing: 0.0,
         }
     }
 }
 
+impl CheckedPattern {
+    /// Parity of the tile at `coord`, linearly blended near tile edges
+    fn smooth_parity(&self, coord: f64) -> f64 {
+        let parity = |tile: f64| (tile as i64).rem_euclid(2) as f64;
+        let nearest_edge = coord.round();
+        let half_band = self.smoothing.min(1.0) / 2.0;
+        let edge_distance = coord - nearest_edge;
+        if edge_distance.abs() >= half_band {
+            return parity(coord.floor());
+        }
+        let t = edge_distance / (2.0 * half_band) + 0.5;
+        (1.0 - t) * parity(nearest_edge - 1.0) + t * parity(nearest_edge)
+    }
+}
+
 impl Texture for CheckedPattern {
     fn color_at(&self, u: f64, v: f64) -> Color {
         assert!(f64_gt(u, 0.0) && f64_lt(u, 1.0));
         assert!(f64_gt(v, 0.0) && f64_lt(v, 1.0));
-        let selection = ((u * self.count).floor() + (v * self.count).floor()) as u64 % 2;
-        match selection {
-            0 => self.primary_color.clone(),
-            1 => self.secondary_color.clone(),
-            _ => unreachable!(),
+        if self.smoothing <= 0.0 {
+            let selection = ((u * self.count).floor() + (v * self.count).floor()) as u64 % 2;
+            return match selection {
+                0 => self.primary_color.clone(),
+                1 => self.secondary_color.clone(),
+                _ => unreachable!(),
+            };
+        }
+        let u_parity = self.smooth_parity(u * self.count);
+        let v_parity = self.smooth_parity(v * self.count);
+        // Blended equivalent of a XOR between both parities
+        let secondary_weight = u_parity * (1.0 - v_parity) + v_parity * (1.0 - u_parity);
+        (1.0 - secondary_weight) * &self.primary_color + secondary_weight * &self.secondary_color
+    }
+}
+
+/// Value giving its color to a surface point of a `ColorRampTexture`
+#[derive(Deserialize, Debug, Default, Copy, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RampInput {
+    /// Vertical texture coordinate
+    #[default]
+    V,
+    /// World y coordinate of the point, e.g. for terrains
+    Height,
+}
+
+/// Gradient map, generalizing `GradientColorTexture` to any number of colors: the
+/// input value is linearly interpolated between the colors of the two nearest
+/// stops. Values out of the stops take the color of the nearest one.
+#[derive(Debug, Clone)]
+pub struct ColorRampTexture {
+    /// Positions and colors, sorted by position
+    stops: Vec<(f64, Color)>,
+    input: RampInput,
+}
+
+impl ColorRampTexture {
+    pub fn new(mut stops: Vec<(f64, Color)>, input: RampInput) -> Self {
+        stops.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+        ColorRampTexture { stops, input }
+    }
+
+    /// Color of the ramp for `value`, black if there is no stop
+    pub fn color_for(&self, value: f64) -> Color {
+        let next_index = self
+            .stops
+            .iter()
+            .position(|(position, _)| *position > value);
+        match next_index {
+            None => self
+                .stops
+                .last()
+                .map_or(Color::BLACK, |(_, color)| color.clone()),
+            Some(0) => self.stops[0].1.clone(),
+            Some(index) => {
+                let (start, start_color) = &self.stops[index - 1];
+                let (end, end_color) = &self.stops[index];
+                let t = (value - start) / (end - start);
+                (1.0 - t) * start_color + t * end_color
+            }
+        }
+    }
+}
+
+impl Texture for ColorRampTexture {
+    /// Without the surface point, the height is not known and `v` is used
+    fn color_at(&self, _: f64, v: f64) -> Color {
+        self.color_for(v)
+    }
+
+    fn color_at_point(&self, point: Vec3, _: f64, v: f64) -> Color {
+        match self.input {
+            RampInput::V => self.color_for(v),
+            RampInput::Height => self.color_for(point.y),
         }
     }
 }
 
-#[derive(Deserialize, Debug)]
+/// Change of texture coordinates: rotation around the origin, then scale, then
+/// offset. The result is wrapped in the unit interval, so that the texture is tiled.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+#[serde(deny_unknown_fields)]
+pub struct UvTransform {
+    pub scale: [f64; 2],
+    pub offset: [f64; 2],
+    pub rotation_degree: f64,
+}
+
+impl Default for UvTransform {
+    fn default() -> Self {
+        UvTransform {
+            scale: [1.0, 1.0],
+            offset: [0.0, 0.0],
+            rotation_degree: 0.0,
+        }
+    }
+}
+
+impl UvTransform {
+    pub fn apply(&self, u: f64, v: f64) -> (UnitInterval, UnitInterval) {
+        let (sin, cos) = self.rotation_degree.to_radians().sin_cos();
+        let (u, v) = (cos * u - sin * v, sin * u + cos * v);
+        (
+            (self.scale[0] * u + self.offset[0]).rem_euclid(1.0),
+            (self.scale[1] * v + self.offset[1]).rem_euclid(1.0),
+        )
+    }
+}
+
+/// Texture whose coordinates are transformed before being sampled, so that objects
+/// sharing a texture can tile it differently
+#[derive(Debug)]
+pub struct TransformedTexture {
+    pub texture: Box<dyn Texture>,
+    pub transform: UvTransform,
+}
+
+impl Texture for TransformedTexture {
+    fn color_at(&self, u: f64, v: f64) -> Color {
+        let (u, v) = self.transform.apply(u, v);
+        self.texture.color_at(u, v)
+    }
+
+    fn color_at_point(&self, point: Vec3, u: f64, v: f64) -> Color {
+        let (u, v) = self.transform.apply(u, v);
+        self.texture.color_at_point(point, u, v)
+    }
+}
+
+/// Behavior of an `ImageTexture` at the edges of the image
+#[derive(Deserialize, Debug, Default, Copy, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TextureWrap {
+    /// The image is tiled, so that texels of opposite edges are blended
+    Repeat,
+    /// Texels of the edges extend beyond the image
+    #[default]
+    Clamp,
+}
+
+/// Raster texture, with bilinear interpolation between the four nearest texels.
+///
+/// Color maps are usually stored sRGB-encoded, so texels are decoded to linear space
+/// before being interpolated, unless `is_srgb` is false (e.g. for data maps).
+pub struct ImageTexture {
+    width: u32,
+    height: u32,
+    texels: Vec<Color>,
+    pub is_srgb: bool,
+    pub wrap: TextureWrap,
+}
+
+impl ImageTexture {
+    pub fn new(width: u32, height: u32, texels: Vec<Color>) -> Self {
+        assert_eq!(
+            texels.len(),
+            (width * height) as usize,
+            "texel count does not match image size"
+        );
+        ImageTexture {
+            width,
+            height,
+            texels,
+            is_srgb: true,
+            wrap: TextureWrap::default(),
+        }
+    }
+
+    pub fn with_wrap(self, wrap: TextureWrap) -> Self {
+        ImageTexture { wrap, ..self }
+    }
+
+    /// Linear color of a texel, whose coordinates can be outside of the image
+    fn texel(&self, x: i64, y: i64) -> Color {
+        let (width, height) = (i64::from(self.width), i64::from(self.height));
+        let (x, y) = match self.wrap {
+            TextureWrap::Repeat => (x.rem_euclid(width), y.rem_euclid(height)),
+            TextureWrap::Clamp => (x.clamp(0, width - 1), y.clamp(0, height - 1)),
+        };
+        let texel = &self.texels[(x + y * width) as usize];
+        if self.is_srgb {
+            texel.srgb_to_linear()
+        } else {
+            texel.clone()
+        }
+    }
+
+    pub fn with_srgb(width: u32, height: u32, texels: Vec<Color>, is_srgb: bool) -> Self {
+        ImageTexture {
+            is_srgb,
+            ..ImageTexture::new(width, height, texels)
+        }
+    }
+
+    /// Load a PNG or JPEG image file, as a sRGB encoded color map
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let image = image::open(path)
+            .map_err(|e| RaytracerError::ImageError(format!("{}: {}", path.display(), e)))?
+            .to_rgb8();
+        let (width, height) = image.dimensions();
+        let texels = image
+            .pixels()
+            .map(|pixel| Color::from_rgb8(pixel.0))
+            .collect();
+        Ok(ImageTexture::new(width, height, texels))
+    }
+}
+
+impl Debug for ImageTexture {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("ImageTexture")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("is_srgb", &self.is_srgb)
+            .field("wrap", &self.wrap)
+            .finish()
+    }
+}
+
+impl Texture for ImageTexture {
+    fn color_at(&self, u: f64, v: f64) -> Color {
+        // Texel centers are at half-integer coordinates
+        let x = u * f64::from(self.width) - 0.5;
+        let y = v * f64::from(self.height) - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+        let top = (1.0 - tx) * self.texel(x0, y0) + tx * self.texel(x0 + 1, y0);
+        let bottom = (1.0 - tx) * self.texel(x0, y0 + 1) + tx * self.texel(x0 + 1, y0 + 1);
+        (1.0 - ty) * top + ty * bottom
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
 #[serde(default)]
 pub struct TextureEffects {
     #[serde(default)]
@@ -103,6 +362,12 @@ pub struct TextureEffects {
     pub transparency: Option<Transparency>,
     #[serde(default)]
     pub mirror: Option<Mirror>,
+    #[serde(default)]
+    pub thin_film: Option<ThinFilm>,
+    #[serde(default)]
+    pub emission: Option<Emission>,
+    #[serde(default)]
+    pub subsurface: Option<Subsurface>,
 }
 
 impl Default for TextureEffects {
@@ -111,27 +376,157 @@ impl Default for TextureEffects {
             phong: None,
             transparency: None,
             mirror: None,
+            thin_film: None,
+            emission: None,
+            subsurface: None,
         }
     }
 }
 
-#[derive(Deserialize, Debug)]
+/// Light emitted by the surface, added to its shading. Only the path tracing
+/// strategy also uses it to light other objects
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Emission {
+    pub color: Color,
+    /// Scale of the emitted light, which can exceed 1
+    pub strength: f64,
+    /// When false, light is only emitted on the side the normal points toward,
+    /// like a ceiling light panel
+    pub emissive_two_sided: bool,
+}
+
+impl Emission {
+    /// Whether light is emitted toward a ray of `direction` hitting a surface of `normal`
+    pub fn is_emitted_toward(&self, normal: Vec3, direction: Vec3) -> bool {
+        self.emissive_two_sided || normal.dot_product(direction) < 0.0
+    }
+}
+
+impl Default for Emission {
+    fn default() -> Self {
+        Emission {
+            color: Color::WHITE,
+            strength: 1.0,
+            emissive_two_sided: true,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
 #[serde(default)]
 pub struct Mirror {
     pub coeff: UnitInterval,
+    /// When set, the reflection follows the Schlick approximation of the Fresnel
+    /// equations for this refractive index, from its minimum head-on to `coeff` at
+    /// grazing angles, as on water or glass. Otherwise `coeff` is used at all angles
+    pub ior: Option<f64>,
+}
+
+impl Mirror {
+    /// Fraction of light reflected for a ray whose angle with the surface normal
+    /// has the cosine `cos_incidence`
+    pub fn reflectance(&self, cos_incidence: f64) -> UnitInterval {
+        match self.ior {
+            None => self.coeff,
+            Some(ior) => {
+                let r0 = ((1.0 - ior) / (1.0 + ior)).powi(2);
+                let schlick = r0 + (1.0 - r0) * (1.0 - cos_incidence.abs()).powi(5);
+                self.coeff * schlick
+            }
+        }
+    }
 }
 
 impl Default for Mirror {
     fn default() -> Self {
-        Mirror { coeff: 0.8 }
+        Mirror {
+            coeff: 0.8,
+            ior: None,
+        }
     }
 }
 
-#[derive(Deserialize, Debug)]
+/// Thin transparent layer on the surface, giving iridescent reflections as seen
+/// on soap bubbles or oil slicks
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ThinFilm {
+    /// Film thickness, in nanometers
+    pub thickness: f64,
+    /// Refractive index of the film
+    pub ior: f64,
+}
+
+impl ThinFilm {
+    /// Approximated wavelengths of red, green and blue, in nanometers
+    const RGB_WAVELENGTHS: [f64; 3] = [650.0, 510.0, 475.0];
+
+    /// Reflectance of the film per color channel, given the cosine of the angle
+    /// between the view ray and the surface normal
+    pub fn interference_color(&self, cos_incidence: f64) -> Color {
+        // https://en.wikipedia.org/wiki/Thin-film_interference
+        let sin_incidence_square = 1.0 - cos_incidence.powi(2);
+        let cos_refraction = (1.0 - sin_incidence_square / self.ior.powi(2)).sqrt();
+        let optical_path = 2.0 * self.ior * self.thickness * cos_refraction;
+        // Half a wavelength phase shift on the outer reflection, hence a sine
+        let reflectance = |wavelength: f64| (PI * optical_path / wavelength).sin().powi(2);
+        let [red, green, blue] = Self::RGB_WAVELENGTHS;
+        Color::new(reflectance(red), reflectance(green), reflectance(blue))
+    }
+}
+
+impl Default for ThinFilm {
+    fn default() -> Self {
+        ThinFilm {
+            thickness: 380.0,
+            ior: 1.33,
+        }
+    }
+}
+
+/// Cheap approximation of the light scattered under the surface of translucent
+/// materials such as wax, skin or marble, giving them a soft look
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Subsurface {
+    /// How far diffuse light goes past the terminator: 0 is the usual diffuse
+    /// light, 1 lights the whole object
+    pub wrap: f64,
+    /// Part of the light behind a thin object seen through it
+    pub back_illumination: UnitInterval,
+}
+
+impl Subsurface {
+    /// Diffuse factor for a light, given the cosine between the surface normal and
+    /// the direction of the light
+    pub fn diffuse_factor(&self, cos_light: f64) -> f64 {
+        let wrapped = ((cos_light + self.wrap) / (1.0 + self.wrap)).max(0.0);
+        wrapped + self.back_illumination * (-cos_light).max(0.0)
+    }
+}
+
+impl Default for Subsurface {
+    fn default() -> Self {
+        Subsurface {
+            wrap: 0.5,
+            back_illumination: 0.0,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
 #[serde(default)]
 pub struct Transparency {
     pub refractive_index: f64,
     pub alpha: UnitInterval,
+    /// Where transparent objects overlap, the medium with the highest priority is used
+    pub priority: i32,
+    /// Filter applied to the light seen through the object, such as stained glass
+    pub transmission_color: Option<Color>,
+    /// Split the light between a reflected and a refracted ray following the Fresnel
+    /// equations, as for glass or water, instead of only refracting it
+    pub fresnel: bool,
 }
 
 impl Default for Transparency {
@@ -139,11 +534,14 @@ impl Default for Transparency {
         Transparency {
             refractive_index: 1.0,
             alpha: 0.5,
+            priority: 0,
+            transmission_color: None,
+            fresnel: false,
         }
     }
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 #[serde(default)]
 pub struct Phong {
     pub size: u32,
@@ -158,3 +556,130 @@ impl Default for Phong {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fresnel_mirror_reflects_more_at_grazing_angles() {
+        let water = Mirror {
+            coeff: 1.0,
+            ior: Some(1.33),
+        };
+        let head_on = water.reflectance(1.0);
+        assert!((head_on - 0.02).abs() < 0.001);
+        assert!(water.reflectance(0.5) > head_on);
+        assert!(water.reflectance(0.0) > 0.99);
+        let constant = Mirror::default();
+        assert_eq!(constant.reflectance(1.0), constant.reflectance(0.0));
+    }
+
+    #[test]
+    fn color_ramp_interpolates_between_nearest_stops() {
+        let ramp = ColorRampTexture::new(
+            vec![(1.0, Color::WHITE), (0.0, Color::BLUE), (0.5, Color::GREEN)],
+            RampInput::Height,
+        );
+        let color = ramp.color_at_point(Vec3::new(3.0, 0.75, 0.0), 0.5, 0.1);
+        assert!((color.red() - 0.5).abs() < 1e-9);
+        assert!((color.green() - 1.0).abs() < 1e-9);
+        assert!((color.blue() - 0.5).abs() < 1e-9);
+        let color = ramp.color_at_point(Vec3::new(0.0, 0.25, 0.0), 0.5, 0.9);
+        assert!((color.green() - 0.5).abs() < 1e-9 && (color.blue() - 0.5).abs() < 1e-9);
+        // Out of the stops, and from the texture coordinate
+        assert_eq!(ramp.color_for(-2.0).to_rgb8(), [0, 0, 255]);
+        assert_eq!(ramp.color_for(5.0).to_rgb8(), [255, 255, 255]);
+        assert_eq!(ramp.color_at(0.5, 0.5).to_rgb8(), [0, 255, 0]);
+    }
+
+    #[test]
+    fn color_ramp_with_nan_stop_is_built() {
+        let ramp = ColorRampTexture::new(
+            vec![
+                (1.0, Color::WHITE),
+                (f64::NAN, Color::RED),
+                (0.0, Color::BLUE),
+            ],
+            RampInput::V,
+        );
+        let color = ramp.color_for(0.5);
+        assert!((color.red() - 0.5).abs() < 1e-9 && (color.blue() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn smoothed_checker_on_tile_edge_is_average() {
+        let pattern = CheckedPattern {
+            primary_color: Color::WHITE,
+            secondary_color: Color::BLACK,
+            count: 10.0,
+            smoothing: 0.2,
+        };
+        let color = pattern.color_at(0.1, 0.05);
+        assert!((color.red() - 0.5).abs() < 1e-9);
+        assert!((color.green() - 0.5).abs() < 1e-9);
+        assert!((color.blue() - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn smoothed_checker_inside_tile_is_plain() {
+        let pattern = CheckedPattern {
+            smoothing: 0.2,
+            ..Default::default()
+        };
+        let color = pattern.color_at(0.15, 0.05);
+        assert!((color.red() - pattern.secondary_color.red()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn thin_film_thickness_shifts_hue() {
+        let thin_film = |thickness| ThinFilm {
+            thickness,
+            ior: 1.33,
+        };
+        let thin_color = thin_film(250.0).interference_color(1.0);
+        assert!(thin_color.blue() > thin_color.green() && thin_color.green() > thin_color.red());
+        let thick_color = thin_film(350.0).interference_color(1.0);
+        assert!(
+            thick_color.red() > thick_color.green() && thick_color.green() > thick_color.blue()
+        );
+    }
+
+    #[test]
+    fn srgb_mid_gray_texel_is_decoded_to_linear() {
+        let texture = ImageTexture::new(1, 1, vec![Color::new(0.5, 0.5, 0.5)]);
+        let color = texture.color_at(0.5, 0.5);
+        assert!((color.red() - 0.214).abs() < 1e-3);
+        assert!((color.green() - 0.214).abs() < 1e-3);
+        assert!((color.blue() - 0.214).abs() < 1e-3);
+    }
+
+    #[test]
+    fn image_texture_interpolates_between_texels() {
+        let texels = vec![Color::BLACK, Color::WHITE];
+        let texture = ImageTexture::with_srgb(2, 1, texels, false);
+        // Halfway between texel centers
+        assert!((texture.color_at(0.5, 0.5).red() - 0.5).abs() < 1e-12);
+        assert!((texture.color_at(0.375, 0.5).red() - 0.25).abs() < 1e-12);
+        assert!(texture.color_at(0.25, 0.5).red() < 1e-12);
+    }
+
+    #[test]
+    fn image_texture_edges_follow_wrap_mode() {
+        let texels = vec![Color::BLACK, Color::WHITE];
+        let clamped = ImageTexture::with_srgb(2, 1, texels.clone(), false);
+        assert!(clamped.color_at(0.0, 0.5).red() < 1e-12);
+        assert!((clamped.color_at(1.0, 0.5).red() - 1.0).abs() < 1e-12);
+        let repeated = ImageTexture::with_srgb(2, 1, texels, false).with_wrap(TextureWrap::Repeat);
+        assert!((repeated.color_at(0.0, 0.5).red() - 0.5).abs() < 1e-12);
+        assert!((repeated.color_at(1.0, 0.5).red() - 0.5).abs() < 1e-12);
+        assert!((repeated.color_at(1.25, 0.5).red()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn data_texel_is_not_decoded() {
+        let texture = ImageTexture::with_srgb(1, 1, vec![Color::new(0.5, 0.5, 0.5)], false);
+        let color = texture.color_at(0.5, 0.5);
+        assert!((color.red() - 0.5).abs() < 1e-12);
+    }
+}
@@ -25,6 +25,7 @@ SOFTWARE.
 use crate::parser::ModelVector;
 use crate::utils::f64_eq;
 use serde::Deserialize;
+use std::str::FromStr;
 
 #[derive(Debug, Default, Copy, Clone, Deserialize)]
 #[serde(from = "ModelVector")]
@@ -83,6 +84,15 @@ impl Vec3 {
     pub fn distance(&self, other: Vec3) -> f64 {
         (other - *self).norm()
     }
+
+    /// Rotation around an axis by an angle in radians, counterclockwise when the
+    /// axis points toward the viewer
+    pub fn rotate_around(&self, axis: Vec3, angle: f64) -> Vec3 {
+        // https://en.wikipedia.org/wiki/Rodrigues%27_rotation_formula
+        let k = axis.normalize();
+        let (sin, cos) = angle.sin_cos();
+        cos * *self + sin * k.cross_product(*self) + (k.dot_product(*self) * (1.0 - cos)) * k
+    }
 }
 
 impl std::cmp::PartialEq for Vec3 {
@@ -91,6 +101,29 @@ impl std::cmp::PartialEq for Vec3 {
     }
 }
 
+/// Parse comma-separated components, like `0,10,-10`
+impl FromStr for Vec3 {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split(',').map(str::trim).collect();
+        if parts.len() != 3 {
+            return Err(format!(
+                "{} has {} components, 3 comma-separated numbers are expected",
+                s,
+                parts.len()
+            ));
+        }
+        let mut components = [0.0; 3];
+        for (component, part) in components.iter_mut().zip(parts) {
+            *component = part
+                .parse::<f64>()
+                .map_err(|_| format!("{} is not a valid vector component in {}", part, s))?;
+        }
+        Ok(Vec3::new(components[0], components[1], components[2]))
+    }
+}
+
 impl std::ops::Neg for Vec3 {
     type Output = Vec3;
 
@@ -247,6 +280,225 @@ impl std::ops::Mul<Mat3> for Mat3 {
     }
 }
 
+/// Unit quaternion representing a rotation
+///
+/// Equality compares components, so that `q` and `-q`, while being the same rotation,
+/// are different.
+#[derive(Debug, Copy, Clone)]
+pub struct Quat {
+    pub w: f64,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl Quat {
+    pub const ID: Quat = Quat {
+        w: 1.0,
+        x: 0.0,
+        y: 0.0,
+        z: 0.0,
+    };
+
+    /// Rotation around an axis by an angle in radians, counterclockwise when the
+    /// axis points toward the viewer, like `Vec3::rotate_around`
+    pub fn from_axis_angle(axis: Vec3, angle: f64) -> Self {
+        let axis = axis.normalize();
+        let (sin, cos) = (angle / 2.0).sin_cos();
+        Quat {
+            w: cos,
+            x: sin * axis.x,
+            y: sin * axis.y,
+            z: sin * axis.z,
+        }
+    }
+
+    pub fn rotate(&self, vec: Vec3) -> Vec3 {
+        // https://en.wikipedia.org/wiki/Quaternions_and_spatial_rotation#Using_quaternions_as_rotations
+        let axis = Vec3::new(self.x, self.y, self.z);
+        let t = 2.0 * axis.cross_product(vec);
+        vec + self.w * t + axis.cross_product(t)
+    }
+
+    pub fn to_mat3(&self) -> Mat3 {
+        let Quat { w, x, y, z } = *self;
+        Mat3([
+            [
+                1.0 - 2.0 * (y * y + z * z),
+                2.0 * (x * y - w * z),
+                2.0 * (x * z + w * y),
+            ],
+            [
+                2.0 * (x * y + w * z),
+                1.0 - 2.0 * (x * x + z * z),
+                2.0 * (y * z - w * x),
+            ],
+            [
+                2.0 * (x * z - w * y),
+                2.0 * (y * z + w * x),
+                1.0 - 2.0 * (x * x + y * y),
+            ],
+        ])
+    }
+}
+
+impl std::cmp::PartialEq for Quat {
+    fn eq(&self, other: &Self) -> bool {
+        f64_eq(self.w, other.w)
+            && f64_eq(self.x, other.x)
+            && f64_eq(self.y, other.y)
+            && f64_eq(self.z, other.z)
+    }
+}
+
+/// Composition of rotations: `self` is applied after `rhs`
+impl std::ops::Mul<Quat> for Quat {
+    type Output = Quat;
+
+    fn mul(self, rhs: Quat) -> Self::Output {
+        Quat {
+            w: self.w * rhs.w - self.x * rhs.x - self.y * rhs.y - self.z * rhs.z,
+            x: self.w * rhs.x + self.x * rhs.w + self.y * rhs.z - self.z * rhs.y,
+            y: self.w * rhs.y - self.x * rhs.z + self.y * rhs.w + self.z * rhs.x,
+            z: self.w * rhs.z + self.x * rhs.y - self.y * rhs.x + self.z * rhs.w,
+        }
+    }
+}
+
+impl std::ops::Mul<Vec3> for Quat {
+    type Output = Vec3;
+
+    fn mul(self, rhs: Vec3) -> Self::Output {
+        self.rotate(rhs)
+    }
+}
+
+/// Homogeneous transformation matrix, combining translation, rotation and scale
+#[derive(Debug, Copy, Clone)]
+pub struct Mat4([[f64; 4]; 4]);
+
+impl Mat4 {
+    #[rustfmt::skip]
+    pub const ID: Mat4 =
+        Mat4([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0]
+        ]);
+
+    pub fn translation(offset: Vec3) -> Self {
+        let mut result = Mat4::ID;
+        result.0[0][3] = offset.x;
+        result.0[1][3] = offset.y;
+        result.0[2][3] = offset.z;
+        result
+    }
+
+    pub fn rotation(rotation: Quat) -> Self {
+        let mut result = Mat4::ID;
+        for (row, rotation_row) in result.0.iter_mut().zip(rotation.to_mat3().0.iter()) {
+            row[..3].copy_from_slice(rotation_row);
+        }
+        result
+    }
+
+    /// Scale along each axis, which can differ
+    pub fn scale(factors: Vec3) -> Self {
+        let mut result = Mat4::ID;
+        result.0[0][0] = factors.x;
+        result.0[1][1] = factors.y;
+        result.0[2][2] = factors.z;
+        result
+    }
+
+    /// Transform a position, affected by the translation
+    pub fn transform_point(&self, point: Vec3) -> Vec3 {
+        let mat = self.0;
+        Vec3::new(
+            point.x * mat[0][0] + point.y * mat[0][1] + point.z * mat[0][2] + mat[0][3],
+            point.x * mat[1][0] + point.y * mat[1][1] + point.z * mat[1][2] + mat[1][3],
+            point.x * mat[2][0] + point.y * mat[2][1] + point.z * mat[2][2] + mat[2][3],
+        )
+    }
+
+    /// Transform a direction, not affected by the translation
+    pub fn transform_vector(&self, vector: Vec3) -> Vec3 {
+        let mat = self.0;
+        Vec3::new(
+            vector.x * mat[0][0] + vector.y * mat[0][1] + vector.z * mat[0][2],
+            vector.x * mat[1][0] + vector.y * mat[1][1] + vector.z * mat[1][2],
+            vector.x * mat[2][0] + vector.y * mat[2][1] + vector.z * mat[2][2],
+        )
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut result = Mat4::ID;
+        for (row_index, row) in self.0.iter().enumerate() {
+            for (column_index, &value) in row.iter().enumerate() {
+                result.0[column_index][row_index] = value;
+            }
+        }
+        result
+    }
+
+    /// Inverse matrix, `None` if the matrix is singular, e.g. with a null scale
+    pub fn inverse(&self) -> Option<Self> {
+        // Gauss-Jordan elimination with partial pivoting
+        let mut mat = self.0;
+        let mut result = Mat4::ID.0;
+        for column in 0..4 {
+            let pivot = (column..4)
+                .max_by(|&a, &b| mat[a][column].abs().total_cmp(&mat[b][column].abs()))?;
+            if mat[pivot][column].abs() < 1e-12 {
+                return None;
+            }
+            mat.swap(column, pivot);
+            result.swap(column, pivot);
+            let pivot_value = mat[column][column];
+            for index in 0..4 {
+                mat[column][index] /= pivot_value;
+                result[column][index] /= pivot_value;
+            }
+            for row in 0..4 {
+                if row != column {
+                    let factor = mat[row][column];
+                    for index in 0..4 {
+                        mat[row][index] -= factor * mat[column][index];
+                        result[row][index] -= factor * result[column][index];
+                    }
+                }
+            }
+        }
+        Some(Mat4(result))
+    }
+}
+
+impl std::cmp::PartialEq for Mat4 {
+    fn eq(&self, other: &Self) -> bool {
+        self.0
+            .iter()
+            .flatten()
+            .zip(other.0.iter().flatten())
+            .all(|(&left, &right)| f64_eq(left, right))
+    }
+}
+
+/// Composition of transformations: `rhs` is applied first
+impl std::ops::Mul<Mat4> for Mat4 {
+    type Output = Mat4;
+
+    fn mul(self, rhs: Mat4) -> Self::Output {
+        let mut result = [[0.0; 4]; 4];
+        for (row, result_row) in result.iter_mut().enumerate() {
+            for (column, value) in result_row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.0[row][k] * rhs.0[k][column]).sum();
+            }
+        }
+        Mat4(result)
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -373,4 +625,113 @@ mod tests {
             assert_eq!(Mat3::ID, Mat3::transformation_between(vec, vec) );
         }
     }
+
+    mod vec {
+        use super::super::*;
+        use std::f64::consts::PI;
+
+        #[test]
+        fn rotate_x_around_z_gives_y() {
+            let result = Vec3::new(1.0, 0.0, 0.0).rotate_around(Vec3::new(0.0, 0.0, 1.0), PI / 2.0);
+            assert!(result.distance(Vec3::new(0.0, 1.0, 0.0)) < 1e-9);
+        }
+
+        #[test]
+        fn vec3_from_comma_separated_string() {
+            assert_eq!(Vec3::new(0.0, 10.0, -10.0), "0,10,-10".parse().unwrap());
+            assert_eq!(Vec3::new(1.5, 2.0, 3.0), " 1.5, 2 ,3 ".parse().unwrap());
+        }
+
+        #[test]
+        fn malformed_vec3_string_is_error() {
+            assert!("1,2".parse::<Vec3>().is_err());
+            assert!("1,2,3,".parse::<Vec3>().is_err());
+            assert!("1,two,3".parse::<Vec3>().is_err());
+            assert!("".parse::<Vec3>().is_err());
+        }
+
+        #[test]
+        fn rotate_by_zero_is_identity() {
+            let vec = Vec3::new(-3.0, 2.5, 7.0);
+            assert_eq!(vec, vec.rotate_around(Vec3::new(1.0, 1.0, 0.0), 0.0));
+        }
+    }
+
+    mod quat {
+        use super::super::*;
+        use std::f64::consts::PI;
+
+        #[test]
+        fn quarter_turns_around_each_axis() {
+            let x = Vec3::new(1.0, 0.0, 0.0);
+            let y = Vec3::new(0.0, 1.0, 0.0);
+            let z = Vec3::new(0.0, 0.0, 1.0);
+            for &(axis, from, to) in &[(x, y, z), (y, z, x), (z, x, y)] {
+                let quat = Quat::from_axis_angle(axis, PI / 2.0);
+                assert_eq!(quat * from, to);
+                assert_eq!(quat.to_mat3() * from, to);
+                assert_eq!(quat * axis, axis);
+            }
+        }
+
+        #[test]
+        fn composition_applies_right_rotation_first() {
+            let around_x = Quat::from_axis_angle(Vec3::new(1.0, 0.0, 0.0), PI / 2.0);
+            let around_z = Quat::from_axis_angle(Vec3::new(0.0, 0.0, 1.0), PI / 2.0);
+            let vec = Vec3::new(0.0, 1.0, 0.0);
+            assert_eq!((around_z * around_x) * vec, around_z * (around_x * vec));
+            assert_eq!((around_z * around_x) * vec, Vec3::new(0.0, 0.0, 1.0));
+            assert_eq!(
+                around_z.to_mat3() * around_x.to_mat3(),
+                (around_z * around_x).to_mat3()
+            );
+        }
+
+        #[test]
+        fn quat_matches_rotate_around() {
+            let axis = Vec3::new(1.0, -2.0, 0.5);
+            let vec = Vec3::new(-3.0, 2.5, 7.0);
+            let quat = Quat::from_axis_angle(axis, 0.7);
+            assert!((quat * vec).distance(vec.rotate_around(axis, 0.7)) < 1e-9);
+            assert_eq!(Quat::from_axis_angle(axis, 0.0), Quat::ID);
+        }
+    }
+
+    mod mat4 {
+        use super::super::*;
+        use std::f64::consts::PI;
+
+        #[test]
+        fn translation_moves_points_but_not_vectors() {
+            let translation = Mat4::translation(Vec3::new(1.0, 2.0, 3.0));
+            let vec = Vec3::new(-1.0, 0.5, 4.0);
+            assert_eq!(translation.transform_point(vec), Vec3::new(0.0, 2.5, 7.0));
+            assert_eq!(translation.transform_vector(vec), vec);
+        }
+
+        #[test]
+        fn composed_transform_applies_right_first() {
+            let transform = Mat4::translation(Vec3::new(0.0, 0.0, 5.0))
+                * Mat4::rotation(Quat::from_axis_angle(Vec3::new(0.0, 0.0, 1.0), PI / 2.0))
+                * Mat4::scale(Vec3::new(2.0, 1.0, 1.0));
+            let point = transform.transform_point(Vec3::new(1.0, 0.0, 0.0));
+            assert!(point.distance(Vec3::new(0.0, 2.0, 5.0)) < 1e-9);
+        }
+
+        #[test]
+        fn inverse_undoes_transform() {
+            let transform = Mat4::translation(Vec3::new(1.0, -2.0, 3.0))
+                * Mat4::rotation(Quat::from_axis_angle(Vec3::new(1.0, 1.0, 0.0), 0.7))
+                * Mat4::scale(Vec3::new(2.0, 0.5, 3.0));
+            let inverse = transform.inverse().unwrap();
+            let product = transform * inverse;
+            assert!(product
+                .0
+                .iter()
+                .flatten()
+                .zip(Mat4::ID.0.iter().flatten())
+                .all(|(value, expected)| (value - expected).abs() < 1e-9));
+            assert!(Mat4::scale(Vec3::new(1.0, 0.0, 1.0)).inverse().is_none());
+        }
+    }
 }
@@ -24,6 +24,7 @@ SOFTWARE.
 
 #![cfg(target_arch = "wasm32")]
 
+use crate::colors::TransferFunction;
 use crate::ray_algorithm::strategy::{RandomAntiAliasingRenderStrategy, StandardRenderStrategy};
 use crate::ray_algorithm::AnyPixelRenderStrategy;
 use crate::renderer::{render_scene, Pixel, RenderConfiguration};
@@ -60,6 +61,7 @@ impl JsConfig {
             Strategy::Normal => Box::new(StandardRenderStrategy),
             Strategy::Random => Box::new(RandomAntiAliasingRenderStrategy {
                 rays_per_pixel: self.ray_number,
+                seed: None,
             }),
         }
     }
@@ -101,6 +103,7 @@ impl TryFrom<&str> for Strategy {
 pub struct Renderer {
     render_iterator: Box<dyn Iterator<Item = Result<Pixel>>>,
     img_buffer: Vec<u8>,
+    transfer_function: TransferFunction,
     width: u32,
     height: u32,
 }
@@ -117,14 +120,17 @@ impl Renderer {
             canvas_width: js_config.canvas_width,
             canvas_height: (js_config.canvas_width as f64 / scene.camera.size_ratio()) as u32,
             render_strategy: js_config.generate_strategy(),
+            ..Default::default()
         };
         let width = config.canvas_width;
         let height = config.canvas_height;
+        let transfer_function = config.transfer_function;
         let img_buffer = vec![0; (config.canvas_width * config.canvas_height * 4) as usize];
         let render_iterator = Box::new(render_scene(scene, config, false).unwrap());
         Ok(Renderer {
             render_iterator,
             img_buffer,
+            transfer_function,
             width,
             height,
         })
@@ -147,10 +153,8 @@ impl Renderer {
             None => false,
             Some(Ok(pixel)) => {
                 let index = 4 * (pixel.x + pixel.y * self.width) as usize;
-                self.img_buffer[index] = (pixel.color.red() * 255.0) as u8;
-                self.img_buffer[index + 1] = (pixel.color.green() * 255.0) as u8;
-                self.img_buffer[index + 2] = (pixel.color.blue() * 255.0) as u8;
-                self.img_buffer[index + 3] = 0xFF;
+                let rgba = self.transfer_function.to_rgba8(&pixel.color, 0xFF);
+                self.img_buffer[index..index + 4].copy_from_slice(&rgba);
                 true
             }
             Some(Err(err)) => {
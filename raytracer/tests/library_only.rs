@@ -0,0 +1,45 @@
+/*
+MIT License
+
+Copyright (c) 2019, 2020 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Checks the library is usable without the WebAssembly bindings.
+//!
+//! Run with `cargo test -p raytracer --no-default-features`.
+
+#![cfg(not(feature = "wasm"))]
+
+mod samples;
+
+use raytracer::renderer::{render_scene, RenderConfiguration};
+
+#[test]
+fn render_without_wasm_feature() {
+    let scene = samples::generate_test_scene();
+    let config = RenderConfiguration {
+        canvas_width: 16,
+        canvas_height: 9,
+        ..Default::default()
+    };
+    let count = render_scene(scene, config, false).unwrap().count();
+    assert_eq!(count, 16 * 9);
+}
@@ -37,7 +37,8 @@ use std::path::PathBuf;
 
 
 pub enum SampleScene {
-    OkBasic
+    OkBasic,
+    OkSpotlight,
 }
 
 impl SampleScene {
@@ -46,6 +47,7 @@ impl SampleScene {
     pub fn to_string(&self) -> String {
         let sample = match self {
             SampleScene::OkBasic => "ok_basic.toml",
+            SampleScene::OkSpotlight => "ok_spotlight.toml",
         };
         Self::load_sample_file(sample)
     }
@@ -79,11 +81,7 @@ pub fn generate_test_scene() -> Scene {
         radius: 5.0,
     };
     let texture = <CheckedPattern as Default>::default();
-    let object = SceneObject {
-        shape: Box::new(primitive),
-        texture: Box::new(texture),
-        effects: Default::default(),
-    };
+    let object = SceneObject::new(Box::new(primitive), Box::new(texture));
     Scene {
         camera: Box::new(camera),
         lights: vec![Box::new(light)],
@@ -75,7 +75,10 @@ pub(crate) fn generate_test_scene() -> Scene {
         texture: Box::new(texture),
         effects: TextureEffects {
             phong: Some(Default::default()),
-            mirror: Some(Mirror { coeff: 1.0 }),
+            mirror: Some(Mirror {
+                coeff: 1.0,
+                ..Default::default()
+            }),
             ..Default::default()
         },
     };
@@ -122,7 +125,10 @@ pub(crate) fn generate_test_scene() -> Scene {
         primitive: Box::new(plane),
         texture: Box::new(texture),
         effects: TextureEffects {
-            mirror: Some(Mirror { coeff: 0.8 }),
+            mirror: Some(Mirror {
+                coeff: 0.8,
+                ..Default::default()
+            }),
             ..Default::default()
         },
     };
@@ -24,6 +24,7 @@ SOFTWARE.
 
 mod samples;
 
+use raytracer::renderer::{render_scene, render_scene_data, RenderConfiguration};
 use raytracer::scene::Scene;
 use std::fs;
 use std::iter;
@@ -36,3 +37,257 @@ fn load_basic_scene() {
     let scene_result = Scene::from_str(&scene_string);
     assert!(scene_result.is_ok());
 }
+
+#[test]
+fn describe_basic_scene() {
+    let scene_string = SampleScene::OkBasic.to_string();
+    let scene = Scene::from_str(&scene_string).unwrap();
+    let summary = scene.describe();
+    assert!(summary.contains("Lights (1):"));
+    assert!(summary.contains("Objects (2):"));
+}
+
+#[test]
+fn load_spotlight_scene() {
+    let scene_string = SampleScene::OkSpotlight.to_string();
+    let scene = Scene::from_str(&scene_string).unwrap();
+    let up = raytracer::vector::Vec3::new(0.0, 1.0, 0.0);
+    let (_, sphere_top) =
+        &scene.light_contributions(raytracer::vector::Vec3::new(0.0, 5.0, 0.0), up)[0];
+    assert!(sphere_top.red() > 0.7);
+    // Beyond the 40 degrees of the outer angle
+    let (_, outside_cone) =
+        &scene.light_contributions(raytracer::vector::Vec3::new(100.0, -5.0, 0.0), up)[0];
+    assert!(outside_cone.red() < 1e-9);
+}
+
+const TWO_LIGHTS_SCENE: &str = r##"
+    [config]
+    ambient_light = [0, 0, 0]
+
+    [camera]
+    type = "perspective"
+    screen_center = [0, 0, -10]
+    look_at = [0, 0, 0]
+    width = 16
+    height = 9
+
+    [[light]]
+    type = "point"
+    source = [0, 0, -50]
+    color = [0.4, 0.4, 0.4]
+
+    [[object]]
+    description = "Sphere"
+    type = "sphere"
+    center = [0, 0, 0]
+    radius = 3
+    texture.type = "plain"
+    texture.color = "white"
+"##;
+
+fn peak_brightness(scene_str: &str) -> f64 {
+    let scene = Scene::from_str(scene_str).unwrap();
+    let config = RenderConfiguration {
+        canvas_width: 32,
+        canvas_height: 18,
+        ..Default::default()
+    };
+    render_scene(scene, config, false)
+        .unwrap()
+        .map(|pixel| pixel.unwrap().color.red())
+        .fold(0.0, f64::max)
+}
+
+#[test]
+fn normalized_lights_keep_peak_brightness() {
+    let second_light = r##"
+    [[light]]
+    type = "point"
+    source = [0, 0, -50]
+    color = [0.4, 0.4, 0.4]
+    "##;
+    let one_light = TWO_LIGHTS_SCENE.to_string();
+    let two_lights = one_light.clone() + second_light;
+    let normalize = |scene: &str| scene.replace("[config]", "[config]\nnormalize_lights = 0.4");
+
+    let reference = peak_brightness(&one_light);
+    assert!(peak_brightness(&two_lights) > reference + 0.1);
+    let normalized_one = peak_brightness(&normalize(&one_light));
+    let normalized_two = peak_brightness(&normalize(&two_lights));
+    assert!((normalized_one - reference).abs() < 1e-6);
+    assert!((normalized_two - normalized_one).abs() < 1e-6);
+}
+
+#[test]
+fn lights_under_the_normalization_target_are_unchanged() {
+    let normalized = TWO_LIGHTS_SCENE.replace("[config]", "[config]\nnormalize_lights = 10.0");
+    let reference = peak_brightness(TWO_LIGHTS_SCENE);
+    assert!((peak_brightness(&normalized) - reference).abs() < 1e-6);
+}
+
+const TWO_SPHERES_OBJECTS: [&str; 2] = [
+    r##"
+    [[object]]
+    description = "Left sphere"
+    type = "sphere"
+    center = [-2, 0, 0]
+    radius = 2.5
+    texture.type = "plain"
+    texture.color = "red"
+    "##,
+    r##"
+    [[object]]
+    description = "Right sphere"
+    type = "sphere"
+    center = [2, 0, 0]
+    radius = 2.5
+    texture.type = "plain"
+    texture.color = "blue"
+    "##,
+];
+
+fn render_with_object_indexes(scene_str: &str) -> Vec<(Option<usize>, [u8; 3])> {
+    let scene = Scene::from_str(scene_str).unwrap();
+    let config = RenderConfiguration {
+        canvas_width: 32,
+        canvas_height: 18,
+        ..Default::default()
+    };
+    render_scene_data(scene, config)
+        .unwrap()
+        .map(|pixel| {
+            let pixel = pixel.unwrap();
+            (pixel.metadata.object_index, pixel.color.to_rgb8())
+        })
+        .collect()
+}
+
+#[test]
+fn sorted_objects_do_not_depend_on_file_order() {
+    let without_objects = TWO_LIGHTS_SCENE.split("[[object]]").next().unwrap();
+    let in_order = without_objects.to_string() + TWO_SPHERES_OBJECTS[0] + TWO_SPHERES_OBJECTS[1];
+    let reversed = without_objects.to_string() + TWO_SPHERES_OBJECTS[1] + TWO_SPHERES_OBJECTS[0];
+    assert_ne!(
+        render_with_object_indexes(&in_order),
+        render_with_object_indexes(&reversed)
+    );
+    let sorted = |scene: &str| scene.replace("[config]", "[config]\nsort_objects = true");
+    assert_eq!(
+        render_with_object_indexes(&sorted(&in_order)),
+        render_with_object_indexes(&sorted(&reversed))
+    );
+}
+
+#[test]
+fn scene_file_paths_are_relative_to_scene_directory() {
+    let root_dir = std::env::temp_dir().join(format!("raytracer-scene-{}", std::process::id()));
+    let scene_dir = root_dir.join("subdir");
+    fs::create_dir_all(&scene_dir).unwrap();
+    image::RgbImage::from_pixel(2, 2, image::Rgb([255, 0, 0]))
+        .save(scene_dir.join("tex.png"))
+        .unwrap();
+    let scene_str = TWO_LIGHTS_SCENE.to_string()
+        + r##"
+    [environment]
+    type = "cubemap"
+    faces = ["tex.png", "tex.png", "tex.png", "tex.png", "tex.png", "tex.png"]
+    "##;
+    let scene_path = scene_dir.join("scene.toml");
+    fs::write(&scene_path, scene_str).unwrap();
+
+    let scene_result = Scene::from_file(&scene_path);
+    fs::remove_dir_all(&root_dir).unwrap();
+    let scene = scene_result.unwrap();
+    let background = scene
+        .config
+        .background_color(raytracer::vector::Vec3::new(0.0, 0.0, 1.0));
+    assert!(background.red() > 0.99 && background.green() < 0.01);
+}
+
+#[test]
+fn image_texture_path_is_relative_to_scene_directory() {
+    let root_dir = std::env::temp_dir().join(format!("raytracer-texture-{}", std::process::id()));
+    let scene_dir = root_dir.join("subdir");
+    fs::create_dir_all(&scene_dir).unwrap();
+    image::RgbImage::from_pixel(2, 2, image::Rgb([0, 255, 0]))
+        .save(scene_dir.join("tex.png"))
+        .unwrap();
+    let scene_str = TWO_LIGHTS_SCENE.replace(
+        r#"texture.type = "plain"
+    texture.color = "white""#,
+        r#"texture.type = "image"
+    texture.path = "tex.png"
+    texture.wrap = "repeat""#,
+    );
+    let scene_path = scene_dir.join("scene.toml");
+    fs::write(&scene_path, scene_str).unwrap();
+
+    let scene_result = Scene::from_file(&scene_path);
+    fs::remove_dir_all(&root_dir).unwrap();
+    let scene = scene_result.unwrap();
+    let color = scene.objects[0].texture.color_at(0.3, 0.8);
+    assert!(color.green() > 0.99 && color.red() < 0.01);
+}
+
+#[test]
+fn linear_image_texture_is_not_srgb_decoded() {
+    let root_dir = std::env::temp_dir().join(format!("raytracer-linear-{}", std::process::id()));
+    fs::create_dir_all(&root_dir).unwrap();
+    image::RgbImage::from_pixel(1, 1, image::Rgb([128, 128, 128]))
+        .save(root_dir.join("roughness.png"))
+        .unwrap();
+    let scene_str = r#"
+    light = []
+
+    [camera]
+    type = "orthogonal"
+    eye = [0, 0, -10]
+    look_at = [0, 0, 0]
+    width = 16
+    height = 9
+
+    [[object]]
+    description = "Color map"
+    type = "sphere"
+    center = [0, 0, 0]
+    radius = 1
+    texture = { type = "image", path = "roughness.png" }
+
+    [[object]]
+    description = "Data map"
+    type = "sphere"
+    center = [3, 0, 0]
+    radius = 1
+    texture = { type = "image", path = "roughness.png", is_srgb = false }
+    "#;
+    let scene_path = root_dir.join("scene.toml");
+    fs::write(&scene_path, scene_str).unwrap();
+
+    let scene_result = Scene::from_file(&scene_path);
+    fs::remove_dir_all(&root_dir).unwrap();
+    let scene = scene_result.unwrap();
+    let decoded = scene.objects[0].texture.color_at(0.5, 0.5);
+    let linear = scene.objects[1].texture.color_at(0.5, 0.5);
+    assert!((linear.red() - 128.0 / 255.0).abs() < 1e-9);
+    assert!(decoded.red() < 0.25);
+}
+
+#[test]
+fn sky_environment_is_brighter_toward_the_sun() {
+    let scene_str = TWO_LIGHTS_SCENE.to_string()
+        + r##"
+    [environment]
+    type = "sky"
+    sun_direction = [1, 1, 0]
+    turbidity = 3.0
+    "##;
+    let scene = Scene::from_str(&scene_str).unwrap();
+    let background = |x, y, z| {
+        scene
+            .config
+            .background_color(raytracer::vector::Vec3::new(x, y, z))
+            .luminance()
+    };
+    assert!(background(1.0, 1.0, 0.0) > 2.0 * background(-1.0, 1.0, 0.0));
+}
@@ -24,8 +24,26 @@ SOFTWARE.
 
 mod samples;
 
-use raytracer::renderer::{render_scene, RenderConfiguration};
-use raytracer::scene::Scene;
+use raytracer::cameras::{OrthogonalCamera, PerspectiveCamera, ThinLensCamera};
+use raytracer::colors::{Color, Radiance, ToneMapping, TransferFunction};
+use raytracer::lights::{DirectionalLight, LightPoint};
+use raytracer::primitives::{InfinitePlan, Sphere};
+use raytracer::ray_algorithm::strategy::RandomAntiAliasingRenderStrategy;
+use raytracer::ray_algorithm::{AnyPixelRenderStrategy, RenderPasses};
+use raytracer::renderer::{
+    composite_passes, estimate_render_time, pixel_order, render_accelerated, render_by_rows,
+    render_progressive, render_scene, render_scene_data, render_scene_multi, render_scene_passes,
+    render_scene_with_report, render_tiles, render_tiles_to_file, tile_order, trace_pixel,
+    NanCheck, OutputKind, Rect, RenderConfiguration, TileOrder, COARSE_TO_FINE_STEP,
+    NAN_DEBUG_COLOR,
+};
+use raytracer::result::{RaytracerError, Result};
+use raytracer::scene::{RayEmitter, Scene, SceneConfiguration, SceneObject};
+use raytracer::textures::{
+    CheckedPattern, Emission, Mirror, PlainColorTexture, Texture, TextureEffects,
+};
+use raytracer::vector::Vec3;
+use std::time::Duration;
 
 
 
@@ -59,3 +77,1063 @@ fn smoke_parallel_rendering() {
     let count = render_iter.count();
     assert_eq!(count, expected_count);
 }
+
+fn assert_passes_composite_to_normal_render(exposure: f64, tone_mapping: ToneMapping) {
+    let config = || RenderConfiguration {
+        canvas_width: 64,
+        canvas_height: 36,
+        exposure,
+        tone_mapping,
+        ..Default::default()
+    };
+    let expected: Vec<Color> = render_scene(samples::generate_test_scene(), config(), false)
+        .unwrap()
+        .map(|pixel| pixel.unwrap().color)
+        .collect();
+    let passes = render_scene_passes(samples::generate_test_scene(), config()).unwrap();
+    let composite = composite_passes(&passes, &config());
+    assert_eq!(composite.len(), expected.len());
+    for (color, expected_color) in composite.iter().zip(expected.iter()) {
+        assert!((color.red() - expected_color.red()).abs() < 1e-9);
+        assert!((color.green() - expected_color.green()).abs() < 1e-9);
+        assert!((color.blue() - expected_color.blue()).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn sum_of_render_passes_is_normal_render() {
+    assert_passes_composite_to_normal_render(0.0, ToneMapping::Clamp);
+}
+
+#[test]
+fn overexposed_render_passes_are_summed_before_tone_mapping() {
+    let config = RenderConfiguration {
+        canvas_width: 64,
+        canvas_height: 36,
+        exposure: 2.0,
+        ..Default::default()
+    };
+    let passes = render_scene_passes(samples::generate_test_scene(), config).unwrap();
+    // Passes are not clamped, so that their sum keeps the light above white
+    let max_red = (0..64 * 36)
+        .map(|index| {
+            passes
+                .values()
+                .fold(Radiance::default(), |acc, buffer| acc + buffer[index])
+                .red()
+        })
+        .fold(f64::MIN, f64::max);
+    assert!(max_red > 1.0);
+    assert_passes_composite_to_normal_render(2.0, ToneMapping::Clamp);
+    assert_passes_composite_to_normal_render(2.0, ToneMapping::Reinhard);
+}
+
+#[test]
+fn negative_light_reduces_brightness() {
+    let total_brightness = |scene: Scene| -> f64 {
+        let config = RenderConfiguration {
+            canvas_width: 64,
+            canvas_height: 36,
+            ..Default::default()
+        };
+        render_scene(scene, config, false)
+            .unwrap()
+            .map(|pixel| {
+                let color = pixel.unwrap().color;
+                color.red() + color.green() + color.blue()
+            })
+            .sum()
+    };
+    let reference = total_brightness(samples::generate_test_scene());
+    let mut scene = samples::generate_test_scene();
+    let negative_light = LightPoint::new(Vec3::new(50.0, 100.0, -50.0)).with_intensity(-0.5);
+    scene.lights.push(Box::new(negative_light));
+    let darkened = total_brightness(scene);
+    assert!(darkened < reference);
+}
+
+#[test]
+fn exposure_plus_one_doubles_color() {
+    let render_colors = |exposure: f64| -> Vec<Color> {
+        let config = RenderConfiguration {
+            canvas_width: 64,
+            canvas_height: 36,
+            exposure,
+            ..Default::default()
+        };
+        render_scene(samples::generate_test_scene(), config, false)
+            .unwrap()
+            .map(|pixel| pixel.unwrap().color)
+            .collect()
+    };
+    let reference = render_colors(0.0);
+    let exposed = render_colors(1.0);
+    let mut checked_count = 0;
+    for (reference, exposed) in reference.iter().zip(exposed.iter()) {
+        if reference.red() > 0.1 && reference.red() < 0.45 {
+            assert!((exposed.red() - 2.0 * reference.red()).abs() < 1e-9);
+            checked_count += 1;
+        }
+    }
+    assert!(checked_count > 0);
+}
+
+/// Color of the center pixel of a small render of a black sphere with the given
+/// `emission`, rendered with the other options of `config`
+fn emissive_sphere_center_color(emission: Emission, config: RenderConfiguration) -> Color {
+    let scene = Scene {
+        camera: Box::new(OrthogonalCamera::default()),
+        lights: vec![Box::new(LightPoint::new(Vec3::new(0.0, 0.0, -10.0)))],
+        objects: vec![SceneObject::new(
+            Box::new(Sphere {
+                center: Vec3::ZERO,
+                radius: 3.0,
+            }),
+            Box::new(PlainColorTexture {
+                color: Color::BLACK,
+            }),
+        )
+        .with_effects(TextureEffects {
+            emission: Some(emission),
+            ..Default::default()
+        })],
+        config: SceneConfiguration {
+            ambient_light: None,
+            ..Default::default()
+        },
+    };
+    let config = RenderConfiguration {
+        canvas_width: 3,
+        canvas_height: 3,
+        ..config
+    };
+    render_scene(scene, config, false)
+        .unwrap()
+        .nth(4)
+        .unwrap()
+        .unwrap()
+        .color
+}
+
+#[test]
+fn exposure_is_applied_before_clamping() {
+    let config = RenderConfiguration {
+        exposure: -2.0,
+        ..Default::default()
+    };
+    let emission = Emission {
+        strength: 3.0,
+        ..Default::default()
+    };
+    let highlight = emissive_sphere_center_color(emission, config);
+    assert!((highlight.red() - 0.75).abs() < 1e-9);
+}
+
+#[test]
+fn gamut_clamp_keeps_the_luminance_of_out_of_gamut_lighting() {
+    // Emitted radiance of (1.6, 0.4, 0.0), too bright and saturated for sRGB
+    let with_gamut_clamp = |gamut_clamp| {
+        let emission = Emission {
+            color: Color::new(1.0, 0.25, 0.0),
+            strength: 1.6,
+            ..Default::default()
+        };
+        let config = RenderConfiguration {
+            gamut_clamp,
+            ..Default::default()
+        };
+        emissive_sphere_center_color(emission, config)
+    };
+    let expected_luminance = 0.2126 * 1.6 + 0.7152 * 0.4;
+    let mapped = with_gamut_clamp(true);
+    let clamped = with_gamut_clamp(false);
+    assert!((mapped.luminance() - expected_luminance).abs() < 1e-9);
+    assert!(clamped.luminance() < expected_luminance - 0.1);
+    assert!(mapped.red() > mapped.green() && mapped.green() > mapped.blue());
+}
+
+#[test]
+fn reinhard_tone_mapping_compresses_the_unclamped_radiance() {
+    for &gamut_clamp in &[false, true] {
+        let config = RenderConfiguration {
+            tone_mapping: ToneMapping::Reinhard,
+            gamut_clamp,
+            ..Default::default()
+        };
+        let emission = Emission {
+            strength: 4.0,
+            ..Default::default()
+        };
+        let color = emissive_sphere_center_color(emission, config);
+        assert!((color.red() - 0.8).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn reinhard_tone_mapping_keeps_overexposed_colors_below_white() {
+    let render_max = |tone_mapping: ToneMapping| -> f64 {
+        let config = RenderConfiguration {
+            canvas_width: 64,
+            canvas_height: 36,
+            exposure: 3.0,
+            tone_mapping,
+            ..Default::default()
+        };
+        render_scene(samples::generate_test_scene(), config, false)
+            .unwrap()
+            .map(|pixel| pixel.unwrap().color.red())
+            .fold(0.0, f64::max)
+    };
+    assert_eq!(render_max(ToneMapping::Clamp), 1.0);
+    assert!(render_max(ToneMapping::Reinhard) < 1.0);
+}
+
+#[test]
+fn pixel_data_reports_depth_of_hit() {
+    let config = RenderConfiguration {
+        canvas_width: 64,
+        canvas_height: 36,
+        ..Default::default()
+    };
+    let pixels: Vec<_> = render_scene_data(samples::generate_test_scene(), config)
+        .unwrap()
+        .map(|pixel| pixel.unwrap())
+        .collect();
+    assert_eq!(pixels.len(), 64 * 36);
+    // The sphere is in the lower part of the image
+    let hit = pixels
+        .iter()
+        .find(|pixel| pixel.x == 32 && pixel.y == 30)
+        .unwrap();
+    assert!(hit.metadata.depth.unwrap().is_finite());
+    assert_eq!(hit.metadata.object_index, Some(0));
+    assert_eq!(hit.metadata.sample_count, 1);
+    let corner = &pixels[0];
+    assert!(corner.metadata.depth.is_none());
+    assert!(corner.metadata.object_index.is_none());
+}
+
+#[test]
+fn render_report_json_has_resolution_and_ray_count() {
+    let mut hit_counts = Vec::new();
+    for &parallel in &[false, true] {
+        let config = RenderConfiguration {
+            canvas_width: 32,
+            canvas_height: 18,
+            render_strategy: Box::new(RandomAntiAliasingRenderStrategy {
+                rays_per_pixel: 3,
+                seed: Some(7),
+            }),
+            ..Default::default()
+        };
+        let mut report = None;
+        let pixel_count = render_scene_with_report(
+            samples::generate_test_scene(),
+            config,
+            parallel,
+            "random",
+            3,
+            |finished| {
+                report = Some(finished);
+                Ok(())
+            },
+        )
+        .unwrap()
+        .map(|pixel| pixel.unwrap())
+        .count();
+        assert_eq!(pixel_count, 32 * 18);
+        let json: serde_json::Value = serde_json::from_str(&report.unwrap().to_json()).unwrap();
+        assert_eq!(json["width"], 32);
+        assert_eq!(json["height"], 18);
+        assert_eq!(json["strategy"], "random");
+        assert_eq!(json["seed"], 7);
+        assert_eq!(json["stats"]["pixels"], 32 * 18);
+        assert_eq!(json["stats"]["camera_rays"], 32 * 18 * 3);
+        // Rays of the seeded strategy, some of them seeing the background
+        let hits = json["stats"]["hits"].as_u64().unwrap();
+        assert!(hits > 0 && hits < 32 * 18 * 3);
+        hit_counts.push(hits);
+    }
+    assert_eq!(hit_counts[0], hit_counts[1]);
+}
+
+#[test]
+fn render_report_error_is_the_last_item() {
+    let config = RenderConfiguration {
+        canvas_width: 8,
+        canvas_height: 4,
+        ..Default::default()
+    };
+    let results: Vec<_> = render_scene_with_report(
+        samples::generate_test_scene(),
+        config,
+        false,
+        "standard",
+        1,
+        |_| Err(RaytracerError::IoError("report".to_string())),
+    )
+    .unwrap()
+    .collect();
+    assert_eq!(results.len(), 8 * 4 + 1);
+    assert!(results[..8 * 4].iter().all(|pixel| pixel.is_ok()));
+    assert!(results[8 * 4].is_err());
+}
+
+#[test]
+fn progressive_frames_are_complete_and_refined() {
+    let config = RenderConfiguration {
+        canvas_width: 32,
+        canvas_height: 18,
+        ..Default::default()
+    };
+    let frames: Vec<_> = render_progressive(samples::generate_test_scene(), config)
+        .unwrap()
+        .take(3)
+        .map(|frame| frame.unwrap())
+        .collect();
+    let mut previous_samples = 0;
+    for frame in frames {
+        assert_eq!(frame.pixels.len(), 32 * 18);
+        assert!(frame.samples_per_pixel > previous_samples);
+        previous_samples = frame.samples_per_pixel;
+    }
+}
+
+#[test]
+fn background_color_is_seen_by_missing_rays() {
+    let config = RenderConfiguration {
+        canvas_width: 16,
+        canvas_height: 9,
+        ..Default::default()
+    };
+    let mut scene = samples::generate_test_scene();
+    scene.set_background(Color::new(0.0, 0.0, 1.0));
+    let corner = render_scene(scene, config, false)
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap();
+    assert_eq!(corner.color.red(), 0.0);
+    assert_eq!(corner.color.green(), 0.0);
+    assert_eq!(corner.color.blue(), 1.0);
+}
+
+#[derive(Debug)]
+struct NanTexture;
+
+impl Texture for NanTexture {
+    fn color_at(&self, _: f64, _: f64) -> Color {
+        Color::new(f64::NAN, 0.0, 0.0)
+    }
+}
+
+#[test]
+fn nan_check_replaces_invalid_colors() {
+    let config = RenderConfiguration {
+        canvas_width: 64,
+        canvas_height: 36,
+        nan_check: NanCheck::DebugColor,
+        ..Default::default()
+    };
+    let mut scene = samples::generate_test_scene();
+    scene.objects[0].texture = Box::new(NanTexture);
+    let hit = render_scene(scene, config, false)
+        .unwrap()
+        .map(|pixel| pixel.unwrap())
+        .find(|pixel| pixel.x == 32 && pixel.y == 30)
+        .unwrap();
+    assert_eq!(hit.color.red(), NAN_DEBUG_COLOR.red());
+    assert_eq!(hit.color.green(), NAN_DEBUG_COLOR.green());
+    assert_eq!(hit.color.blue(), NAN_DEBUG_COLOR.blue());
+}
+
+#[test]
+fn multi_render_beauty_matches_standalone_render() {
+    let small_config = || RenderConfiguration {
+        canvas_width: 32,
+        canvas_height: 18,
+        ..Default::default()
+    };
+    let outputs = render_scene_multi(
+        samples::generate_test_scene(),
+        small_config(),
+        &[OutputKind::Beauty, OutputKind::Depth, OutputKind::ObjectIds],
+    )
+    .unwrap();
+    assert!(outputs.normals.is_none());
+    let beauty = outputs.beauty.unwrap();
+    let depth = outputs.depth.unwrap();
+    let object_ids = outputs.object_ids.unwrap();
+    let standalone = render_scene(samples::generate_test_scene(), small_config(), false).unwrap();
+    for pixel in standalone {
+        let pixel = pixel.unwrap();
+        let index = (pixel.y * 32 + pixel.x) as usize;
+        assert_eq!(beauty[index].red(), pixel.color.red());
+        assert_eq!(beauty[index].green(), pixel.color.green());
+        assert_eq!(beauty[index].blue(), pixel.color.blue());
+        assert_eq!(depth[index].is_some(), object_ids[index].is_some());
+    }
+}
+
+#[test]
+fn tiny_far_sphere_is_culled_only_at_low_resolution() {
+    let tiny_sphere_scene = || Scene {
+        camera: Box::new(PerspectiveCamera::default()),
+        lights: vec![Box::new(LightPoint::new(Vec3::new(0.0, 100.0, 0.0)))],
+        objects: vec![SceneObject::new(
+            Box::new(Sphere {
+                center: Vec3::new(0.0, 0.0, 50.0),
+                radius: 1.0,
+            }),
+            Box::new(PlainColorTexture::default()),
+        )],
+        config: Default::default(),
+    };
+    // Odd canvas sizes, so that a ray goes through the center of the image
+    let sphere_is_seen = |canvas_width, canvas_height| {
+        let config = RenderConfiguration {
+            canvas_width,
+            canvas_height,
+            small_object_culling: Some(1.0),
+            ..Default::default()
+        };
+        render_scene_multi(tiny_sphere_scene(), config, &[OutputKind::ObjectIds])
+            .unwrap()
+            .object_ids
+            .unwrap()
+            .iter()
+            .any(Option::is_some)
+    };
+    assert!(!sphere_is_seen(45, 25));
+    assert!(sphere_is_seen(405, 225));
+}
+
+#[test]
+fn seeded_random_strategy_is_reproducible() {
+    let render_colors = |parallel: bool, depth_of_field: bool| -> Vec<Color> {
+        let mut scene = samples::generate_test_scene();
+        if depth_of_field {
+            let camera = PerspectiveCamera::from_eye(
+                Vec3::new(0.0, 5.0, -20.0),
+                Vec3::ZERO,
+                16.0,
+                9.0,
+                std::f64::consts::PI / 8.0,
+            );
+            scene.camera = Box::new(ThinLensCamera::new(camera, 1.0, 15.0));
+        }
+        let config = RenderConfiguration {
+            canvas_width: 32,
+            canvas_height: 18,
+            render_strategy: Box::new(RandomAntiAliasingRenderStrategy {
+                rays_per_pixel: 4,
+                seed: Some(42),
+            }),
+            ..Default::default()
+        };
+        let mut pixels: Vec<_> = render_scene(scene, config, parallel)
+            .unwrap()
+            .map(|pixel| pixel.unwrap())
+            .collect();
+        pixels.sort_by_key(|pixel| (pixel.y, pixel.x));
+        pixels.into_iter().map(|pixel| pixel.color).collect()
+    };
+    for &depth_of_field in &[false, true] {
+        let sequential = render_colors(false, depth_of_field);
+        // Parallel rendering processes pixels in a different order
+        for colors in &[
+            render_colors(false, depth_of_field),
+            render_colors(true, depth_of_field),
+        ] {
+            for (reference, color) in sequential.iter().zip(colors) {
+                assert_eq!(reference.red(), color.red());
+                assert_eq!(reference.green(), color.green());
+                assert_eq!(reference.blue(), color.blue());
+            }
+        }
+    }
+}
+
+#[test]
+fn wide_pixels_cover_more_scene_horizontally() {
+    // Number of pixel columns and rows seeing a sphere of diameter 3
+    let sphere_extent = |canvas_width: u32, canvas_height: u32, pixel_aspect_ratio: f64| {
+        let scene = Scene {
+            camera: Box::new(OrthogonalCamera::new(
+                Vec3::new(0.0, 0.0, -10.0),
+                Vec3::ZERO,
+                16.0,
+                9.0,
+            )),
+            lights: vec![Box::new(LightPoint::new(Vec3::new(0.0, 0.0, -100.0)))],
+            objects: vec![SceneObject::new(
+                Box::new(Sphere {
+                    center: Vec3::ZERO,
+                    radius: 1.5,
+                }),
+                Box::new(PlainColorTexture::default()),
+            )],
+            config: Default::default(),
+        };
+        let config = RenderConfiguration {
+            canvas_width,
+            canvas_height,
+            pixel_aspect_ratio,
+            ..Default::default()
+        };
+        let object_ids = render_scene_multi(scene, config, &[OutputKind::ObjectIds])
+            .unwrap()
+            .object_ids
+            .unwrap();
+        let hits: Vec<(u32, u32)> = (0..canvas_height)
+            .flat_map(|y| (0..canvas_width).map(move |x| (x, y)))
+            .filter(|&(x, y)| object_ids[(y * canvas_width + x) as usize].is_some())
+            .collect();
+        let columns = hits.iter().map(|&(x, _)| x).max().unwrap()
+            - hits.iter().map(|&(x, _)| x).min().unwrap()
+            + 1;
+        let rows = hits.iter().map(|&(_, y)| y).max().unwrap()
+            - hits.iter().map(|&(_, y)| y).min().unwrap()
+            + 1;
+        (columns as i32, rows as i32)
+    };
+    let (columns, rows) = sphere_extent(64, 36, 1.0);
+    assert!((columns - rows).abs() <= 1);
+    // Twice as many rows, displayed on pixels twice as wide as high
+    let (columns, rows) = sphere_extent(64, 72, 2.0);
+    assert!((2 * columns - rows).abs() <= 2);
+    // The proportions are kept when the canvas does not match the camera
+    let (columns, rows) = sphere_extent(64, 36, 2.0);
+    assert!((2 * columns - rows).abs() <= 2);
+}
+
+#[test]
+fn rows_are_streamed_in_order() {
+    let scene = samples::generate_test_scene();
+    let config = RenderConfiguration {
+        canvas_width: 16,
+        canvas_height: 9,
+        ..Default::default()
+    };
+    let mut row_indices = Vec::new();
+    render_by_rows(scene, config, |y, row| {
+        assert_eq!(row.len(), 16);
+        row_indices.push(y);
+    })
+    .unwrap();
+    assert_eq!(row_indices, (0..9).collect::<Vec<u32>>());
+}
+
+#[test]
+fn traced_pixel_reports_hit_sphere() {
+    let scene = Scene {
+        camera: Box::new(OrthogonalCamera::new(
+            Vec3::new(0.0, 0.0, -10.0),
+            Vec3::ZERO,
+            16.0,
+            9.0,
+        )),
+        lights: vec![Box::new(LightPoint::new(Vec3::new(0.0, 10.0, -10.0)))],
+        objects: vec![
+            SceneObject::new(
+                Box::new(Sphere {
+                    center: Vec3::new(6.0, 0.0, 0.0),
+                    radius: 1.0,
+                }),
+                Box::new(PlainColorTexture::default()),
+            ),
+            SceneObject::new(
+                Box::new(Sphere {
+                    center: Vec3::ZERO,
+                    radius: 2.0,
+                }),
+                Box::new(PlainColorTexture { color: Color::RED }),
+            ),
+        ],
+        config: Default::default(),
+    };
+    let config = RenderConfiguration {
+        canvas_width: 16,
+        canvas_height: 9,
+        ..Default::default()
+    };
+    let trace = trace_pixel(scene, config, 8, 4).unwrap();
+    let hit = trace.hit.as_ref().unwrap();
+    assert_eq!(hit.object_index, 1);
+    assert_eq!(hit.light_contributions.len(), 1);
+    assert!(trace.color.red() > 0.0);
+    for component in &[trace.color.red(), trace.color.green(), trace.color.blue()] {
+        assert!(component.is_finite());
+    }
+    assert!(trace.describe().contains("Hit object: 1"));
+}
+
+#[test]
+fn traced_pixel_outside_of_the_canvas_is_an_error() {
+    let config = RenderConfiguration {
+        canvas_width: 16,
+        canvas_height: 9,
+        ..Default::default()
+    };
+    let result = trace_pixel(samples::generate_test_scene(), config, 16, 0);
+    assert!(matches!(
+        result,
+        Err(RaytracerError::PixelOutOfCanvas(16, 0))
+    ));
+}
+
+#[test]
+fn high_quality_region_only_samples_inside() {
+    let region = Rect {
+        x: 4,
+        y: 2,
+        width: 5,
+        height: 3,
+    };
+    let config = RenderConfiguration {
+        canvas_width: 16,
+        canvas_height: 9,
+        render_strategy: Box::new(RandomAntiAliasingRenderStrategy {
+            rays_per_pixel: 8,
+            seed: Some(1),
+        }),
+        high_quality_region: Some(region),
+        ..Default::default()
+    };
+    let pixels = render_scene_data(samples::generate_test_scene(), config).unwrap();
+    for pixel in pixels {
+        let pixel = pixel.unwrap();
+        let is_inside = region.contains(pixel.x, pixel.y);
+        assert_eq!(pixel.metadata.sample_count, if is_inside { 8 } else { 1 });
+    }
+}
+
+#[test]
+fn shadow_catcher_only_shows_shadow_in_alpha() {
+    let scene = Scene {
+        camera: Box::new(OrthogonalCamera::new(
+            Vec3::new(0.0, 10.0, -10.0),
+            Vec3::ZERO,
+            16.0,
+            9.0,
+        )),
+        lights: vec![Box::new(LightPoint::new(Vec3::new(0.0, 20.0, 0.0)))],
+        objects: vec![
+            SceneObject::new(
+                Box::new(Sphere {
+                    center: Vec3::ZERO,
+                    radius: 1.0,
+                }),
+                Box::new(PlainColorTexture::default()),
+            ),
+            SceneObject::new(
+                Box::new(InfinitePlan::new(
+                    Vec3::new(0.0, -1.0, 0.0),
+                    Vec3::new(0.0, 1.0, 0.0),
+                )),
+                Box::new(PlainColorTexture::default()),
+            )
+            .with_shadow_catcher(true),
+        ],
+        config: Default::default(),
+    };
+    let config = RenderConfiguration {
+        canvas_width: 32,
+        canvas_height: 18,
+        ..Default::default()
+    };
+    let outputs = render_scene_multi(
+        scene,
+        config,
+        &[
+            OutputKind::Beauty,
+            OutputKind::ObjectIds,
+            OutputKind::ShadowAlpha,
+        ],
+    )
+    .unwrap();
+    let beauty = outputs.beauty.unwrap();
+    let object_ids = outputs.object_ids.unwrap();
+    let shadow_alpha = outputs.shadow_alpha.unwrap();
+    let mut catcher_alphas = Vec::new();
+    for index in 0..beauty.len() {
+        if object_ids[index] == Some(1) {
+            // The catcher itself is transparent in the beauty render
+            assert_eq!(beauty[index].red(), 0.0);
+            catcher_alphas.push(shadow_alpha[index]);
+        } else {
+            assert_eq!(shadow_alpha[index], 0.0);
+        }
+    }
+    let shadowed_count = catcher_alphas.iter().filter(|&&alpha| alpha > 0.5).count();
+    let transparent_count = catcher_alphas.iter().filter(|&&alpha| alpha == 0.0).count();
+    assert!(shadowed_count > 0);
+    assert!(transparent_count > 10 * shadowed_count);
+}
+
+#[test]
+fn accelerated_frames_match_rebuilt_scenes() {
+    let config = RenderConfiguration {
+        canvas_width: 32,
+        canvas_height: 18,
+        ..Default::default()
+    };
+    let frame_camera = |eye_x: f64| {
+        PerspectiveCamera::from_eye(
+            Vec3::new(eye_x, 10.0, -10.0),
+            Vec3::new(0.0, 0.0, 30.0),
+            32.0,
+            18.0,
+            std::f64::consts::PI / 8.0,
+        )
+    };
+    let accel = samples::generate_test_scene().build_accel();
+    for &eye_x in &[-5.0, 5.0] {
+        let accelerated =
+            render_accelerated(&accel, Some(Box::new(frame_camera(eye_x))), &config).unwrap();
+        let mut scene = samples::generate_test_scene();
+        scene.camera = Box::new(frame_camera(eye_x));
+        let rebuilt = render_scene(
+            scene,
+            RenderConfiguration {
+                canvas_width: 32,
+                canvas_height: 18,
+                ..Default::default()
+            },
+            false,
+        )
+        .unwrap();
+        for pixel in rebuilt {
+            let pixel = pixel.unwrap();
+            let color = &accelerated[(pixel.y * 32 + pixel.x) as usize];
+            assert_eq!(color.red(), pixel.color.red());
+            assert_eq!(color.green(), pixel.color.green());
+            assert_eq!(color.blue(), pixel.color.blue());
+        }
+    }
+}
+
+#[test]
+fn accelerated_frames_render_concurrently() {
+    let config = RenderConfiguration {
+        canvas_width: 32,
+        canvas_height: 18,
+        ..Default::default()
+    };
+    let frame_camera = |eye_x: f64| -> Box<dyn RayEmitter> {
+        Box::new(PerspectiveCamera::from_eye(
+            Vec3::new(eye_x, 10.0, -10.0),
+            Vec3::new(0.0, 0.0, 30.0),
+            32.0,
+            18.0,
+            std::f64::consts::PI / 8.0,
+        ))
+    };
+    let accel = samples::generate_test_scene().build_accel();
+    let eyes = [-5.0, 0.0, 5.0];
+    let concurrent: Vec<Vec<Color>> = std::thread::scope(|scope| {
+        let frames: Vec<_> = eyes
+            .iter()
+            .map(|&eye_x| {
+                let (accel, config) = (&accel, &config);
+                scope.spawn(move || {
+                    render_accelerated(accel, Some(frame_camera(eye_x)), config).unwrap()
+                })
+            })
+            .collect();
+        frames
+            .into_iter()
+            .map(|frame| frame.join().unwrap())
+            .collect()
+    });
+    for (&eye_x, frame) in eyes.iter().zip(concurrent.iter()) {
+        let expected = render_accelerated(&accel, Some(frame_camera(eye_x)), &config).unwrap();
+        for (color, expected_color) in frame.iter().zip(expected.iter()) {
+            assert_eq!(color.to_rgb8(), expected_color.to_rgb8());
+        }
+    }
+}
+
+#[test]
+fn accelerated_frames_keep_the_scene_camera_and_pixel_aspect_ratio() {
+    let config = || RenderConfiguration {
+        canvas_width: 32,
+        canvas_height: 18,
+        pixel_aspect_ratio: 2.0,
+        ..Default::default()
+    };
+    let accel = samples::generate_test_scene().build_accel();
+    let moved_camera = PerspectiveCamera::from_eye(
+        Vec3::new(5.0, 10.0, -10.0),
+        Vec3::new(0.0, 0.0, 30.0),
+        32.0,
+        18.0,
+        std::f64::consts::PI / 8.0,
+    );
+    render_accelerated(&accel, Some(Box::new(moved_camera)), &config()).unwrap();
+    // The camera of a frame is not kept for the next one
+    let accelerated = render_accelerated(&accel, None, &config()).unwrap();
+    let rebuilt = render_scene(samples::generate_test_scene(), config(), false).unwrap();
+    for pixel in rebuilt {
+        let pixel = pixel.unwrap();
+        let color = &accelerated[(pixel.y * 32 + pixel.x) as usize];
+        assert_eq!(color.to_rgb8(), pixel.color.to_rgb8());
+    }
+    let culling = RenderConfiguration {
+        small_object_culling: Some(1.0),
+        ..config()
+    };
+    assert!(matches!(
+        render_accelerated(&accel, None, &culling),
+        Err(RaytracerError::UnsupportedConfiguration(_))
+    ));
+}
+
+#[test]
+fn coarse_to_fine_order_starts_with_a_sparse_grid() {
+    let (width, height) = (37, 21);
+    let order = pixel_order(width, height, true);
+    let coarse_count = width.div_ceil(COARSE_TO_FINE_STEP) * height.div_ceil(COARSE_TO_FINE_STEP);
+    assert!(order[..coarse_count as usize]
+        .iter()
+        .all(|(x, y)| x % COARSE_TO_FINE_STEP == 0 && y % COARSE_TO_FINE_STEP == 0));
+    let mut sorted = order.clone();
+    sorted.sort_by_key(|&(x, y)| (y, x));
+    assert_eq!(sorted, pixel_order(width, height, false));
+}
+
+#[test]
+fn coarse_to_fine_parallel_render_computes_each_pixel_once() {
+    let config = RenderConfiguration {
+        canvas_width: 45,
+        canvas_height: 27,
+        coarse_to_fine: true,
+        ..Default::default()
+    };
+    let mut counts = vec![0; 45 * 27];
+    for pixel in render_scene(samples::generate_test_scene(), config, true).unwrap() {
+        let pixel = pixel.unwrap();
+        counts[(pixel.y * 45 + pixel.x) as usize] += 1;
+    }
+    assert!(counts.iter().all(|&count| count == 1));
+}
+
+#[test]
+fn clay_render_replaces_textures_and_reflections_but_keeps_shadows() {
+    let clay = Color::new(0.6, 0.6, 0.6);
+    let render_clay = |clay_render: Option<Color>| {
+        let mut scene = Scene {
+            camera: Box::new(OrthogonalCamera::new(
+                Vec3::new(0.0, 10.0, -10.0),
+                Vec3::ZERO,
+                16.0,
+                9.0,
+            )),
+            lights: vec![Box::new(DirectionalLight::new(Vec3::new(0.0, -1.0, 0.0)))],
+            objects: vec![
+                SceneObject::new(
+                    Box::new(Sphere {
+                        center: Vec3::ZERO,
+                        radius: 1.0,
+                    }),
+                    Box::new(PlainColorTexture::default()),
+                ),
+                SceneObject::new(
+                    Box::new(InfinitePlan::new(
+                        Vec3::new(0.0, -1.0, 0.0),
+                        Vec3::new(0.0, 1.0, 0.0),
+                    )),
+                    Box::new(CheckedPattern::default()),
+                ),
+            ],
+            config: Default::default(),
+        };
+        scene.objects[1].effects.mirror = Some(Mirror {
+            coeff: 1.0,
+            ..Default::default()
+        });
+        scene.config.ambient_light = None;
+        scene.config.clay_render = clay_render;
+        let config = RenderConfiguration {
+            canvas_width: 32,
+            canvas_height: 18,
+            ..Default::default()
+        };
+        let outputs =
+            render_scene_multi(scene, config, &[OutputKind::Beauty, OutputKind::ObjectIds])
+                .unwrap();
+        let object_ids = outputs.object_ids.unwrap();
+        outputs
+            .beauty
+            .unwrap()
+            .into_iter()
+            .zip(object_ids)
+            .filter(|(_, id)| *id == Some(1))
+            .map(|(color, _)| color.to_rgb8())
+            .collect::<Vec<_>>()
+    };
+    let plane_colors = render_clay(None);
+    assert!(plane_colors.iter().any(|rgb| rgb != &plane_colors[0]));
+    let plane_colors = render_clay(Some(clay.clone()));
+    let lit_count = plane_colors
+        .iter()
+        .filter(|&&rgb| rgb == clay.to_rgb8())
+        .count();
+    let shadowed_count = plane_colors
+        .iter()
+        .filter(|&&rgb| rgb == Color::BLACK.to_rgb8())
+        .count();
+    assert!(shadowed_count > 0);
+    assert!(lit_count > shadowed_count);
+    assert_eq!(lit_count + shadowed_count, plane_colors.len());
+}
+
+/// Strategy whose pixels take at least a known time to render
+struct SleepingRenderStrategy(Duration);
+
+impl AnyPixelRenderStrategy for SleepingRenderStrategy {
+    fn render_pixel(
+        &self,
+        _: &Scene,
+        _: &dyn RayEmitter,
+        _: f64,
+        _: f64,
+        _: f64,
+        _: f64,
+    ) -> Result<Radiance> {
+        std::thread::sleep(self.0);
+        Ok(Radiance::default())
+    }
+
+    fn render_pixel_passes(
+        &self,
+        _: &Scene,
+        _: &dyn RayEmitter,
+        _: f64,
+        _: f64,
+        _: f64,
+        _: f64,
+    ) -> Result<RenderPasses> {
+        std::thread::sleep(self.0);
+        Ok(RenderPasses::default())
+    }
+}
+
+#[test]
+fn render_time_estimate_is_extrapolated_from_the_sampled_pixels() {
+    let pixel_cost = Duration::from_millis(2);
+    let config = RenderConfiguration {
+        canvas_width: 10,
+        canvas_height: 10,
+        render_strategy: Box::new(SleepingRenderStrategy(pixel_cost)),
+        ..Default::default()
+    };
+    // 10 sampled pixels, extrapolated to the 100 pixels of the canvas
+    let estimate = estimate_render_time(samples::generate_test_scene(), config, 0.1).unwrap();
+    assert!(estimate >= 100 * pixel_cost);
+    // Only exceeded if sleeping is much longer than asked
+    assert!(estimate < 1000 * pixel_cost);
+}
+
+#[test]
+fn tiles_streamed_to_file_match_an_in_memory_render() {
+    let config = || RenderConfiguration {
+        canvas_width: 50,
+        canvas_height: 30,
+        ..Default::default()
+    };
+    let path = std::env::temp_dir().join(format!("raytracer-tiles-{}.ppm", std::process::id()));
+    render_tiles_to_file(
+        samples::generate_test_scene(),
+        config(),
+        16,
+        TileOrder::Hilbert,
+        &path,
+    )
+    .unwrap();
+    let file = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let header = b"P6\n50 30\n255\n";
+    assert_eq!(&file[..header.len()], header);
+    let expected: Vec<u8> = render_scene(samples::generate_test_scene(), config(), false)
+        .unwrap()
+        .flat_map(|pixel| {
+            TransferFunction::Srgb
+                .to_rgb8(&pixel.unwrap().color)
+                .to_vec()
+        })
+        .collect();
+    assert_eq!(&file[header.len()..], &expected[..]);
+}
+
+#[test]
+fn tiles_streamed_to_an_unwritable_path_give_a_write_error() {
+    let path = std::env::temp_dir()
+        .join(format!("raytracer-missing-{}", std::process::id()))
+        .join("tiles.ppm");
+    let result = render_tiles_to_file(
+        samples::generate_test_scene(),
+        Default::default(),
+        16,
+        TileOrder::Scanline,
+        &path,
+    );
+    match result {
+        Err(error @ RaytracerError::WriteError(_)) => {
+            assert!(error.to_string().starts_with("Error while writing file"))
+        }
+        _ => panic!("a write error was expected"),
+    }
+}
+
+#[test]
+fn tiles_cover_the_canvas_once_in_any_order() {
+    let (width, height) = (45, 27);
+    let reference = render_accelerated(
+        &samples::generate_test_scene().build_accel(),
+        None,
+        &RenderConfiguration {
+            canvas_width: width,
+            canvas_height: height,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    for &order in &[TileOrder::Scanline, TileOrder::Spiral, TileOrder::Hilbert] {
+        let config = RenderConfiguration {
+            canvas_width: width,
+            canvas_height: height,
+            ..Default::default()
+        };
+        let mut counts = vec![0; (width * height) as usize];
+        let tiles = render_tiles(samples::generate_test_scene(), config, 8, order).unwrap();
+        for tile in tiles {
+            let tile = tile.unwrap();
+            assert_eq!(tile.pixels.len(), (tile.width * tile.height) as usize);
+            for (index, color) in tile.pixels.iter().enumerate() {
+                let x = tile.x + index as u32 % tile.width;
+                let y = tile.y + index as u32 / tile.width;
+                let canvas_index = (x + y * width) as usize;
+                counts[canvas_index] += 1;
+                assert_eq!(color.to_rgb8(), reference[canvas_index].to_rgb8());
+            }
+        }
+        assert!(counts.iter().all(|&count| count == 1), "{:?}", order);
+    }
+}
+
+#[test]
+fn hilbert_and_spiral_orders_keep_tiles_close() {
+    let tiles = tile_order(4, 4, TileOrder::Hilbert);
+    assert_eq!(tiles.len(), 16);
+    for pair in tiles.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        let distance = (a.0 as i64 - b.0 as i64).abs() + (a.1 as i64 - b.1 as i64).abs();
+        assert_eq!(distance, 1, "{:?}", tiles);
+    }
+    let spiral = tile_order(5, 5, TileOrder::Spiral);
+    assert_eq!(spiral[0], (2, 2));
+    assert!(spiral[1..9]
+        .iter()
+        .all(|&(x, y)| x.max(y) <= 3 && x.min(y) >= 1));
+}
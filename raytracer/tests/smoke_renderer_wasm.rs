@@ -24,7 +24,7 @@ SOFTWARE.
 
 //! Test suite for the Web and headless browsers.
 
-#![cfg(target_arch = "wasm32")]
+#![cfg(all(target_arch = "wasm32", feature = "wasm"))]
 
 mod samples;
 